@@ -0,0 +1,335 @@
+//! Register map generator
+//!
+//! Reads a TOML or YAML device description and emits `register!` and
+//! `field_enum!` definitions for use by the driver (see `src/registers.rs`).
+//!
+//! See `device.toml` in the driver crate for an example description, and
+//! regenerate the driver registers with:
+//!
+//! ```text
+//! cargo run -p driver-codegen -- device.toml -o src/registers/device.rs
+//! ```
+
+use std::collections::HashSet;
+use std::fmt::{self, Write};
+use std::path::Path;
+
+use serde::Deserialize;
+
+
+/// Device description
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Device {
+    /// Device name
+    pub name: String,
+
+    /// Enums used for multi-bit register fields
+    #[serde(default)]
+    pub enums: Vec<Enum>,
+
+    /// Device registers
+    #[serde(default)]
+    pub registers: Vec<Register>,
+}
+
+/// Field enum description
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Enum {
+    /// Enum name
+    pub name: String,
+    /// Enum documentation
+    #[serde(default)]
+    pub doc: Option<String>,
+    /// Enum variants
+    pub variants: Vec<Variant>,
+}
+
+/// Field enum variant description
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Variant {
+    /// Variant name
+    pub name: String,
+    /// Variant value
+    pub value: u32,
+    /// Variant documentation
+    #[serde(default)]
+    pub doc: Option<String>,
+}
+
+/// Register access mode
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum Access {
+    /// Read only
+    #[serde(rename = "ro")]
+    ReadOnly,
+    /// Read / write
+    #[serde(rename = "rw")]
+    ReadWrite,
+    /// Write only
+    #[serde(rename = "wo")]
+    WriteOnly,
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Access::ReadOnly => write!(f, "ReadOnly"),
+            Access::ReadWrite => write!(f, "ReadWrite"),
+            Access::WriteOnly => write!(f, "WriteOnly"),
+        }
+    }
+}
+
+/// Register description
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Register {
+    /// Register (type) name
+    pub name: String,
+    /// Register documentation
+    #[serde(default)]
+    pub doc: Option<String>,
+    /// Register address
+    pub address: u8,
+    /// Register width in bits (8, 16, or 32)
+    pub width: u32,
+    /// Register access mode
+    pub access: Access,
+    /// Register value following device reset
+    #[serde(default)]
+    pub reset: u32,
+    /// Register fields
+    #[serde(default)]
+    pub fields: Vec<Field>,
+}
+
+/// Register field description
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Field {
+    /// Field (getter) name, the setter is `set_<name>`
+    pub name: String,
+    /// Field documentation
+    #[serde(default)]
+    pub doc: Option<String>,
+    /// Field bits as `[msb, lsb]`
+    pub bits: [u32; 2],
+    /// Field type, defaults to `bool` for single bit fields
+    /// or the register type for wider fields
+    #[serde(default, rename = "type")]
+    pub ty: Option<String>,
+}
+
+
+/// Codegen error type
+#[derive(Debug)]
+pub enum Error {
+    /// Failed to read input file
+    Io(std::io::Error),
+    /// Failed to parse TOML description
+    Toml(toml::de::Error),
+    /// Failed to parse YAML description
+    Yaml(serde_yaml::Error),
+    /// Unrecognised input file extension
+    UnknownFormat,
+    /// Description is invalid
+    Invalid(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "io error: {}", e),
+            Error::Toml(e) => write!(f, "toml error: {}", e),
+            Error::Yaml(e) => write!(f, "yaml error: {}", e),
+            Error::UnknownFormat => write!(f, "unknown format, expected .toml, .yaml, or .yml"),
+            Error::Invalid(e) => write!(f, "invalid description: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+
+impl Device {
+    /// Parse a TOML device description
+    pub fn from_toml(s: &str) -> Result<Self, Error> {
+        toml::from_str(s).map_err(Error::Toml)
+    }
+
+    /// Parse a YAML device description
+    pub fn from_yaml(s: &str) -> Result<Self, Error> {
+        serde_yaml::from_str(s).map_err(Error::Yaml)
+    }
+
+    /// Load a device description, using the file extension to select the format
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let s = std::fs::read_to_string(path)?;
+
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => Self::from_toml(&s),
+            Some("yaml") | Some("yml") => Self::from_yaml(&s),
+            _ => Err(Error::UnknownFormat),
+        }
+    }
+
+    /// Check the description is consistent
+    ///
+    /// (the `register!` macro also checks field layout at compile time,
+    /// this just provides earlier and friendlier errors)
+    pub fn validate(&self) -> Result<(), Error> {
+        let enums: HashSet<&str> = self.enums.iter().map(|e| e.name.as_str()).collect();
+        let mut addresses = HashSet::new();
+
+        // Enums and registers share the type namespace
+        let mut names = HashSet::new();
+        for name in self.enums.iter().map(|e| &e.name).chain(self.registers.iter().map(|r| &r.name)) {
+            if !names.insert(name.as_str()) {
+                return Err(Error::Invalid(format!("type name {} is already in use", name)));
+            }
+        }
+
+        for e in &self.enums {
+            if e.variants.is_empty() {
+                return Err(Error::Invalid(format!("enum {} has no variants", e.name)));
+            }
+        }
+
+        for r in &self.registers {
+            if ![8, 16, 32].contains(&r.width) {
+                return Err(Error::Invalid(format!("register {} width must be 8, 16, or 32 bits", r.name)));
+            }
+            if r.width < 32 && r.reset >> r.width != 0 {
+                return Err(Error::Invalid(format!("register {} reset value 0x{:x} exceeds {} bits", r.name, r.reset, r.width)));
+            }
+            if !addresses.insert(r.address) {
+                return Err(Error::Invalid(format!("register {} address 0x{:02x} is already in use", r.name, r.address)));
+            }
+
+            let mut used = 0u64;
+            for f in &r.fields {
+                let [msb, lsb] = f.bits;
+                if msb < lsb || msb >= r.width {
+                    return Err(Error::Invalid(format!("field {}.{} bits [{}:{}] are invalid", r.name, f.name, msb, lsb)));
+                }
+
+                let mask = ((1u64 << (msb - lsb + 1)) - 1) << lsb;
+                if used & mask != 0 {
+                    return Err(Error::Invalid(format!("field {}.{} overlaps another field", r.name, f.name)));
+                }
+                used |= mask;
+
+                match f.ty.as_deref() {
+                    None | Some("bool") | Some("u8") | Some("u16") | Some("u32") => (),
+                    Some(t) if enums.contains(t) => {
+                        let e = self.enums.iter().find(|e| e.name == t).unwrap();
+                        let width = msb - lsb + 1;
+
+                        if let Some(v) = e.variants.iter().find(|v| v.value >> width != 0) {
+                            return Err(Error::Invalid(format!(
+                                "field {}.{} is {} bits, too narrow for {}::{} = {}",
+                                r.name, f.name, width, e.name, v.name, v.value,
+                            )));
+                        }
+                    }
+                    Some(t) => return Err(Error::Invalid(format!("field {}.{} has unknown type {}", r.name, f.name, t))),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Generate register definitions
+    ///
+    /// `source` is recorded in the file header to show where the
+    /// output came from.
+    pub fn generate(&self, source: &str) -> Result<String, Error> {
+        self.validate()?;
+
+        let mut s = String::new();
+
+        writeln!(s, "//! {} register definitions", self.name).unwrap();
+        writeln!(s, "//!").unwrap();
+        writeln!(s, "//! Generated by driver-codegen from `{}`, do not edit", source).unwrap();
+
+        for e in &self.enums {
+            writeln!(s).unwrap();
+            write_enum(&mut s, e);
+        }
+
+        for r in &self.registers {
+            writeln!(s).unwrap();
+            write_register(&mut s, r);
+        }
+
+        Ok(s)
+    }
+}
+
+/// Write doc comment lines at the provided indent
+fn write_doc(s: &mut String, indent: &str, doc: &Option<String>) {
+    if let Some(doc) = doc {
+        for l in doc.trim().lines() {
+            match l.trim_end() {
+                "" => writeln!(s, "{}///", indent).unwrap(),
+                l => writeln!(s, "{}/// {}", indent, l).unwrap(),
+            }
+        }
+    }
+}
+
+fn write_enum(s: &mut String, e: &Enum) {
+    writeln!(s, "field_enum! {{").unwrap();
+    write_doc(s, "    ", &e.doc);
+    writeln!(s, "    pub enum {} {{", e.name).unwrap();
+
+    for v in &e.variants {
+        write_doc(s, "        ", &v.doc);
+        writeln!(s, "        {} = {},", v.name, v.value).unwrap();
+    }
+
+    writeln!(s, "    }}").unwrap();
+    writeln!(s, "}}").unwrap();
+}
+
+fn write_register(s: &mut String, r: &Register) {
+    let raw = format!("u{}", r.width);
+    let digits = r.width as usize / 4;
+
+    writeln!(s, "register! {{").unwrap();
+    write_doc(s, "    ", &r.doc);
+    writeln!(s, "    pub struct {}: {} {{", r.name, raw).unwrap();
+    writeln!(s, "        const ADDRESS = 0x{:02x};", r.address).unwrap();
+    writeln!(s, "        const ACCESS = {};", r.access).unwrap();
+    writeln!(s, "        const RESET = 0x{:0w$x};", r.reset, w = digits).unwrap();
+
+    if !r.fields.is_empty() {
+        writeln!(s).unwrap();
+    }
+
+    for f in &r.fields {
+        let [msb, lsb] = f.bits;
+        let ty = match &f.ty {
+            Some(t) => t.clone(),
+            None if msb == lsb => "bool".to_string(),
+            None => raw.clone(),
+        };
+
+        write_doc(s, "        ", &f.doc);
+        writeln!(s, "        {}, set_{}: [{}:{}] as {};", f.name, f.name, msb, lsb, ty).unwrap();
+    }
+
+    writeln!(s, "    }}").unwrap();
+    writeln!(s, "}}").unwrap();
+}
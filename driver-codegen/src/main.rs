@@ -0,0 +1,45 @@
+//! Register map generator CLI
+//!
+//! Usage: `driver-codegen <device.toml|device.yaml> [-o <output.rs>]`,
+//! writing to stdout if no output file is specified.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use driver_codegen::Device;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let (input, output) = match args.as_slice() {
+        [input] => (input, None),
+        [input, o, output] if o == "-o" => (input, Some(output)),
+        _ => {
+            eprintln!("usage: driver-codegen <device.toml|device.yaml> [-o <output.rs>]");
+            process::exit(2);
+        }
+    };
+
+    // Record only the file name so output doesn't depend on where we're run from
+    let source = Path::new(input).file_name().and_then(|n| n.to_str()).unwrap_or(input);
+
+    let generated = match Device::load(input).and_then(|d| d.generate(source)) {
+        Ok(g) => g,
+        Err(e) => {
+            eprintln!("error generating registers from {}: {}", input, e);
+            process::exit(1);
+        }
+    };
+
+    match output {
+        Some(o) => {
+            if let Err(e) = fs::write(o, generated) {
+                eprintln!("error writing {}: {}", o, e);
+                process::exit(1);
+            }
+        }
+        None => print!("{}", generated),
+    }
+}
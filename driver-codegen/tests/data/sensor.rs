@@ -0,0 +1,62 @@
+//! Sensor register definitions
+//!
+//! Generated by driver-codegen from `sensor.toml`, do not edit
+
+field_enum! {
+    /// Output data rate
+    ///
+    /// Higher rates increase power consumption
+    pub enum Rate {
+        Hz1 = 0,
+        /// 10 Hz
+        Hz10 = 1,
+        /// 100 Hz
+        Hz100 = 3,
+    }
+}
+
+register! {
+    /// Device identifier
+    pub struct WhoAmI: u8 {
+        const ADDRESS = 0x0f;
+        const ACCESS = ReadOnly;
+        const RESET = 0x6a;
+    }
+}
+
+register! {
+    /// Sensor configuration
+    pub struct Config: u16 {
+        const ADDRESS = 0x10;
+        const ACCESS = ReadWrite;
+        const RESET = 0x0004;
+
+        enable, set_enable: [0:0] as bool;
+        /// Output data rate
+        rate, set_rate: [3:2] as Rate;
+        /// Amplifier gain
+        gain, set_gain: [15:8] as u8;
+    }
+}
+
+register! {
+    pub struct Sample: u32 {
+        const ADDRESS = 0x20;
+        const ACCESS = ReadOnly;
+        const RESET = 0x00000000;
+
+        /// Sample value
+        value, set_value: [23:0] as u32;
+        /// Sample is valid
+        valid, set_valid: [31:31] as bool;
+    }
+}
+
+register! {
+    /// Soft reset
+    pub struct Reset: u8 {
+        const ADDRESS = 0x7f;
+        const ACCESS = WriteOnly;
+        const RESET = 0x00;
+    }
+}
@@ -0,0 +1,193 @@
+//! Golden-file tests for generated register definitions
+//!
+//! Set `UPDATE_GOLDEN=1` to rewrite the expected output after an
+//! intentional change to the generator.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use driver_codegen::{Device, Error};
+
+fn data(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("data").join(name)
+}
+
+fn check_golden(generated: &str, golden: &Path) {
+    if env::var("UPDATE_GOLDEN").is_ok() {
+        fs::write(golden, generated).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(golden).unwrap();
+    assert_eq!(generated, expected, "generated output does not match {}", golden.display());
+}
+
+#[test]
+fn toml_matches_golden() {
+    let d = Device::load(data("sensor.toml")).unwrap();
+    let g = d.generate("sensor.toml").unwrap();
+
+    check_golden(&g, &data("sensor.rs"));
+}
+
+#[test]
+fn yaml_matches_golden() {
+    let d = Device::load(data("sensor.yaml")).unwrap();
+    let g = d.generate("sensor.toml").unwrap();
+
+    check_golden(&g, &data("sensor.rs"));
+}
+
+#[test]
+fn toml_and_yaml_agree() {
+    let t = Device::load(data("sensor.toml")).unwrap();
+    let y = Device::load(data("sensor.yaml")).unwrap();
+
+    assert_eq!(t, y);
+}
+
+#[test]
+fn driver_registers_up_to_date() {
+    // The driver's checked-in registers must match its device description
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+
+    let d = Device::load(root.join("device.toml")).unwrap();
+    let g = d.generate("device.toml").unwrap();
+
+    check_golden(&g, &root.join("src").join("registers").join("device.rs"));
+}
+
+#[test]
+fn rejects_overlapping_fields() {
+    let d = Device::from_toml(r#"
+        name = "Bad"
+
+        [[registers]]
+        name = "Reg"
+        address = 0x00
+        width = 8
+        access = "rw"
+        fields = [
+            { name = "a", bits = [3, 0] },
+            { name = "b", bits = [4, 3] },
+        ]
+    "#).unwrap();
+
+    assert!(matches!(d.generate("bad.toml"), Err(Error::Invalid(_))));
+}
+
+#[test]
+fn rejects_fields_exceeding_width() {
+    let d = Device::from_toml(r#"
+        name = "Bad"
+
+        [[registers]]
+        name = "Reg"
+        address = 0x00
+        width = 8
+        access = "rw"
+        fields = [
+            { name = "a", bits = [8, 0] },
+        ]
+    "#).unwrap();
+
+    assert!(matches!(d.generate("bad.toml"), Err(Error::Invalid(_))));
+}
+
+#[test]
+fn rejects_unknown_field_type() {
+    let d = Device::from_toml(r#"
+        name = "Bad"
+
+        [[registers]]
+        name = "Reg"
+        address = 0x00
+        width = 8
+        access = "rw"
+        fields = [
+            { name = "a", bits = [1, 0], type = "Missing" },
+        ]
+    "#).unwrap();
+
+    assert!(matches!(d.generate("bad.toml"), Err(Error::Invalid(_))));
+}
+
+#[test]
+fn rejects_reset_value_exceeding_width() {
+    let d = Device::from_toml(r#"
+        name = "Bad"
+
+        [[registers]]
+        name = "Reg"
+        address = 0x00
+        width = 8
+        access = "rw"
+        reset = 0x100
+    "#).unwrap();
+
+    assert!(matches!(d.generate("bad.toml"), Err(Error::Invalid(e)) if e.contains("reset value")));
+}
+
+#[test]
+fn rejects_enum_values_exceeding_field() {
+    let d = Device::from_toml(r#"
+        name = "Bad"
+
+        [[enums]]
+        name = "Mode"
+        variants = [
+            { name = "A", value = 0 },
+            { name = "B", value = 4 },
+        ]
+
+        [[registers]]
+        name = "Reg"
+        address = 0x00
+        width = 8
+        access = "rw"
+        fields = [
+            { name = "mode", bits = [1, 0], type = "Mode" },
+        ]
+    "#).unwrap();
+
+    assert!(matches!(d.generate("bad.toml"), Err(Error::Invalid(e)) if e.contains("Mode::B")));
+}
+
+#[test]
+fn rejects_duplicate_names() {
+    let d = Device::from_toml(r#"
+        name = "Bad"
+
+        [[registers]]
+        name = "Reg"
+        address = 0x00
+        width = 8
+        access = "rw"
+
+        [[registers]]
+        name = "Reg"
+        address = 0x01
+        width = 8
+        access = "rw"
+    "#).unwrap();
+
+    assert!(matches!(d.generate("bad.toml"), Err(Error::Invalid(e)) if e.contains("Reg")));
+
+    // Enums share the namespace with registers
+    let d = Device::from_toml(r#"
+        name = "Bad"
+
+        [[enums]]
+        name = "Reg"
+        variants = [{ name = "A", value = 0 }]
+
+        [[registers]]
+        name = "Reg"
+        address = 0x00
+        width = 8
+        access = "rw"
+    "#).unwrap();
+
+    assert!(matches!(d.generate("bad.toml"), Err(Error::Invalid(_))));
+}
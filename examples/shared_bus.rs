@@ -0,0 +1,39 @@
+//! Two drivers sharing one I2C bus, run against the simulator
+//!
+//! Run with `cargo run --example shared_bus`
+
+use core::cell::RefCell;
+
+use driver_example::registers::{Control, Mode};
+use driver_example::sim::{SimBus, SimulatedDevice};
+use driver_example::{Config, ExampleDriver, I2cInterface};
+
+fn main() {
+    // Two devices on one bus, strapped to different addresses
+    let dev_a = SimulatedDevice::new().with_address(0x01);
+    let dev_b = SimulatedDevice::new().with_address(0x02);
+
+    // On hardware this would be your HAL I2C peripheral
+    let bus = RefCell::new(SimBus::new(&[&dev_a, &dev_b]));
+
+    // Each driver gets a proxy to the shared bus
+    let iface = I2cInterface::new_shared(&bus, 0x01);
+    let a = ExampleDriver::new(Config::default(), iface, dev_a.busy(), dev_a.reset(), dev_a.delay()).unwrap();
+
+    let iface = I2cInterface::new_shared(&bus, 0x02);
+    let mut b = ExampleDriver::new(Config::default(), iface, dev_b.busy(), dev_b.reset(), dev_b.delay()).unwrap();
+
+    // Put one device to sleep, leaving the other running
+    let a = a.sleep().unwrap();
+    let b_mode = b.read_reg::<Control>().unwrap().mode();
+
+    println!("device a: {:?} (asleep)", a.device_info());
+    println!("device b: {:?} (mode {:?})", b.device_info(), b_mode);
+
+    // Then wake it again
+    let mut a = a.wake().unwrap();
+    let a_mode = a.read_reg::<Control>().unwrap().mode();
+    assert_eq!(a_mode, Some(Mode::Normal));
+
+    println!("device a: {:?} (mode {:?})", a.device_info(), a_mode);
+}
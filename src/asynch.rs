@@ -0,0 +1,822 @@
+//! Async driver (requires the `async` feature)
+//!
+//! This mirrors the blocking `ExampleDriver` over embedded-hal-async traits,
+//! so bus operations and delays yield to the executor rather than blocking.
+//!
+//! The async interfaces and driver have the same shape as the blocking ones,
+//! but the device sequencing is shared: configuration and identification
+//! (`init_control`, `DeviceInfo`), sleep / wake control values, FIFO setup
+//! and status decoding, command and checksum framing, and the retry / backoff
+//! decision all live alongside the blocking driver. This module only makes
+//! the awaited bus, pin and delay calls between them.
+//!
+//! The driver carries the same `crate::state` lifecycle, so `sleep()`, `wake()`,
+//! `reset()` and `configure()` consume the driver and return it in the new state.
+
+use core::future::{poll_fn, Future};
+use core::marker::PhantomData;
+use core::pin::pin;
+use core::task::Poll;
+
+use embedded_hal_async::i2c::{self, I2c, SevenBitAddress, TenBitAddress};
+use embedded_hal_async::spi::{self, SpiDevice};
+
+use crate::config::ConfigError;
+use crate::checksum;
+use crate::interface::{i2c_checksum_header, register_address, spi_checksum_header, Address, AddressMode, BusErrorKind, SPI_READ_FLAG};
+use crate::pins::{OptionalOutputPin, OptionalWait};
+use crate::recovery::{BusRecovery, NoRecovery, RecoveryTrigger};
+use crate::retry::{Retry, RetryStats};
+use crate::timer::{AsyncTimer, Timeout};
+use crate::events::EventQueue;
+use crate::fifo::{FifoConfig, FifoRead, STATUS_BYTES};
+use crate::protocol::{self, CommandFrame, Response, MAX_HEADER};
+use crate::registers::{self, ChipId, Control, FifoControl, FifoData, FifoStatus, IrqEnable, IrqStatus, Mode, Readable, Register, Revision, Status, Writable};
+use crate::state::{self, Awake, Ready, Sleeping, Unconfigured};
+use crate::{init_control, soft_reset_command, Config, DeviceInfo, Error, Operation, ReadyFallback, CHIP_ID};
+
+
+/// Async interface trait abstracts over the bus used to talk to the device
+#[allow(async_fn_in_trait)]
+pub trait Interface {
+    /// Interface error type
+    type Error;
+
+    /// Read `buff.len()` bytes starting at register `reg`
+    async fn read_register(&mut self, reg: u8, buff: &mut [u8]) -> Result<(), Self::Error>;
+
+    /// Write `data` starting at register `reg`
+    async fn write_register(&mut self, reg: u8, data: &[u8]) -> Result<(), Self::Error>;
+
+    /// Read from a register without auto-incrementing, see `crate::Interface::read_register_fixed`
+    async fn read_register_fixed(&mut self, reg: u8, buff: &mut [u8]) -> Result<(), Self::Error> {
+        self.read_register(reg, buff).await
+    }
+
+    /// Write `data` then read `buff.len()` bytes in a single transaction
+    async fn transfer(&mut self, data: &[u8], buff: &mut [u8]) -> Result<(), Self::Error>;
+
+    /// Classify an interface error, errors are treated as `Other` by default
+    fn error_kind(_error: &Self::Error) -> BusErrorKind {
+        BusErrorKind::Other
+    }
+
+    /// Register address byte sent, see `crate::Interface::register_address`
+    fn register_address(&self, reg: u8, _len: usize) -> u8 {
+        reg
+    }
+
+    /// Frame header covered by checksums, see `crate::Interface::checksum_header`
+    fn checksum_header(&self, reg: u8, _read: bool, buff: &mut [u8; checksum::MAX_HEADER]) -> usize {
+        buff[0] = reg;
+        1
+    }
+}
+
+
+/// Async version of `crate::interface::probe`
+pub async fn probe<Bus, A>(i2c: &mut Bus, candidates: &[Address]) -> Option<Address>
+where
+    Bus: I2c<A>,
+    A: AddressMode,
+{
+    for c in candidates.iter() {
+        let a = match A::from_address(*c) {
+            Some(a) => a,
+            None => continue,
+        };
+
+        let mut id = [0u8; 1];
+        if i2c.write_read(a, &[ChipId::ADDRESS], &mut id).await.is_ok() && id[0] == CHIP_ID {
+            return Some(*c);
+        }
+    }
+
+    None
+}
+
+
+/// Async I2C interface to the device, see `crate::I2cInterface`
+pub struct I2cInterface<Bus, Recovery = NoRecovery, A = SevenBitAddress> {
+    /// I2C device
+    i2c: Bus,
+
+    /// Device I2C address
+    address: A,
+
+    /// Bus recovery hook
+    recovery: Recovery,
+
+    /// Bus recovery trigger
+    trigger: RecoveryTrigger,
+
+    /// Register address auto-increment flag, set for multi-byte accesses
+    auto_increment: u8,
+}
+
+impl<Bus> I2cInterface<Bus, NoRecovery, SevenBitAddress>
+where
+    Bus: I2c<SevenBitAddress>,
+{
+    /// Create a new I2C interface with the provided 7-bit device address
+    pub fn new(i2c: Bus, address: SevenBitAddress) -> Self {
+        Self { i2c, address, recovery: NoRecovery, trigger: RecoveryTrigger::new(0), auto_increment: 0 }
+    }
+
+    /// Create a new I2C interface using the 7-bit `Config::address`,
+    /// returning `ConfigError::Address` if it is invalid or a 10-bit address
+    pub fn from_config(i2c: Bus, config: &Config) -> Result<Self, ConfigError> {
+        Self::with_config_address(i2c, config)
+    }
+}
+
+impl<Bus> I2cInterface<Bus, NoRecovery, TenBitAddress>
+where
+    Bus: I2c<TenBitAddress>,
+{
+    /// Create a new I2C interface with the provided 10-bit device address
+    pub fn new_ten_bit(i2c: Bus, address: TenBitAddress) -> Self {
+        Self { i2c, address, recovery: NoRecovery, trigger: RecoveryTrigger::new(0), auto_increment: 0 }
+    }
+
+    /// Create a new I2C interface using the 10-bit `Config::address`,
+    /// returning `ConfigError::Address` if it is invalid or a 7-bit address
+    pub fn from_config_ten_bit(i2c: Bus, config: &Config) -> Result<Self, ConfigError> {
+        Self::with_config_address(i2c, config)
+    }
+}
+
+impl<Bus, A> I2cInterface<Bus, NoRecovery, A>
+where
+    Bus: I2c<A>,
+    A: AddressMode,
+{
+    /// Create an interface using the `Config::address`, if it matches the address mode `A`
+    fn with_config_address(i2c: Bus, config: &Config) -> Result<Self, ConfigError> {
+        let address = A::from_address(config.address)
+            .filter(|_| config.address.is_valid())
+            .ok_or(ConfigError::Address)?;
+
+        Ok(Self { i2c, address, recovery: NoRecovery, trigger: RecoveryTrigger::new(0), auto_increment: 0 })
+    }
+
+    /// Attach a bus recovery hook, see `crate::I2cInterface::with_recovery`
+    pub fn with_recovery<Recovery>(self, recovery: Recovery, after_errors: u32) -> I2cInterface<Bus, Recovery, A>
+    where
+        Recovery: BusRecovery<Bus>,
+    {
+        I2cInterface {
+            i2c: self.i2c,
+            address: self.address,
+            recovery,
+            trigger: RecoveryTrigger::new(after_errors),
+            auto_increment: self.auto_increment,
+        }
+    }
+}
+
+impl<Bus, Recovery, A> I2cInterface<Bus, Recovery, A>
+where
+    Bus: I2c<A>,
+    Recovery: BusRecovery<Bus>,
+    A: AddressMode,
+{
+    /// Set the register address auto-increment flag, see `crate::I2cInterface::with_auto_increment`
+    pub fn with_auto_increment(self, flag: u8) -> Self {
+        Self { auto_increment: flag, ..self }
+    }
+
+    /// Fetch the device address
+    pub fn address(&self) -> Address {
+        self.address.into_address()
+    }
+
+    /// Fetch the number of bus recoveries run
+    pub fn recoveries(&self) -> u32 {
+        self.trigger.recoveries
+    }
+
+    /// Track the transaction result, running bus recovery if required
+    fn check<T>(&mut self, r: Result<T, Bus::Error>) -> Result<T, Bus::Error> {
+        if self.trigger.record(r.is_ok()) {
+            let _ = self.recovery.recover(&mut self.i2c);
+        }
+
+        r
+    }
+}
+
+impl<Bus, Recovery, A> Interface for I2cInterface<Bus, Recovery, A>
+where
+    Bus: I2c<A>,
+    Recovery: BusRecovery<Bus>,
+    A: AddressMode,
+{
+    type Error = Bus::Error;
+
+    async fn read_register(&mut self, reg: u8, buff: &mut [u8]) -> Result<(), Self::Error> {
+        let reg = self.register_address(reg, buff.len());
+        self.read_register_fixed(reg, buff).await
+    }
+
+    async fn read_register_fixed(&mut self, reg: u8, buff: &mut [u8]) -> Result<(), Self::Error> {
+        let r = self.i2c.write_read(self.address, &[reg], buff).await;
+        self.check(r)
+    }
+
+    async fn write_register(&mut self, reg: u8, data: &[u8]) -> Result<(), Self::Error> {
+        let reg = self.register_address(reg, data.len());
+
+        let r = self.i2c.transaction(self.address, &mut [
+            i2c::Operation::Write(&[reg]),
+            i2c::Operation::Write(data),
+        ]).await;
+        self.check(r)
+    }
+
+    async fn transfer(&mut self, data: &[u8], buff: &mut [u8]) -> Result<(), Self::Error> {
+        let r = self.i2c.write_read(self.address, data, buff).await;
+        self.check(r)
+    }
+
+    fn error_kind(error: &Self::Error) -> BusErrorKind {
+        i2c::Error::kind(error).into()
+    }
+
+    fn register_address(&self, reg: u8, len: usize) -> u8 {
+        register_address(reg, len, self.auto_increment)
+    }
+
+    fn checksum_header(&self, reg: u8, read: bool, buff: &mut [u8; checksum::MAX_HEADER]) -> usize {
+        i2c_checksum_header(self.address.into_address(), reg, read, buff)
+    }
+}
+
+
+/// Async SPI interface to the device
+pub struct SpiInterface<Spi> {
+    /// SPI device
+    spi: Spi,
+
+    /// Register address auto-increment flag, set for multi-byte accesses
+    auto_increment: u8,
+}
+
+impl<Spi> SpiInterface<Spi>
+where
+    Spi: SpiDevice,
+{
+    /// Create a new SPI interface
+    pub fn new(spi: Spi) -> Self {
+        Self { spi, auto_increment: 0 }
+    }
+
+    /// Set the register address auto-increment flag, see `crate::SpiInterface::with_auto_increment`
+    pub fn with_auto_increment(self, flag: u8) -> Self {
+        Self { auto_increment: flag, ..self }
+    }
+}
+
+impl<Spi> Interface for SpiInterface<Spi>
+where
+    Spi: SpiDevice,
+{
+    type Error = Spi::Error;
+
+    async fn read_register(&mut self, reg: u8, buff: &mut [u8]) -> Result<(), Self::Error> {
+        let reg = self.register_address(reg, buff.len());
+        self.read_register_fixed(reg, buff).await
+    }
+
+    async fn read_register_fixed(&mut self, reg: u8, buff: &mut [u8]) -> Result<(), Self::Error> {
+        self.spi.transaction(&mut [
+            spi::Operation::Write(&[reg | SPI_READ_FLAG]),
+            spi::Operation::Read(buff),
+        ]).await
+    }
+
+    async fn write_register(&mut self, reg: u8, data: &[u8]) -> Result<(), Self::Error> {
+        let reg = self.register_address(reg, data.len());
+
+        self.spi.transaction(&mut [
+            spi::Operation::Write(&[reg & !SPI_READ_FLAG]),
+            spi::Operation::Write(data),
+        ]).await
+    }
+
+    async fn transfer(&mut self, data: &[u8], buff: &mut [u8]) -> Result<(), Self::Error> {
+        self.spi.transaction(&mut [
+            spi::Operation::Write(data),
+            spi::Operation::Read(buff),
+        ]).await
+    }
+
+    fn error_kind(error: &Self::Error) -> BusErrorKind {
+        spi::Error::kind(error).into()
+    }
+
+    fn register_address(&self, reg: u8, len: usize) -> u8 {
+        register_address(reg, len, self.auto_increment)
+    }
+
+    fn checksum_header(&self, reg: u8, read: bool, buff: &mut [u8; checksum::MAX_HEADER]) -> usize {
+        spi_checksum_header(reg, read, buff)
+    }
+}
+
+/// Async interface supporting command framing, see `crate::protocol::CommandInterface`
+#[allow(async_fn_in_trait)]
+pub trait CommandInterface: Interface {
+    /// Send `header` and `payload` then read `response` in a single
+    /// transaction, returning the status byte clocked back with the opcode
+    async fn command(&mut self, header: &[u8], payload: &[u8], response: &mut [u8]) -> Result<u8, Self::Error>;
+}
+
+impl<Spi> CommandInterface for SpiInterface<Spi>
+where
+    Spi: SpiDevice,
+{
+    async fn command(&mut self, header: &[u8], payload: &[u8], response: &mut [u8]) -> Result<u8, Self::Error> {
+        let mut buff = [0u8; MAX_HEADER];
+        let buff = &mut buff[..header.len()];
+        buff.copy_from_slice(header);
+
+        self.spi.transaction(&mut [
+            spi::Operation::TransferInPlace(buff),
+            spi::Operation::Write(payload),
+            spi::Operation::Read(response),
+        ]).await?;
+
+        Ok(buff[0])
+    }
+}
+
+
+/// Async driver object, see `crate::ExampleDriver` for details
+///
+/// This carries the same `crate::state` type parameter as the blocking driver
+pub struct ExampleDriver<Iface, BusyPin, ResetPin, Delay, State = Ready> {
+    /// Device configuration
+    config: Config,
+
+    /// Device interface
+    iface: Iface,
+
+    /// Busy input pin
+    busy: BusyPin,
+
+    /// Reset output pin
+    reset: ResetPin,
+
+    /// Delay implementation
+    delay: Delay,
+
+    /// Bus retry counters
+    stats: RetryStats,
+
+    /// Device identification, read by `configure()`
+    info: DeviceInfo,
+
+    /// Device lifecycle state
+    _state: PhantomData<State>,
+}
+
+/// Result of a state transition, returning the driver in the `Next` state
+pub type Transition<Iface, BusyPin, ResetPin, Delay, PinError, Next> =
+    Result<ExampleDriver<Iface, BusyPin, ResetPin, Delay, Next>, Error<<Iface as Interface>::Error, PinError>>;
+
+impl<Iface, BusyPin, ResetPin, PinError, Delay> ExampleDriver<Iface, BusyPin, ResetPin, Delay, Ready>
+where
+    Iface: Interface,
+    BusyPin: OptionalWait<Error = PinError>,
+    ResetPin: OptionalOutputPin<Error = PinError>,
+    Delay: AsyncTimer,
+{
+    /// Create and initialise a new driver
+    pub async fn new(config: Config, iface: Iface, busy: BusyPin, reset: ResetPin, delay: Delay) -> Result<Self, Error<Iface::Error, PinError>> {
+        // Create the driver object
+        let s = ExampleDriver::new_unconfigured(config, iface, busy, reset, delay)?;
+
+        // Reset, identify and configure the device
+        s.configure().await
+    }
+
+    /// Put the device into low power sleep
+    pub async fn sleep(mut self) -> Transition<Iface, BusyPin, ResetPin, Delay, PinError, Sleeping> {
+        let c = self.read_reg_unchecked::<Control>().await?;
+        self.write_reg_unchecked(state::mode_control(c, Mode::Sleep)).await?;
+
+        Ok(self.into_state())
+    }
+
+    /// Reset the device, returning it to the unconfigured state
+    pub async fn reset(mut self) -> Transition<Iface, BusyPin, ResetPin, Delay, PinError, Unconfigured> {
+        self.reset_device().await?;
+
+        Ok(self.into_state())
+    }
+}
+
+impl<Iface, BusyPin, ResetPin, PinError, Delay> ExampleDriver<Iface, BusyPin, ResetPin, Delay, Unconfigured>
+where
+    Iface: Interface,
+    BusyPin: OptionalWait<Error = PinError>,
+    ResetPin: OptionalOutputPin<Error = PinError>,
+    Delay: AsyncTimer,
+{
+    /// Create a driver without touching the device, see `crate::ExampleDriver::new_unconfigured`
+    pub fn new_unconfigured(config: Config, iface: Iface, busy: BusyPin, reset: ResetPin, delay: Delay) -> Result<Self, Error<Iface::Error, PinError>> {
+        config.validate().map_err(Error::Config)?;
+
+        Ok(Self {
+            config, iface, busy, reset, delay,
+            stats: RetryStats::default(),
+            info: DeviceInfo::default(),
+            _state: PhantomData,
+        })
+    }
+
+    /// Reset and configure the device, see `crate::ExampleDriver::configure`
+    pub async fn configure(mut self) -> Transition<Iface, BusyPin, ResetPin, Delay, PinError, Ready> {
+        // (example) Reset device
+        self.reset_device().await?;
+
+        // Check we're actually talking to the right device
+        self.info = DeviceInfo::identify(self.read_reg::<ChipId>().await?)?;
+        self.info.revision = self.read_reg::<Revision>().await?.0;
+
+        // (example) Configure the device
+        self.write_reg(init_control()).await?;
+
+        Ok(self.into_state())
+    }
+}
+
+impl<Iface, BusyPin, ResetPin, PinError, Delay> ExampleDriver<Iface, BusyPin, ResetPin, Delay, Sleeping>
+where
+    Iface: Interface,
+    BusyPin: OptionalWait<Error = PinError>,
+    ResetPin: OptionalOutputPin<Error = PinError>,
+    Delay: AsyncTimer,
+{
+    /// Wake the device from sleep
+    pub async fn wake(mut self) -> Transition<Iface, BusyPin, ResetPin, Delay, PinError, Ready> {
+        let c = self.read_reg_unchecked::<Control>().await?;
+        self.write_reg_unchecked(state::mode_control(c, Mode::Normal)).await?;
+
+        self.wait_busy().await?;
+
+        Ok(self.into_state())
+    }
+}
+
+impl<Iface, BusyPin, ResetPin, PinError, Delay, State> ExampleDriver<Iface, BusyPin, ResetPin, Delay, State>
+where
+    Iface: Interface,
+    BusyPin: OptionalWait<Error = PinError>,
+    ResetPin: OptionalOutputPin<Error = PinError>,
+    Delay: AsyncTimer,
+    State: Awake,
+{
+    /// Read a register from the device
+    pub async fn read_reg<R: Readable>(&mut self) -> Result<R, Error<Iface::Error, PinError>> {
+        self.read_reg_unchecked().await
+    }
+
+    /// Write a register to the device, see `crate::ExampleDriver::write_reg`
+    pub async fn write_reg<R: Writable>(&mut self, r: R) -> Result<(), Error<Iface::Error, PinError>> {
+        let mut buff = [0u8; registers::MAX_WIDTH];
+        r.to_bytes(&mut buff[..R::WIDTH]);
+
+        self.write_regs(R::ADDRESS, &buff[..R::WIDTH]).await
+    }
+
+    /// Read consecutive registers in a single transaction, see `crate::ExampleDriver::read_regs`
+    pub async fn read_regs(&mut self, start: u8, data: &mut [u8]) -> Result<(), Error<Iface::Error, PinError>> {
+        self.read_burst(start, data, true).await
+    }
+
+    /// Write consecutive registers in a single transaction, see `crate::ExampleDriver::write_regs`
+    pub async fn write_regs(&mut self, start: u8, data: &[u8]) -> Result<(), Error<Iface::Error, PinError>> {
+        if data.len() <= registers::MAX_BURST {
+            state::check_write::<State>(start, data).map_err(Error::StateChange)?;
+        }
+
+        self.write_burst(start, data).await
+    }
+
+    /// Read-modify-write a register, returning the value written
+    pub async fn modify_reg<R, F>(&mut self, f: F) -> Result<R, Error<Iface::Error, PinError>>
+    where
+        R: Readable + Writable,
+        F: FnOnce(&mut R),
+    {
+        let mut r = self.read_reg::<R>().await?;
+        f(&mut r);
+
+        self.write_reg(r).await?;
+
+        Ok(r)
+    }
+
+    /// Enable the provided device interrupts, see `crate::ExampleDriver::enable_interrupts`
+    pub async fn enable_interrupts(&mut self, mask: IrqEnable) -> Result<(), Error<Iface::Error, PinError>> {
+        self.write_reg(mask).await?;
+
+        self.modify_reg::<Control, _>(|c| {
+            c.set_irq_enable(mask.raw() != 0);
+        }).await?;
+
+        Ok(())
+    }
+
+    /// Handle a device interrupt, see `crate::ExampleDriver::on_interrupt`
+    pub async fn on_interrupt<const N: usize>(&mut self, events: &EventQueue<N>) -> Result<IrqStatus, Error<Iface::Error, PinError>> {
+        let status = self.read_reg::<IrqStatus>().await?;
+
+        events.latch(status);
+
+        Ok(status)
+    }
+
+    /// Configure and enable the FIFO, see `crate::ExampleDriver::configure_fifo`
+    pub async fn configure_fifo(&mut self, config: FifoConfig) -> Result<(), Error<Iface::Error, PinError>> {
+        let (watermark, control) = config.registers().map_err(Error::Config)?;
+        self.write_reg(watermark).await?;
+        self.write_reg(control).await
+    }
+
+    /// Disable the FIFO
+    pub async fn disable_fifo(&mut self) -> Result<(), Error<Iface::Error, PinError>> {
+        self.write_reg(FifoControl::default()).await
+    }
+
+    /// Discard any buffered frames
+    pub async fn flush_fifo(&mut self) -> Result<(), Error<Iface::Error, PinError>> {
+        self.modify_reg::<FifoControl, _>(|c| {
+            c.set_flush(true);
+        }).await?;
+
+        Ok(())
+    }
+
+    /// Read whole frames from the FIFO, see `crate::ExampleDriver::read_fifo`
+    pub async fn read_fifo(&mut self, buff: &mut [u8]) -> Result<FifoRead, Error<Iface::Error, PinError>> {
+        let mut status = [0u8; STATUS_BYTES];
+        self.read_regs(FifoStatus::ADDRESS, &mut status).await?;
+
+        let r = FifoRead::new(status, buff.len());
+        for chunk in r.bursts(buff) {
+            self.read_burst(FifoData::ADDRESS, chunk, false).await?;
+        }
+
+        Ok(r)
+    }
+}
+
+impl<Iface, BusyPin, ResetPin, PinError, Delay, State> ExampleDriver<Iface, BusyPin, ResetPin, Delay, State>
+where
+    Iface: Interface,
+    BusyPin: OptionalWait<Error = PinError>,
+    ResetPin: OptionalOutputPin<Error = PinError>,
+    Delay: AsyncTimer,
+{
+    /// Release the driver, returning the owned peripherals
+    /// (this does not change the device state)
+    pub fn free(self) -> (Iface, BusyPin, ResetPin, Delay) {
+        (self.iface, self.busy, self.reset, self.delay)
+    }
+
+    /// Fetch the device identification read by `configure()`
+    pub fn device_info(&self) -> DeviceInfo {
+        self.info
+    }
+
+    /// Fetch bus transaction retry counters
+    pub fn retry_stats(&self) -> RetryStats {
+        self.stats
+    }
+
+    /// Clear bus transaction retry counters
+    pub fn clear_retry_stats(&mut self) {
+        self.stats = RetryStats::default();
+    }
+
+    /// Wait for the device to become ready, returning `Error::ResetTimeout`
+    /// if it does not become ready within the configured reset timeout
+    ///
+    /// This uses the busy pin if connected, otherwise `Config::ready_fallback`
+    pub async fn wait_busy(&mut self) -> Result<(), Error<Iface::Error, PinError>> {
+        if !self.busy.is_connected() {
+            return match self.config.ready_fallback {
+                ReadyFallback::Status => self.poll_status().await,
+                ReadyFallback::Delay(ms) => {
+                    self.delay.delay_ms(ms).await;
+                    Ok(())
+                }
+            };
+        }
+
+        let timeout = self.delay.delay_ms(self.config.reset_timeout_ms);
+        let ready = self.busy.wait_for_level(self.config.busy_polarity.level(false));
+
+        match select(ready, timeout).await {
+            Either::First(r) => r.map_err(|error| Error::Pin { op: Operation::WaitBusy, error }),
+            Either::Second(_) => Err(Error::ResetTimeout),
+        }
+    }
+
+    /// Poll the status register until the device is ready
+    async fn poll_status(&mut self) -> Result<(), Error<Iface::Error, PinError>> {
+        let mut timeout = Timeout::new(self.delay.now_ms(), self.config.reset_timeout_ms);
+        while self.read_reg_unchecked::<Status>().await?.busy() {
+            self.delay.delay_ms(self.config.poll_ms).await;
+
+            if timeout.expired(self.delay.now_ms(), self.config.poll_ms) {
+                return Err(Error::ResetTimeout);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reset the device and wait for it to become ready
+    ///
+    /// This pulses the reset line, or issues a software reset
+    /// if no reset pin is connected
+    async fn reset_device(&mut self) -> Result<(), Error<Iface::Error, PinError>> {
+        if self.reset.is_connected() {
+            self.set_reset(true)?;
+            self.delay.delay_ms(self.config.reset_pulse_ms).await;
+            self.set_reset(false)?;
+        } else {
+            self.write_reg_unchecked(soft_reset_command()).await?;
+        }
+
+        if self.config.reset_settle_ms > 0 {
+            self.delay.delay_ms(self.config.reset_settle_ms).await;
+        }
+
+        self.wait_busy().await
+    }
+
+    /// Assert or release the reset line
+    fn set_reset(&mut self, asserted: bool) -> Result<(), Error<Iface::Error, PinError>> {
+        let level = self.config.reset_polarity.level(asserted);
+
+        self.reset.set_level(level).map_err(|error| Error::Pin { op: Operation::Reset, error })
+    }
+
+    /// Read a register irrespective of device state
+    async fn read_reg_unchecked<R: Readable>(&mut self) -> Result<R, Error<Iface::Error, PinError>> {
+        let mut buff = [0u8; registers::MAX_WIDTH];
+        self.read_burst(R::ADDRESS, &mut buff[..R::WIDTH], true).await?;
+
+        Ok(R::from_bytes(&buff[..R::WIDTH]))
+    }
+
+    /// Write a register irrespective of device state
+    async fn write_reg_unchecked<R: Writable>(&mut self, r: R) -> Result<(), Error<Iface::Error, PinError>> {
+        let mut buff = [0u8; registers::MAX_WIDTH];
+        r.to_bytes(&mut buff[..R::WIDTH]);
+
+        self.write_burst(R::ADDRESS, &buff[..R::WIDTH]).await
+    }
+
+    /// Read a burst from `start`, with or without auto-incrementing the register
+    /// address, checking the frame checksum if enabled
+    async fn read_burst(&mut self, start: u8, data: &mut [u8], increment: bool) -> Result<(), Error<Iface::Error, PinError>> {
+        let checksum = self.config.checksum;
+
+        let mut buff = [0u8; checksum::MAX_FRAME];
+        let frame = checksum.frame(&mut buff, data.len())?;
+
+        // The checksum is checked within the retry, so mismatches are retried
+        let mut retry = Retry::new();
+        loop {
+            let r = match increment {
+                true => self.iface.read_register(start, frame).await,
+                false => self.iface.read_register_fixed(start, frame).await,
+            };
+
+            let iface = &self.iface;
+            let r = r.map_err(Error::interface(Operation::Read(start))).and_then(|()| {
+                checksum.frame_verify(Operation::Read(start), frame, data, |len, header| {
+                    let reg = match increment {
+                        true => iface.register_address(start, len),
+                        false => start,
+                    };
+                    iface.checksum_header(reg, true, header)
+                })
+            });
+
+            match r {
+                Ok(()) => break,
+                Err(e) => self.backoff(&mut retry, e).await?,
+            }
+        }
+        retry.succeeded(&mut self.stats);
+
+        Ok(())
+    }
+
+    /// Write consecutive registers in a single transaction, appending the frame checksum if enabled
+    async fn write_burst(&mut self, start: u8, data: &[u8]) -> Result<(), Error<Iface::Error, PinError>> {
+        let checksum = self.config.checksum;
+
+        let mut buff = [0u8; checksum::MAX_FRAME];
+        let frame = checksum.frame(&mut buff, data.len())?;
+
+        let iface = &self.iface;
+        checksum.frame_write(frame, data, |len, header| {
+            iface.checksum_header(iface.register_address(start, len), false, header)
+        });
+
+        let mut retry = Retry::new();
+        while let Err(e) = self.iface.write_register(start, frame).await {
+            self.backoff(&mut retry, Error::Interface { op: Operation::Write(start), error: e }).await?;
+        }
+        retry.succeeded(&mut self.stats);
+
+        Ok(())
+    }
+
+    /// Handle a failed bus transaction attempt, waiting for the backoff period
+    /// if it should be retried or returning the error if not
+    async fn backoff(&mut self, retry: &mut Retry, error: Error<Iface::Error, PinError>) -> Result<(), Error<Iface::Error, PinError>> {
+        let kind = error.bus_error_kind(Iface::error_kind);
+        match retry.failed(&self.config.retry, &mut self.stats, kind) {
+            Some(backoff_ms) => {
+                self.delay.delay_ms(backoff_ms).await;
+                Ok(())
+            }
+            None => Err(error),
+        }
+    }
+
+    /// Move the driver into a new state
+    fn into_state<Next>(self) -> ExampleDriver<Iface, BusyPin, ResetPin, Delay, Next> {
+        ExampleDriver {
+            config: self.config,
+            iface: self.iface,
+            busy: self.busy,
+            reset: self.reset,
+            delay: self.delay,
+            stats: self.stats,
+            info: self.info,
+            _state: PhantomData,
+        }
+    }
+}
+
+
+/// Result of `select`
+enum Either<A, B> {
+    First(A),
+    Second(B),
+}
+
+/// Wait on two futures, returning the result of whichever completes first
+///
+/// (this saves depending on an executor-specific futures crate)
+async fn select<A: Future, B: Future>(a: A, b: B) -> Either<A::Output, B::Output> {
+    let mut a = pin!(a);
+    let mut b = pin!(b);
+
+    poll_fn(|cx| {
+        if let Poll::Ready(r) = a.as_mut().poll(cx) {
+            return Poll::Ready(Either::First(r));
+        }
+        if let Poll::Ready(r) = b.as_mut().poll(cx) {
+            return Poll::Ready(Either::Second(r));
+        }
+        Poll::Pending
+    }).await
+}
+
+impl<Iface, BusyPin, ResetPin, PinError, Delay, State> ExampleDriver<Iface, BusyPin, ResetPin, Delay, State>
+where
+    Iface: CommandInterface,
+    BusyPin: OptionalWait<Error = PinError>,
+    ResetPin: OptionalOutputPin<Error = PinError>,
+    Delay: AsyncTimer,
+    State: Awake,
+{
+    /// Send a command with the provided address and payload,
+    /// returning the decoded status and response payload
+    pub async fn command(&mut self, cmd: protocol::Command, address: u32, payload: &[u8]) -> Result<Response, Error<Iface::Error, PinError>> {
+        let mut frame = CommandFrame::new(&cmd, address);
+        let (header, buff) = frame.buffers();
+
+        let mut retry = Retry::new();
+        let status = loop {
+            match self.iface.command(header, payload, buff).await {
+                Ok(status) => break status,
+                Err(e) => self.backoff(&mut retry, Error::Interface { op: Operation::Command(cmd.opcode()), error: e }).await?,
+            }
+        };
+        retry.succeeded(&mut self.stats);
+
+        Ok(frame.response(status))
+    }
+}
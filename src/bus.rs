@@ -0,0 +1,85 @@
+//! Shared bus support (requires the `shared-bus` feature)
+//!
+//! The driver owns its `Interface`, but the underlying bus only needs to
+//! implement `I2c` or `SpiDevice`, so multiple drivers can share one
+//! peripheral through the embedded-hal-bus proxies re-exported here:
+//!
+//! - `i2c::RefCellDevice` / `spi::RefCellDevice` for drivers used from a single context
+//! - `i2c::CriticalSectionDevice` / `spi::CriticalSectionDevice` for drivers
+//!   shared between interrupt contexts
+//! - `i2c::MutexDevice` / `spi::MutexDevice` for drivers shared between threads
+//!   (with the `std` feature)
+//!
+//! I2C devices are selected by address, while each SPI proxy owns the chip
+//! select for its device so it is asserted for every transaction:
+//!
+//! ```text
+//! let i2c = RefCell::new(i2c);
+//! let a = ExampleDriver::new(config_a, I2cInterface::new_shared(&i2c, 0x01), ...)?;
+//! let b = ExampleDriver::new(config_b, I2cInterface::new_shared(&i2c, 0x02), ...)?;
+//!
+//! let spi = RefCell::new(spi);
+//! let c = ExampleDriver::new(config, SpiInterface::new_shared(&spi, cs_c)?, ...)?;
+//! ```
+//!
+//! See `examples/shared_bus.rs` for a complete example using the simulator.
+
+use core::cell::RefCell;
+
+use critical_section::Mutex;
+use embedded_hal::digital::OutputPin;
+use embedded_hal::i2c::{I2c, SevenBitAddress};
+use embedded_hal::spi::SpiBus;
+
+pub use embedded_hal_bus::{i2c, spi};
+
+use crate::interface::{I2cInterface, SpiInterface};
+
+
+impl<'a, Bus> I2cInterface<i2c::RefCellDevice<'a, Bus>>
+where
+    Bus: I2c,
+{
+    /// Create an I2C interface on a bus shared via a `RefCell`
+    pub fn new_shared(bus: &'a RefCell<Bus>, address: SevenBitAddress) -> Self {
+        I2cInterface::new(i2c::RefCellDevice::new(bus), address)
+    }
+}
+
+impl<'a, Bus> I2cInterface<i2c::CriticalSectionDevice<'a, Bus>>
+where
+    Bus: I2c,
+{
+    /// Create an I2C interface on a bus shared via a critical section mutex
+    pub fn new_critical_section(bus: &'a Mutex<RefCell<Bus>>, address: SevenBitAddress) -> Self {
+        I2cInterface::new(i2c::CriticalSectionDevice::new(bus), address)
+    }
+}
+
+impl<'a, Bus, Cs> SpiInterface<spi::RefCellDevice<'a, Bus, Cs, spi::NoDelay>>
+where
+    Bus: SpiBus,
+    Cs: OutputPin,
+{
+    /// Create an SPI interface on a bus shared via a `RefCell`,
+    /// using `cs` to select the device
+    ///
+    /// This fails if the chip select can't be deasserted
+    pub fn new_shared(bus: &'a RefCell<Bus>, cs: Cs) -> Result<Self, Cs::Error> {
+        spi::RefCellDevice::new_no_delay(bus, cs).map(SpiInterface::new)
+    }
+}
+
+impl<'a, Bus, Cs> SpiInterface<spi::CriticalSectionDevice<'a, Bus, Cs, spi::NoDelay>>
+where
+    Bus: SpiBus,
+    Cs: OutputPin,
+{
+    /// Create an SPI interface on a bus shared via a critical section mutex,
+    /// using `cs` to select the device
+    ///
+    /// This fails if the chip select can't be deasserted
+    pub fn new_critical_section(bus: &'a Mutex<RefCell<Bus>>, cs: Cs) -> Result<Self, Cs::Error> {
+        spi::CriticalSectionDevice::new_no_delay(bus, cs).map(SpiInterface::new)
+    }
+}
@@ -0,0 +1,168 @@
+//! Frame checksums
+//!
+//! Some devices append a CRC to every register frame. When enabled with
+//! `Config::checksum` this is appended to register writes and checked on
+//! register reads, returning `Error::Checksum` on mismatch.
+//!
+//! The CRC covers the frame header as sent on the wire (see
+//! `Interface::checksum_header`) followed by the register data:
+//!
+//! - I2C: the address byte(s), register, and for reads the repeated-start
+//!   address byte, as for an SMBus PEC
+//! - SPI: the register byte including `SPI_READ_FLAG`
+//!
+//! The register byte is as sent, including any auto-increment flag (see
+//! `Interface::register_address`).
+//!
+//! CRC-16 values are sent most significant byte first.
+
+use crate::{registers, Error, Operation};
+
+/// Maximum checksum length in bytes
+pub const MAX_LEN: usize = 2;
+
+/// Maximum checksum header length in bytes
+pub const MAX_HEADER: usize = 4;
+
+/// Maximum register frame length, a full burst followed by the checksum
+pub(crate) const MAX_FRAME: usize = registers::MAX_BURST + MAX_LEN;
+
+/// Frame checksum algorithm
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Checksum {
+    /// No checksum
+    #[default]
+    None,
+    /// CRC-8/SMBus (polynomial 0x07, initial value 0x00), as used for SMBus PEC
+    Crc8Smbus,
+    /// CRC-16/CCITT-FALSE (polynomial 0x1021, initial value 0xffff)
+    Crc16Ccitt,
+}
+
+impl Checksum {
+    /// Checksum length in bytes
+    pub const fn bytes(&self) -> usize {
+        match self {
+            Checksum::None => 0,
+            Checksum::Crc8Smbus => 1,
+            Checksum::Crc16Ccitt => 2,
+        }
+    }
+
+    /// Check whether checksums are disabled
+    pub const fn is_none(&self) -> bool {
+        matches!(self, Checksum::None)
+    }
+
+    /// Compute the checksum over `header` followed by `data`
+    pub fn compute(&self, header: &[u8], data: &[u8]) -> u16 {
+        match self {
+            Checksum::None => 0,
+            Checksum::Crc8Smbus => crc8_smbus(crc8_smbus(0, header), data) as u16,
+            Checksum::Crc16Ccitt => crc16_ccitt(crc16_ccitt(0xffff, header), data),
+        }
+    }
+
+    /// Fill the trailing `bytes()` bytes of `frame` with the checksum of `header`
+    /// and the preceding data
+    pub fn append(&self, header: &[u8], frame: &mut [u8]) {
+        let (data, crc) = frame.split_at_mut(frame.len() - self.bytes());
+        let value = self.compute(header, data);
+
+        crc.copy_from_slice(&value.to_be_bytes()[2 - self.bytes()..]);
+    }
+
+    /// Check the trailing `bytes()` bytes of `frame` match the checksum of
+    /// `header` and the preceding data, returning the `(expected, actual)`
+    /// checksums on mismatch
+    pub fn verify(&self, header: &[u8], frame: &[u8]) -> Result<(), (u16, u16)> {
+        let (data, crc) = frame.split_at(frame.len() - self.bytes());
+
+        let expected = self.compute(header, data);
+        let actual = crc.iter().fold(0u16, |a, b| (a << 8) | *b as u16);
+
+        match expected == actual {
+            true => Ok(()),
+            false => Err((expected, actual)),
+        }
+    }
+}
+
+// Register frames, shared by the blocking and async drivers
+//
+// `header` closures are passed the frame length and write the frame header
+// to the provided buffer as for `Interface::checksum_header`, they are only
+// called with checksums enabled
+impl Checksum {
+    /// Frame for `len` bytes of register data followed by the checksum,
+    /// returning `Error::BurstLength` if this exceeds `registers::MAX_BURST`
+    pub(crate) fn frame<'a, I, P>(&self, buff: &'a mut [u8; MAX_FRAME], len: usize) -> Result<&'a mut [u8], Error<I, P>> {
+        if len > registers::MAX_BURST {
+            return Err(Error::BurstLength(len));
+        }
+
+        Ok(&mut buff[..len + self.bytes()])
+    }
+
+    /// Fill a write `frame` with `data` followed by the checksum
+    pub(crate) fn frame_write<H>(&self, frame: &mut [u8], data: &[u8], header: H)
+    where
+        H: FnOnce(usize, &mut [u8; MAX_HEADER]) -> usize,
+    {
+        frame[..data.len()].copy_from_slice(data);
+
+        if !self.is_none() {
+            let mut buff = [0u8; MAX_HEADER];
+            let n = header(frame.len(), &mut buff);
+
+            self.append(&buff[..n], frame);
+        }
+    }
+
+    /// Check the checksum of a read `frame` for `op`, copying the register data to `data`
+    pub(crate) fn frame_verify<I, P, H>(&self, op: Operation, frame: &[u8], data: &mut [u8], header: H) -> Result<(), Error<I, P>>
+    where
+        H: FnOnce(usize, &mut [u8; MAX_HEADER]) -> usize,
+    {
+        if !self.is_none() {
+            let mut buff = [0u8; MAX_HEADER];
+            let n = header(frame.len(), &mut buff);
+
+            self.verify(&buff[..n], frame)
+                .map_err(|(expected, actual)| Error::Checksum { op, expected, actual })?;
+        }
+
+        data.copy_from_slice(&frame[..data.len()]);
+
+        Ok(())
+    }
+}
+
+/// Update a CRC-8/SMBus with `data`, start from `0`
+pub fn crc8_smbus(mut crc: u8, data: &[u8]) -> u8 {
+    for b in data {
+        crc ^= *b;
+        for _ in 0..8 {
+            crc = match crc & 0x80 != 0 {
+                true => (crc << 1) ^ 0x07,
+                false => crc << 1,
+            };
+        }
+    }
+    crc
+}
+
+/// Update a CRC-16/CCITT-FALSE with `data`, start from `0xffff`
+pub fn crc16_ccitt(mut crc: u16, data: &[u8]) -> u16 {
+    for b in data {
+        crc ^= (*b as u16) << 8;
+        for _ in 0..8 {
+            crc = match crc & 0x8000 != 0 {
+                true => (crc << 1) ^ 0x1021,
+                false => crc << 1,
+            };
+        }
+    }
+    crc
+}
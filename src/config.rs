@@ -0,0 +1,142 @@
+//! Driver configuration
+//!
+//! Board-specific details such as reset / busy line polarity and timing
+//! live here so one driver can support multiple board revisions.
+//! See `pins` for boards without reset or busy lines.
+
+use core::fmt;
+
+use crate::checksum::Checksum;
+use crate::interface::Address;
+use crate::retry::RetryPolicy;
+
+/// Signal polarity
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Polarity {
+    /// Signal is asserted when the line is low
+    ActiveLow,
+    /// Signal is asserted when the line is high
+    ActiveHigh,
+}
+
+impl Polarity {
+    /// Line level (`true` for high) for the provided signal state
+    pub fn level(self, asserted: bool) -> bool {
+        match self {
+            Polarity::ActiveLow => !asserted,
+            Polarity::ActiveHigh => asserted,
+        }
+    }
+}
+
+/// How to wait for the device to become ready when no busy pin is connected
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReadyFallback {
+    /// Poll the `Status` register busy flag
+    Status,
+    /// Wait a fixed time (in milliseconds)
+    Delay(u32),
+}
+
+/// Driver configuration data
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// Device I2C address, see `I2cInterface::from_config`
+    pub address: Address,
+
+    /// Device polling time
+    pub poll_ms: u32,
+
+    /// Reset line polarity
+    pub reset_polarity: Polarity,
+    /// Reset pulse width
+    pub reset_pulse_ms: u32,
+    /// Time to wait after releasing reset before checking the busy line
+    pub reset_settle_ms: u32,
+    /// Maximum time to wait for the device to become ready after reset
+    pub reset_timeout_ms: u32,
+
+    /// Busy line polarity (asserted while the device is busy)
+    pub busy_polarity: Polarity,
+    /// Ready detection used when no busy pin is connected
+    pub ready_fallback: ReadyFallback,
+
+    /// Retry policy for bus transactions
+    pub retry: RetryPolicy,
+
+    /// Register frame checksum, see `checksum`
+    pub checksum: Checksum,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            address: Address::default(),
+            poll_ms: 100,
+            reset_polarity: Polarity::ActiveLow,
+            reset_pulse_ms: 10,
+            reset_settle_ms: 0,
+            reset_timeout_ms: 100,
+            busy_polarity: Polarity::ActiveLow,
+            ready_fallback: ReadyFallback::Status,
+            retry: RetryPolicy::default(),
+            checksum: Checksum::None,
+        }
+    }
+}
+
+/// Configuration errors
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ConfigError {
+    /// Poll period must be non-zero
+    PollPeriod,
+    /// Reset pulse width must be non-zero
+    ResetPulse,
+    /// Reset timeout must be at least one poll period
+    ResetTimeout,
+    /// Retry policy must allow at least one attempt
+    RetryAttempts,
+    /// I2C address is out of range, or doesn't match the interface address mode
+    Address,
+    /// FIFO watermark must be between one frame and `fifo::FIFO_DEPTH`
+    FifoWatermark,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::PollPeriod => write!(f, "poll period must be non-zero"),
+            ConfigError::ResetPulse => write!(f, "reset pulse width must be non-zero"),
+            ConfigError::ResetTimeout => write!(f, "reset timeout must be at least one poll period"),
+            ConfigError::RetryAttempts => write!(f, "retry policy must allow at least one attempt"),
+            ConfigError::Address => write!(f, "invalid I2C address"),
+            ConfigError::FifoWatermark => write!(f, "FIFO watermark must be between 1 and {} frames", crate::fifo::FIFO_DEPTH),
+        }
+    }
+}
+
+impl core::error::Error for ConfigError {}
+
+impl Config {
+    /// Check the configuration is valid
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.poll_ms == 0 {
+            return Err(ConfigError::PollPeriod);
+        }
+        if self.reset_pulse_ms == 0 {
+            return Err(ConfigError::ResetPulse);
+        }
+        if self.reset_timeout_ms < self.poll_ms {
+            return Err(ConfigError::ResetTimeout);
+        }
+        if self.retry.max_attempts == 0 {
+            return Err(ConfigError::RetryAttempts);
+        }
+        if !self.address.is_valid() {
+            return Err(ConfigError::Address);
+        }
+
+        Ok(())
+    }
+}
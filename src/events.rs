@@ -0,0 +1,197 @@
+//! Interrupt events
+//!
+//! The device signals events via its interrupt output. Call
+//! `ExampleDriver::on_interrupt` from the interrupt handler to latch (and
+//! clear) the device interrupt status, which is decoded into `Event`s and
+//! pushed to an `EventQueue`. The main loop then consumes them with
+//! `EventQueue::poll_events`:
+//!
+//! ```
+//! # use driver_example::sim::SimulatedDevice;
+//! use driver_example::{Config, ExampleDriver, I2cInterface};
+//! use driver_example::events::{Event, EventQueue};
+//! use driver_example::registers::IrqEnable;
+//!
+//! static EVENTS: EventQueue<8> = EventQueue::new();
+//!
+//! # let dev = SimulatedDevice::new();
+//! # let iface = I2cInterface::new(dev.i2c(), dev.address());
+//! let mut d = ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+//! d.enable_interrupts(*IrqEnable::default().set_data_ready(true)).unwrap();
+//!
+//! // In the interrupt handler
+//! # dev.raise_interrupt(0x01);
+//! d.on_interrupt(&EVENTS).unwrap();
+//!
+//! // In the main loop
+//! for e in EVENTS.poll_events() {
+//!     assert_eq!(e, Event::DataReady);
+//! }
+//! ```
+//!
+//! The queue is lock-free and can be shared between the interrupt handler
+//! and main loop as a `static`, but supports only a single producer and a
+//! single consumer.
+
+use core::sync::atomic::{AtomicU32, AtomicU8, AtomicUsize, Ordering};
+
+use crate::registers::IrqStatus;
+
+
+/// (example) Device events, decoded from `IrqStatus`
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Event {
+    /// New data available
+    DataReady,
+    /// Threshold level crossed
+    Threshold,
+    /// Device fault
+    Fault,
+    /// FIFO level reached the watermark, see `fifo`
+    FifoWatermark,
+    /// FIFO overflowed and frames were dropped, see `fifo`
+    FifoOverflow,
+}
+
+impl Event {
+    /// All events, in the order they are decoded
+    pub const ALL: [Event; 5] = [
+        Event::DataReady,
+        Event::Threshold,
+        Event::Fault,
+        Event::FifoWatermark,
+        Event::FifoOverflow,
+    ];
+
+    /// Check whether the event is flagged in the provided interrupt status
+    pub fn is_set(self, status: IrqStatus) -> bool {
+        match self {
+            Event::DataReady => status.data_ready(),
+            Event::Threshold => status.threshold(),
+            Event::Fault => status.fault(),
+            Event::FifoWatermark => status.fifo_watermark(),
+            Event::FifoOverflow => status.fifo_overflow(),
+        }
+    }
+
+    fn encode(self) -> u8 {
+        self as u8
+    }
+
+    fn decode(v: u8) -> Event {
+        Event::ALL[v as usize]
+    }
+}
+
+
+/// Lock-free single producer, single consumer queue of `N` events
+#[derive(Debug)]
+pub struct EventQueue<const N: usize> {
+    /// Encoded events
+    slots: [AtomicU8; N],
+    /// Read index (modulo `2 * N`), written only by the consumer
+    head: AtomicUsize,
+    /// Write index (modulo `2 * N`), written only by the producer
+    tail: AtomicUsize,
+    /// Events dropped because the queue was full, written only by the producer
+    dropped: AtomicU32,
+}
+
+impl<const N: usize> Default for EventQueue<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> EventQueue<N> {
+    /// Create a new empty queue
+    pub const fn new() -> Self {
+        assert!(N > 0, "EventQueue must have a non-zero capacity");
+
+        Self {
+            slots: [const { AtomicU8::new(0) }; N],
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            dropped: AtomicU32::new(0),
+        }
+    }
+
+    /// Number of events in the queue
+    pub fn len(&self) -> usize {
+        let head = self.head.load(Ordering::Acquire);
+        let tail = self.tail.load(Ordering::Acquire);
+
+        (tail + 2 * N - head) % (2 * N)
+    }
+
+    /// Check whether the queue is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of events dropped due to the queue being full
+    pub fn dropped(&self) -> u32 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Push an event (producer side), returning the event if the queue is full
+    pub fn push(&self, e: Event) -> Result<(), Event> {
+        let tail = self.tail.load(Ordering::Relaxed);
+        let head = self.head.load(Ordering::Acquire);
+
+        if (tail + 2 * N - head) % (2 * N) == N {
+            let dropped = self.dropped.load(Ordering::Relaxed);
+            self.dropped.store(dropped.saturating_add(1), Ordering::Relaxed);
+            return Err(e);
+        }
+
+        self.slots[tail % N].store(e.encode(), Ordering::Relaxed);
+        self.tail.store((tail + 1) % (2 * N), Ordering::Release);
+
+        Ok(())
+    }
+
+    /// Pop the oldest event (consumer side)
+    pub fn pop(&self) -> Option<Event> {
+        let head = self.head.load(Ordering::Relaxed);
+        let tail = self.tail.load(Ordering::Acquire);
+
+        if head == tail {
+            return None;
+        }
+
+        let e = Event::decode(self.slots[head % N].load(Ordering::Relaxed));
+        self.head.store((head + 1) % (2 * N), Ordering::Release);
+
+        Some(e)
+    }
+
+    /// Push the events flagged in an interrupt status (producer side),
+    /// returning the number of events queued
+    pub fn latch(&self, status: IrqStatus) -> usize {
+        Event::ALL.iter()
+            .filter(|e| e.is_set(status))
+            .filter(|e| self.push(**e).is_ok())
+            .count()
+    }
+
+    /// Drain queued events (consumer side)
+    pub fn poll_events(&self) -> PollEvents<'_, N> {
+        PollEvents { queue: self }
+    }
+}
+
+/// Iterator over queued events, see `EventQueue::poll_events`
+#[derive(Debug)]
+pub struct PollEvents<'a, const N: usize> {
+    queue: &'a EventQueue<N>,
+}
+
+impl<const N: usize> Iterator for PollEvents<'_, N> {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        self.queue.pop()
+    }
+}
@@ -0,0 +1,252 @@
+//! FIFO streaming
+//!
+//! The device buffers measurement frames in a hardware FIFO, so they can be
+//! read in bulk rather than one interrupt per sample. Enable it with
+//! `ExampleDriver::configure_fifo`, then drain it with `read_fifo` when the
+//! `Event::FifoWatermark` interrupt fires (or periodically):
+//!
+//! ```
+//! # use driver_example::sim::SimulatedDevice;
+//! use driver_example::{Config, ExampleDriver, I2cInterface};
+//! use driver_example::fifo::{FifoConfig, Frame, FRAME_SIZE};
+//!
+//! # let dev = SimulatedDevice::new();
+//! # let iface = I2cInterface::new(dev.i2c(), dev.address());
+//! let mut d = ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+//! d.configure_fifo(FifoConfig { watermark: 4, ..Default::default() }).unwrap();
+//!
+//! # dev.push_fifo(Frame { x: 1, y: 2, z: 3 });
+//! let mut buff = [0u8; 8 * FRAME_SIZE];
+//! let r = d.read_fifo(&mut buff).unwrap();
+//!
+//! for f in r.frames(&buff) {
+//!     assert_eq!(f, Frame { x: 1, y: 2, z: 3 });
+//! }
+//! assert_eq!(r.dropped, 0);
+//! ```
+//!
+//! Frames that don't fit are left in the FIFO and counted in
+//! `FifoRead::pending`. If the FIFO fills before it is drained, frames are
+//! dropped according to the `FifoMode`, and `read_fifo` reports the overflow
+//! and the number of frames lost.
+//!
+//! TODO: update the frame format and depth to match your device
+
+use core::convert::TryInto;
+use core::slice::{ChunksExact, ChunksMut};
+
+use crate::config::ConfigError;
+use crate::interface::Interface;
+use crate::pins::{OptionalInputPin, OptionalOutputPin};
+use crate::registers::{self, FifoControl, FifoData, FifoDropped, FifoMode, FifoStatus, FifoWatermark, Register};
+use crate::state::Awake;
+use crate::timer::Timer;
+use crate::{Error, ExampleDriver};
+
+
+/// (example) FIFO frame size in bytes
+pub const FRAME_SIZE: usize = 6;
+
+/// (example) FIFO depth in frames
+pub const FIFO_DEPTH: usize = 32;
+
+/// FIFO status and dropped frame counter, read in a single burst from `FifoStatus::ADDRESS`
+pub(crate) const STATUS_BYTES: usize = 2;
+
+/// Maximum bytes read from the FIFO per burst, a whole number of frames
+const BURST_BYTES: usize = registers::MAX_BURST / FRAME_SIZE * FRAME_SIZE;
+
+/// FIFO configuration
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FifoConfig {
+    /// Level (in frames) at which the FIFO watermark interrupt is raised
+    pub watermark: u8,
+    /// Behaviour when the FIFO is full
+    pub mode: FifoMode,
+}
+
+impl Default for FifoConfig {
+    fn default() -> Self {
+        Self { watermark: 16, mode: FifoMode::Stream }
+    }
+}
+
+impl FifoConfig {
+    /// Check the configuration is valid
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.watermark == 0 || self.watermark as usize > FIFO_DEPTH {
+            return Err(ConfigError::FifoWatermark);
+        }
+
+        Ok(())
+    }
+
+    /// Validate the configuration, returning the register values applying
+    /// it and enabling (and flushing) the FIFO
+    ///
+    /// This is shared by the blocking and async drivers
+    pub(crate) fn registers(&self) -> Result<(FifoWatermark, FifoControl), ConfigError> {
+        self.validate()?;
+
+        let mut w = FifoWatermark::default();
+        w.set_level(self.watermark);
+
+        let mut c = FifoControl::default();
+        c.set_enable(true);
+        c.set_mode(self.mode);
+        c.set_flush(true);
+
+        Ok((w, c))
+    }
+}
+
+
+/// (example) Decoded FIFO frame, a three axis sample
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Frame {
+    /// X axis
+    pub x: i16,
+    /// Y axis
+    pub y: i16,
+    /// Z axis
+    pub z: i16,
+}
+
+impl Frame {
+    /// Decode a frame, axes are sent MSB first
+    pub fn from_bytes(b: &[u8; FRAME_SIZE]) -> Self {
+        Self {
+            x: i16::from_be_bytes([b[0], b[1]]),
+            y: i16::from_be_bytes([b[2], b[3]]),
+            z: i16::from_be_bytes([b[4], b[5]]),
+        }
+    }
+
+    /// Encode a frame
+    pub fn to_bytes(&self) -> [u8; FRAME_SIZE] {
+        let [x0, x1] = self.x.to_be_bytes();
+        let [y0, y1] = self.y.to_be_bytes();
+        let [z0, z1] = self.z.to_be_bytes();
+
+        [x0, x1, y0, y1, z0, z1]
+    }
+}
+
+/// Iterator decoding frames from a buffer, see `FifoRead::frames`
+#[derive(Debug)]
+pub struct Frames<'a> {
+    chunks: ChunksExact<'a, u8>,
+}
+
+impl Iterator for Frames<'_> {
+    type Item = Frame;
+
+    fn next(&mut self) -> Option<Frame> {
+        self.chunks.next().map(|c| Frame::from_bytes(c.try_into().unwrap()))
+    }
+}
+
+
+/// Result of a FIFO read
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FifoRead {
+    /// Number of frames read
+    pub count: usize,
+    /// Frames left in the FIFO (that didn't fit in the buffer)
+    pub pending: usize,
+    /// FIFO overflowed since the last read
+    pub overflow: bool,
+    /// FIFO data was read while empty since the last read
+    pub underrun: bool,
+    /// Frames dropped due to overflow since the last read (saturating at 255)
+    pub dropped: u8,
+}
+
+impl FifoRead {
+    /// Decode the FIFO status and dropped frame counter, reading as many
+    /// frames as fit in a buffer of `len` bytes
+    ///
+    /// This is shared by the blocking and async drivers
+    pub(crate) fn new(status: [u8; STATUS_BYTES], len: usize) -> Self {
+        let (status, dropped) = (FifoStatus(status[0]), FifoDropped(status[1]));
+
+        let level = status.level() as usize;
+        let count = level.min(len / FRAME_SIZE);
+
+        Self {
+            count,
+            pending: level - count,
+            overflow: status.overflow(),
+            underrun: status.underrun(),
+            dropped: dropped.count(),
+        }
+    }
+
+    /// Number of bytes read
+    pub fn bytes(&self) -> usize {
+        self.count * FRAME_SIZE
+    }
+
+    /// Split `buff` into the bursts to read the frames into
+    ///
+    /// This is shared by the blocking and async drivers
+    pub(crate) fn bursts<'a>(&self, buff: &'a mut [u8]) -> ChunksMut<'a, u8> {
+        buff[..self.bytes()].chunks_mut(BURST_BYTES)
+    }
+
+    /// Decode the frames read into `buff`
+    pub fn frames<'a>(&self, buff: &'a [u8]) -> Frames<'a> {
+        Frames { chunks: buff[..self.bytes()].chunks_exact(FRAME_SIZE) }
+    }
+}
+
+
+impl<Iface, BusyPin, ResetPin, PinError, Delay, State> ExampleDriver <Iface, BusyPin, ResetPin, Delay, State>
+where
+    Iface: Interface,
+    BusyPin: OptionalInputPin<Error = PinError>,
+    ResetPin: OptionalOutputPin<Error = PinError>,
+    Delay: Timer,
+    State: Awake,
+{
+    /// Configure and enable the FIFO, discarding any buffered frames
+    pub fn configure_fifo(&mut self, config: FifoConfig) -> Result<(), Error<Iface::Error, PinError>> {
+        let (watermark, control) = config.registers().map_err(Error::Config)?;
+        self.write_reg(watermark)?;
+        self.write_reg(control)
+    }
+
+    /// Disable the FIFO
+    pub fn disable_fifo(&mut self) -> Result<(), Error<Iface::Error, PinError>> {
+        self.write_reg(FifoControl::default())
+    }
+
+    /// Discard any buffered frames
+    pub fn flush_fifo(&mut self) -> Result<(), Error<Iface::Error, PinError>> {
+        self.modify_reg::<FifoControl, _>(|c| {
+            c.set_flush(true);
+        })?;
+
+        Ok(())
+    }
+
+    /// Read as many whole frames as fit in `buff` from the FIFO, returning
+    /// the number read along with any overflow / underrun and dropped frames
+    ///
+    /// The FIFO status and dropped counter are read (and cleared) in a single
+    /// burst, then the frames in bursts of up to `registers::MAX_BURST` bytes
+    /// from the FIFO data register, without the auto-increment flag.
+    pub fn read_fifo(&mut self, buff: &mut [u8]) -> Result<FifoRead, Error<Iface::Error, PinError>> {
+        let mut status = [0u8; STATUS_BYTES];
+        self.read_regs(FifoStatus::ADDRESS, &mut status)?;
+
+        let r = FifoRead::new(status, buff.len());
+        for chunk in r.bursts(buff) {
+            self.read_burst(FifoData::ADDRESS, chunk, false)?;
+        }
+
+        Ok(r)
+    }
+}
@@ -0,0 +1,311 @@
+//! Adaptors for HALs still on embedded-hal 0.2 (requires the `hal-02` feature)
+//!
+//! The driver is written against embedded-hal 1.0, this module provides
+//! `Interface` implementations over the 0.2 blocking bus traits as well as
+//! wrappers that expose 0.2 pins and delays as their 1.0 equivalents.
+//!
+//! 0.2 bus errors have no error kind, so these interfaces can't classify
+//! errors for `RetryPolicy` and all errors are `BusErrorKind::Other`.
+//!
+//! ```
+//! # use driver_example::mock::{delay, i2c, pin};
+//! use driver_example::{hal02, Config, ExampleDriver};
+//!
+//! # let i2c = i2c::Mock::new(&[
+//! #     i2c::Transaction::write_read(0x01, &[0x0f], &[0x5a]),
+//! #     i2c::Transaction::write_read(0x01, &[0x0e], &[0x10]),
+//! #     i2c::Transaction::write(0x01, &[0x01, 0x02]),
+//! # ]);
+//! # let busy = pin::Mock::new(&[pin::Transaction::get(pin::State::High)]);
+//! # let reset = pin::Mock::new(&[pin::Transaction::set(pin::State::Low), pin::Transaction::set(pin::State::High)]);
+//! # let delay = delay::Mock::new(&[delay::Transaction::Ms(10)]);
+//! // `i2c`, `busy`, `reset` and `delay` implement the embedded-hal 0.2 traits
+//! let iface = hal02::I2cInterface::new(i2c, 0x01);
+//! let d = ExampleDriver::new(Config::default(), iface, hal02::Input(busy), hal02::Output(reset), hal02::Delay(delay)).unwrap();
+//! ```
+
+use core::fmt::Debug;
+
+use embedded_hal_02::blocking::{spi, i2c, delay};
+use embedded_hal_02::digital::v2 as digital02;
+use embedded_hal::digital::{self, ErrorType};
+
+use crate::{checksum, registers};
+use crate::interface::{i2c_checksum_header, register_address, spi_checksum_header, Address, Interface, SPI_READ_FLAG};
+
+
+/// Maximum register write length (including the register address) for I2C,
+/// enough for a full burst with checksum
+///
+/// Register writes are assembled into a single buffer so they can be sent
+/// without a STOP condition between the address and the data
+pub const I2C_MAX_WRITE: usize = registers::MAX_BURST + checksum::MAX_LEN + 1;
+
+/// I2C interface error type
+#[derive(Debug, Clone, PartialEq)]
+pub enum I2cError<E> {
+    /// Underlying I2C device error
+    I2c(E),
+    /// Write exceeded `I2C_MAX_WRITE`
+    TooLong,
+}
+
+/// I2C interface to the device
+pub struct I2cInterface<I2c> {
+    /// I2C device
+    i2c: I2c,
+
+    /// Device I2C address
+    address: u8,
+
+    /// Register address auto-increment flag
+    auto_increment: u8,
+}
+
+impl<I2c, E> I2cInterface<I2c>
+where
+    I2c: i2c::Write<Error = E> + i2c::WriteRead<Error = E>,
+{
+    /// Create a new I2C interface with the provided device address
+    pub fn new(i2c: I2c, address: u8) -> Self {
+        Self { i2c, address, auto_increment: 0 }
+    }
+
+    /// Set the register address auto-increment flag, see `crate::I2cInterface::with_auto_increment`
+    pub fn with_auto_increment(self, flag: u8) -> Self {
+        Self { auto_increment: flag, ..self }
+    }
+}
+
+impl<I2c, E> Interface for I2cInterface<I2c>
+where
+    I2c: i2c::Write<Error = E> + i2c::WriteRead<Error = E>,
+{
+    type Error = I2cError<E>;
+
+    fn read_register(&mut self, reg: u8, buff: &mut [u8]) -> Result<(), Self::Error> {
+        let reg = self.register_address(reg, buff.len());
+        self.read_register_fixed(reg, buff)
+    }
+
+    fn write_register(&mut self, reg: u8, data: &[u8]) -> Result<(), Self::Error> {
+        if data.len() + 1 > I2C_MAX_WRITE {
+            return Err(I2cError::TooLong);
+        }
+
+        let mut buff = [0u8; I2C_MAX_WRITE];
+        buff[0] = self.register_address(reg, data.len());
+        buff[1..][..data.len()].copy_from_slice(data);
+
+        self.i2c.write(self.address, &buff[..data.len() + 1]).map_err(I2cError::I2c)
+    }
+
+    fn transfer(&mut self, data: &[u8], buff: &mut [u8]) -> Result<(), Self::Error> {
+        self.i2c.write_read(self.address, data, buff).map_err(I2cError::I2c)
+    }
+
+    fn read_register_fixed(&mut self, reg: u8, buff: &mut [u8]) -> Result<(), Self::Error> {
+        self.i2c.write_read(self.address, &[reg], buff).map_err(I2cError::I2c)
+    }
+
+    fn register_address(&self, reg: u8, len: usize) -> u8 {
+        register_address(reg, len, self.auto_increment)
+    }
+
+    fn checksum_header(&self, reg: u8, read: bool, buff: &mut [u8; checksum::MAX_HEADER]) -> usize {
+        i2c_checksum_header(Address::SevenBit(self.address), reg, read, buff)
+    }
+}
+
+
+/// SPI interface error type
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpiError<SpiErr, PinErr> {
+    /// Underlying SPI device error
+    Spi(SpiErr),
+    /// Underlying GPIO pin error
+    Pin(PinErr),
+}
+
+/// SPI interface to the device using a manually managed chip select
+pub struct SpiInterface<Spi, CsPin> {
+    /// SPI device
+    spi: Spi,
+
+    /// Chip select pin
+    /// Technically this _can_ be managed by the HAL, however:
+    ///  - often it is not
+    ///  - some hals do not expose transactional (write-read) methods
+    ///    which are required for interacting with some devices
+    ///
+    /// So at this time it's easier to manage yourself
+    cs: CsPin,
+
+    /// Register address auto-increment flag
+    auto_increment: u8,
+}
+
+impl<Spi, SpiErr, CsPin, PinErr> SpiInterface<Spi, CsPin>
+where
+    Spi: spi::Transfer<u8, Error = SpiErr> + spi::Write<u8, Error = SpiErr>,
+    CsPin: digital02::OutputPin<Error = PinErr>,
+{
+    /// Create a new SPI interface using the provided chip select pin
+    pub fn new(spi: Spi, cs: CsPin) -> Self {
+        Self { spi, cs, auto_increment: 0 }
+    }
+
+    /// Set the register address auto-increment flag, see `crate::SpiInterface::with_auto_increment`
+    pub fn with_auto_increment(self, flag: u8) -> Self {
+        Self { auto_increment: flag, ..self }
+    }
+
+    /// Run the provided closure with chip select asserted
+    fn with_cs<F>(&mut self, f: F) -> Result<(), SpiError<SpiErr, PinErr>>
+    where
+        F: FnOnce(&mut Spi) -> Result<(), SpiErr>,
+    {
+        self.cs.set_low().map_err(SpiError::Pin)?;
+
+        let r = f(&mut self.spi).map_err(SpiError::Spi);
+
+        // Always release CS, even if the transfer failed
+        self.cs.set_high().map_err(SpiError::Pin)?;
+
+        r
+    }
+}
+
+impl<Spi, SpiErr, CsPin, PinErr> Interface for SpiInterface<Spi, CsPin>
+where
+    Spi: spi::Transfer<u8, Error = SpiErr> + spi::Write<u8, Error = SpiErr>,
+    CsPin: digital02::OutputPin<Error = PinErr>,
+{
+    type Error = SpiError<SpiErr, PinErr>;
+
+    fn read_register(&mut self, reg: u8, buff: &mut [u8]) -> Result<(), Self::Error> {
+        let reg = self.register_address(reg, buff.len());
+        self.read_register_fixed(reg, buff)
+    }
+
+    fn read_register_fixed(&mut self, reg: u8, buff: &mut [u8]) -> Result<(), Self::Error> {
+        self.with_cs(|spi| {
+            spi.write(&[reg | SPI_READ_FLAG])?;
+
+            for b in buff.iter_mut() {
+                *b = 0;
+            }
+            spi.transfer(buff)?;
+
+            Ok(())
+        })
+    }
+
+    fn write_register(&mut self, reg: u8, data: &[u8]) -> Result<(), Self::Error> {
+        let reg = self.register_address(reg, data.len());
+
+        self.with_cs(|spi| {
+            spi.write(&[reg & !SPI_READ_FLAG])?;
+            spi.write(data)
+        })
+    }
+
+    fn transfer(&mut self, data: &[u8], buff: &mut [u8]) -> Result<(), Self::Error> {
+        self.with_cs(|spi| {
+            spi.write(data)?;
+            spi.transfer(buff)?;
+
+            Ok(())
+        })
+    }
+
+    fn register_address(&self, reg: u8, len: usize) -> u8 {
+        register_address(reg, len, self.auto_increment)
+    }
+
+    fn checksum_header(&self, reg: u8, read: bool, buff: &mut [u8; checksum::MAX_HEADER]) -> usize {
+        spi_checksum_header(reg, read, buff)
+    }
+}
+
+
+/// Pin error wrapper, allows 0.2 pin errors to be used as 1.0 errors
+#[derive(Debug, Clone, PartialEq)]
+pub struct PinError<E>(pub E);
+
+impl<E: Debug> digital::Error for PinError<E> {
+    fn kind(&self) -> digital::ErrorKind {
+        digital::ErrorKind::Other
+    }
+}
+
+/// Input pin wrapper, exposes a 0.2 `InputPin` as a 1.0 `InputPin`
+pub struct Input<P>(pub P);
+
+impl<P, E> ErrorType for Input<P>
+where
+    P: digital02::InputPin<Error = E>,
+    E: Debug,
+{
+    type Error = PinError<E>;
+}
+
+impl<P, E> digital::InputPin for Input<P>
+where
+    P: digital02::InputPin<Error = E>,
+    E: Debug,
+{
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        self.0.is_high().map_err(PinError)
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        self.0.is_low().map_err(PinError)
+    }
+}
+
+/// Output pin wrapper, exposes a 0.2 `OutputPin` as a 1.0 `OutputPin`
+pub struct Output<P>(pub P);
+
+impl<P, E> ErrorType for Output<P>
+where
+    P: digital02::OutputPin<Error = E>,
+    E: Debug,
+{
+    type Error = PinError<E>;
+}
+
+impl<P, E> digital::OutputPin for Output<P>
+where
+    P: digital02::OutputPin<Error = E>,
+    E: Debug,
+{
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.0.set_low().map_err(PinError)
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.0.set_high().map_err(PinError)
+    }
+}
+
+/// Delay wrapper, exposes a 0.2 delay as a 1.0 `DelayNs`
+pub struct Delay<D>(pub D);
+
+impl<D> embedded_hal::delay::DelayNs for Delay<D>
+where
+    D: delay::DelayUs<u32> + delay::DelayMs<u32>,
+{
+    fn delay_ns(&mut self, ns: u32) {
+        // 0.2 has no nanosecond delay so round up to the next microsecond
+        self.0.delay_us(ns.div_ceil(1_000));
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        self.0.delay_us(us);
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        self.0.delay_ms(ms);
+    }
+}
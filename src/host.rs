@@ -0,0 +1,48 @@
+//! Host-only helpers (requires the `std` feature)
+//!
+//! These are useful when running the driver on a host, for example
+//! with linux-embedded-hal or in tests, and aren't available on-target.
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use embedded_hal::delay::DelayNs;
+
+use crate::timer::Clock;
+
+
+/// `DelayNs` implementation using `std::thread::sleep`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StdDelay;
+
+impl DelayNs for StdDelay {
+    fn delay_ns(&mut self, ns: u32) {
+        thread::sleep(Duration::from_nanos(ns as u64));
+    }
+}
+
+
+/// `Clock` implementation using `std::time::Instant`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StdClock {
+    start: Instant,
+}
+
+impl StdClock {
+    /// Create a new clock, starting from zero
+    pub fn new() -> Self {
+        Self { start: Instant::now() }
+    }
+}
+
+impl Default for StdClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for StdClock {
+    fn now_ms(&mut self) -> u32 {
+        self.start.elapsed().as_millis() as u32
+    }
+}
@@ -0,0 +1,540 @@
+//! Device communication interfaces
+//!
+//! The driver logic talks to the device through the `Interface` trait,
+//! so the same `ExampleDriver` can run over whichever bus a board has
+//! wired. Delete the implementation you don't need, or add your own
+//! if the device has a different transport.
+
+use embedded_hal::digital::InputPin;
+use embedded_hal::i2c::{self, I2c, SevenBitAddress, TenBitAddress};
+use embedded_hal::spi::{self, SpiDevice};
+
+use crate::checksum;
+use crate::config::{Config, ConfigError};
+use crate::protocol::{CommandInterface, MAX_HEADER};
+use crate::recovery::{BusRecovery, NoRecovery, RecoveryTrigger};
+use crate::registers::{ChipId, Register};
+use crate::CHIP_ID;
+
+
+/// Bus error classification, used to decide which errors can be retried
+/// (see `RetryPolicy`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BusErrorKind {
+    /// Device did not acknowledge
+    NoAcknowledge,
+    /// Bus arbitration was lost
+    ArbitrationLoss,
+    /// Bus error, such as a misplaced start / stop or chip select fault
+    Bus,
+    /// Data was lost due to an overrun
+    Overrun,
+    /// Frame checksum mismatch, see `Error::Checksum`
+    Checksum,
+    /// Any other (or unknown) error
+    Other,
+}
+
+impl From<i2c::ErrorKind> for BusErrorKind {
+    fn from(kind: i2c::ErrorKind) -> Self {
+        match kind {
+            i2c::ErrorKind::NoAcknowledge(_) => BusErrorKind::NoAcknowledge,
+            i2c::ErrorKind::ArbitrationLoss => BusErrorKind::ArbitrationLoss,
+            i2c::ErrorKind::Bus => BusErrorKind::Bus,
+            i2c::ErrorKind::Overrun => BusErrorKind::Overrun,
+            _ => BusErrorKind::Other,
+        }
+    }
+}
+
+impl From<spi::ErrorKind> for BusErrorKind {
+    fn from(kind: spi::ErrorKind) -> Self {
+        match kind {
+            spi::ErrorKind::Overrun => BusErrorKind::Overrun,
+            spi::ErrorKind::ModeFault | spi::ErrorKind::ChipSelectFault => BusErrorKind::Bus,
+            _ => BusErrorKind::Other,
+        }
+    }
+}
+
+
+/// Interface trait abstracts over the bus used to talk to the device
+pub trait Interface {
+    /// Interface error type
+    type Error;
+
+    /// Read `buff.len()` bytes starting at register `reg`
+    ///
+    /// Multi-byte reads and writes are a single transaction, relying on
+    /// the device to auto-increment the register address
+    fn read_register(&mut self, reg: u8, buff: &mut [u8]) -> Result<(), Self::Error>;
+
+    /// Write `data` starting at register `reg`
+    fn write_register(&mut self, reg: u8, data: &[u8]) -> Result<(), Self::Error>;
+
+    /// Read `buff.len()` bytes from register `reg` without auto-incrementing
+    /// the register address, as for a FIFO data register
+    ///
+    /// `reg` is sent as-is, this defaults to `read_register` for interfaces
+    /// without an auto-increment flag
+    fn read_register_fixed(&mut self, reg: u8, buff: &mut [u8]) -> Result<(), Self::Error> {
+        self.read_register(reg, buff)
+    }
+
+    /// Write `data` then read `buff.len()` bytes in a single transaction
+    fn transfer(&mut self, data: &[u8], buff: &mut [u8]) -> Result<(), Self::Error>;
+
+    /// Classify an interface error, errors are treated as `Other` by default
+    fn error_kind(_error: &Self::Error) -> BusErrorKind {
+        BusErrorKind::Other
+    }
+
+    /// Register address byte sent for a `len` byte access starting at `reg`,
+    /// including any auto-increment flag
+    ///
+    /// This defaults to `reg`
+    fn register_address(&self, reg: u8, _len: usize) -> u8 {
+        reg
+    }
+
+    /// Write the frame header covered by checksums for a register read or
+    /// write to `buff`, returning the header length (see `checksum`)
+    ///
+    /// `reg` is the register address byte as sent (see `register_address`),
+    /// this defaults to that byte alone
+    fn checksum_header(&self, reg: u8, _read: bool, buff: &mut [u8; checksum::MAX_HEADER]) -> usize {
+        buff[0] = reg;
+        1
+    }
+}
+
+
+/// (example) Default device I2C address
+pub const DEFAULT_ADDRESS: u8 = 0x01;
+
+/// Device I2C address
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Address {
+    /// 7-bit address
+    SevenBit(u8),
+    /// 10-bit address (requires a HAL implementing `I2c<TenBitAddress>`)
+    TenBit(u16),
+}
+
+impl Default for Address {
+    fn default() -> Self {
+        Address::SevenBit(DEFAULT_ADDRESS)
+    }
+}
+
+impl Address {
+    /// (example) Address selected by the device address strap pins,
+    /// with the strap value added to `DEFAULT_ADDRESS`
+    pub fn strapped(straps: u8) -> Self {
+        Address::SevenBit(DEFAULT_ADDRESS + (straps & 0x03))
+    }
+
+    /// Read the two strap pins (or board jumpers) wired to the MCU to determine
+    /// the device address, `[ADDR0, ADDR1]` with ADDR0 the least significant strap bit
+    ///
+    /// The device only has two strap pins (see `strapped`), so exactly two must be provided
+    pub fn from_strap_pins<P: InputPin>(pins: &mut [P; 2]) -> Result<Self, P::Error> {
+        let mut straps = 0;
+        for (i, p) in pins.iter_mut().enumerate() {
+            if p.is_high()? {
+                straps |= 1 << i;
+            }
+        }
+
+        Ok(Self::strapped(straps))
+    }
+
+    /// Check the address is in range for its addressing mode
+    pub fn is_valid(&self) -> bool {
+        match self {
+            Address::SevenBit(a) => *a <= 0x7f,
+            Address::TenBit(a) => *a <= 0x3ff,
+        }
+    }
+
+    /// Write the address byte(s) sent on the wire after a (repeated) START to
+    /// `buff`, returning the number of bytes
+    ///
+    /// A 10-bit read following a write sends only the first address byte
+    pub(crate) fn wire_bytes(&self, read: bool, restart: bool, buff: &mut [u8]) -> usize {
+        match *self {
+            Address::SevenBit(a) => {
+                buff[0] = (a << 1) | read as u8;
+                1
+            }
+            Address::TenBit(a) => {
+                buff[0] = 0xf0 | ((a >> 7) as u8 & 0x06) | read as u8;
+                if restart {
+                    return 1;
+                }
+                buff[1] = a as u8;
+                2
+            }
+        }
+    }
+}
+
+/// I2C address mode, implemented for `SevenBitAddress` and `TenBitAddress`
+pub trait AddressMode: i2c::AddressMode + Copy {
+    /// Convert from an `Address`, returning `None` if the mode doesn't match
+    fn from_address(address: Address) -> Option<Self>;
+
+    /// Convert into an `Address`
+    fn into_address(self) -> Address;
+}
+
+impl AddressMode for SevenBitAddress {
+    fn from_address(address: Address) -> Option<Self> {
+        match address {
+            Address::SevenBit(a) => Some(a),
+            Address::TenBit(_) => None,
+        }
+    }
+
+    fn into_address(self) -> Address {
+        Address::SevenBit(self)
+    }
+}
+
+impl AddressMode for TenBitAddress {
+    fn from_address(address: Address) -> Option<Self> {
+        match address {
+            Address::TenBit(a) => Some(a),
+            Address::SevenBit(_) => None,
+        }
+    }
+
+    fn into_address(self) -> Address {
+        Address::TenBit(self)
+    }
+}
+
+/// Scan `candidates` for a device, returning the first address at which
+/// the `ChipId` register reads `CHIP_ID`
+///
+/// Candidates not matching the bus address mode `A` are skipped, as are
+/// addresses that fail to respond. If your HAL supports both 7-bit and 10-bit
+/// addressing, select the mode with `probe::<_, SevenBitAddress>(..)`.
+pub fn probe<Bus, A>(i2c: &mut Bus, candidates: &[Address]) -> Option<Address>
+where
+    Bus: I2c<A>,
+    A: AddressMode,
+{
+    candidates.iter()
+        .filter_map(|c| A::from_address(*c))
+        .find(|a| {
+            let mut id = [0u8; 1];
+            i2c.write_read(*a, &[ChipId::ADDRESS], &mut id).is_ok() && id[0] == CHIP_ID
+        })
+        .map(A::into_address)
+}
+
+
+/// I2C interface to the device
+///
+/// This uses 7-bit addressing by default, see `new_ten_bit` for 10-bit addressing.
+pub struct I2cInterface<Bus, Recovery = NoRecovery, A = SevenBitAddress> {
+    /// I2C device
+    i2c: Bus,
+
+    /// Device I2C address
+    address: A,
+
+    /// Bus recovery hook
+    recovery: Recovery,
+
+    /// Bus recovery trigger
+    trigger: RecoveryTrigger,
+
+    /// Register address auto-increment flag, set for multi-byte accesses
+    auto_increment: u8,
+}
+
+impl<Bus> I2cInterface<Bus, NoRecovery, SevenBitAddress>
+where
+    Bus: I2c<SevenBitAddress>,
+{
+    /// Create a new I2C interface with the provided 7-bit device address
+    pub fn new(i2c: Bus, address: SevenBitAddress) -> Self {
+        Self { i2c, address, recovery: NoRecovery, trigger: RecoveryTrigger::new(0), auto_increment: 0 }
+    }
+
+    /// Create a new I2C interface using the 7-bit `Config::address`,
+    /// returning `ConfigError::Address` if it is invalid or a 10-bit address
+    pub fn from_config(i2c: Bus, config: &Config) -> Result<Self, ConfigError> {
+        Self::with_config_address(i2c, config)
+    }
+}
+
+impl<Bus> I2cInterface<Bus, NoRecovery, TenBitAddress>
+where
+    Bus: I2c<TenBitAddress>,
+{
+    /// Create a new I2C interface with the provided 10-bit device address
+    pub fn new_ten_bit(i2c: Bus, address: TenBitAddress) -> Self {
+        Self { i2c, address, recovery: NoRecovery, trigger: RecoveryTrigger::new(0), auto_increment: 0 }
+    }
+
+    /// Create a new I2C interface using the 10-bit `Config::address`,
+    /// returning `ConfigError::Address` if it is invalid or a 7-bit address
+    pub fn from_config_ten_bit(i2c: Bus, config: &Config) -> Result<Self, ConfigError> {
+        Self::with_config_address(i2c, config)
+    }
+}
+
+impl<Bus, A> I2cInterface<Bus, NoRecovery, A>
+where
+    Bus: I2c<A>,
+    A: AddressMode,
+{
+    /// Create an interface using the `Config::address`, if it matches the address mode `A`
+    fn with_config_address(i2c: Bus, config: &Config) -> Result<Self, ConfigError> {
+        let address = A::from_address(config.address)
+            .filter(|_| config.address.is_valid())
+            .ok_or(ConfigError::Address)?;
+
+        Ok(Self { i2c, address, recovery: NoRecovery, trigger: RecoveryTrigger::new(0), auto_increment: 0 })
+    }
+
+    /// Attach a bus recovery hook, run after `after_errors` consecutive
+    /// I2C errors (see `recovery`)
+    pub fn with_recovery<Recovery>(self, recovery: Recovery, after_errors: u32) -> I2cInterface<Bus, Recovery, A>
+    where
+        Recovery: BusRecovery<Bus>,
+    {
+        I2cInterface {
+            i2c: self.i2c,
+            address: self.address,
+            recovery,
+            trigger: RecoveryTrigger::new(after_errors),
+            auto_increment: self.auto_increment,
+        }
+    }
+}
+
+impl<Bus, Recovery, A> I2cInterface<Bus, Recovery, A>
+where
+    Bus: I2c<A>,
+    Recovery: BusRecovery<Bus>,
+    A: AddressMode,
+{
+    /// Set `flag` in the register address for multi-byte accesses, for
+    /// devices that only auto-increment the register address when asked to
+    /// (commonly the top bit of the register address, check your datasheet)
+    pub fn with_auto_increment(self, flag: u8) -> Self {
+        Self { auto_increment: flag, ..self }
+    }
+
+    /// Fetch the device address
+    pub fn address(&self) -> Address {
+        self.address.into_address()
+    }
+
+    /// Fetch the number of bus recoveries run
+    pub fn recoveries(&self) -> u32 {
+        self.trigger.recoveries
+    }
+
+    /// Track the transaction result, running bus recovery if required
+    ///
+    /// Recovery errors are dropped in favour of the original I2C error,
+    /// as the transaction has failed either way
+    fn check<T>(&mut self, r: Result<T, Bus::Error>) -> Result<T, Bus::Error> {
+        if self.trigger.record(r.is_ok()) {
+            let _ = self.recovery.recover(&mut self.i2c);
+        }
+
+        r
+    }
+}
+
+impl<Bus, Recovery, A> Interface for I2cInterface<Bus, Recovery, A>
+where
+    Bus: I2c<A>,
+    Recovery: BusRecovery<Bus>,
+    A: AddressMode,
+{
+    type Error = Bus::Error;
+
+    fn read_register(&mut self, reg: u8, buff: &mut [u8]) -> Result<(), Self::Error> {
+        let reg = self.register_address(reg, buff.len());
+        self.read_register_fixed(reg, buff)
+    }
+
+    fn read_register_fixed(&mut self, reg: u8, buff: &mut [u8]) -> Result<(), Self::Error> {
+        // The register address is written then read back after a
+        // repeated START, so the pointer isn't released in between
+        let r = self.i2c.write_read(self.address, &[reg], buff);
+        self.check(r)
+    }
+
+    fn write_register(&mut self, reg: u8, data: &[u8]) -> Result<(), Self::Error> {
+        let reg = self.register_address(reg, data.len());
+
+        // Adjacent writes are merged into a single bus write,
+        // so the register address and data go out without a restart
+        let r = self.i2c.transaction(self.address, &mut [
+            i2c::Operation::Write(&[reg]),
+            i2c::Operation::Write(data),
+        ]);
+        self.check(r)
+    }
+
+    fn transfer(&mut self, data: &[u8], buff: &mut [u8]) -> Result<(), Self::Error> {
+        let r = self.i2c.write_read(self.address, data, buff);
+        self.check(r)
+    }
+
+    fn error_kind(error: &Self::Error) -> BusErrorKind {
+        i2c::Error::kind(error).into()
+    }
+
+    fn register_address(&self, reg: u8, len: usize) -> u8 {
+        register_address(reg, len, self.auto_increment)
+    }
+
+    fn checksum_header(&self, reg: u8, read: bool, buff: &mut [u8; checksum::MAX_HEADER]) -> usize {
+        i2c_checksum_header(self.address.into_address(), reg, read, buff)
+    }
+}
+
+/// Register address byte for a `len` byte access, with the auto-increment
+/// `flag` set for multi-byte accesses
+pub(crate) fn register_address(reg: u8, len: usize, flag: u8) -> u8 {
+    match len > 1 {
+        true => reg | flag,
+        false => reg,
+    }
+}
+
+/// I2C checksum header, the address and register, followed
+/// by the repeated START address for reads as for an SMBus PEC
+pub(crate) fn i2c_checksum_header(address: Address, reg: u8, read: bool, buff: &mut [u8; checksum::MAX_HEADER]) -> usize {
+    let mut n = address.wire_bytes(false, false, buff);
+
+    buff[n] = reg;
+    n += 1;
+
+    if read {
+        n += address.wire_bytes(true, true, &mut buff[n..]);
+    }
+
+    n
+}
+
+
+/// SPI register read flag, set in the address byte for reads
+/// (check your datasheet, some devices invert this)
+pub const SPI_READ_FLAG: u8 = 0x80;
+
+/// SPI checksum header, the register byte including the read flag
+pub(crate) fn spi_checksum_header(reg: u8, read: bool, buff: &mut [u8; checksum::MAX_HEADER]) -> usize {
+    buff[0] = match read {
+        true => reg | SPI_READ_FLAG,
+        false => reg & !SPI_READ_FLAG,
+    };
+    1
+}
+
+/// SPI interface to the device
+///
+/// Chip select is managed by the `SpiDevice` implementation, so each
+/// method here is a single CS-framed transaction. If your HAL only gives
+/// you an `SpiBus`, wrap it with `embedded-hal-bus` to get an `SpiDevice`.
+pub struct SpiInterface<Spi> {
+    /// SPI device
+    spi: Spi,
+
+    /// Register address auto-increment flag, set for multi-byte accesses
+    auto_increment: u8,
+}
+
+impl<Spi> SpiInterface<Spi>
+where
+    Spi: SpiDevice,
+{
+    /// Create a new SPI interface
+    pub fn new(spi: Spi) -> Self {
+        Self { spi, auto_increment: 0 }
+    }
+
+    /// Set `flag` in the register address for multi-byte accesses, for
+    /// devices that only auto-increment the register address when asked to
+    /// (commonly `0x40`, next to `SPI_READ_FLAG`, check your datasheet)
+    pub fn with_auto_increment(self, flag: u8) -> Self {
+        Self { auto_increment: flag, ..self }
+    }
+}
+
+impl<Spi> Interface for SpiInterface<Spi>
+where
+    Spi: SpiDevice,
+{
+    type Error = Spi::Error;
+
+    fn read_register(&mut self, reg: u8, buff: &mut [u8]) -> Result<(), Self::Error> {
+        let reg = self.register_address(reg, buff.len());
+        self.read_register_fixed(reg, buff)
+    }
+
+    fn read_register_fixed(&mut self, reg: u8, buff: &mut [u8]) -> Result<(), Self::Error> {
+        self.spi.transaction(&mut [
+            spi::Operation::Write(&[reg | SPI_READ_FLAG]),
+            spi::Operation::Read(buff),
+        ])
+    }
+
+    fn write_register(&mut self, reg: u8, data: &[u8]) -> Result<(), Self::Error> {
+        let reg = self.register_address(reg, data.len());
+
+        self.spi.transaction(&mut [
+            spi::Operation::Write(&[reg & !SPI_READ_FLAG]),
+            spi::Operation::Write(data),
+        ])
+    }
+
+    fn transfer(&mut self, data: &[u8], buff: &mut [u8]) -> Result<(), Self::Error> {
+        self.spi.transaction(&mut [
+            spi::Operation::Write(data),
+            spi::Operation::Read(buff),
+        ])
+    }
+
+    fn error_kind(error: &Self::Error) -> BusErrorKind {
+        spi::Error::kind(error).into()
+    }
+
+    fn register_address(&self, reg: u8, len: usize) -> u8 {
+        register_address(reg, len, self.auto_increment)
+    }
+
+    fn checksum_header(&self, reg: u8, read: bool, buff: &mut [u8; checksum::MAX_HEADER]) -> usize {
+        spi_checksum_header(reg, read, buff)
+    }
+}
+
+impl<Spi> CommandInterface for SpiInterface<Spi>
+where
+    Spi: SpiDevice,
+{
+    fn command(&mut self, header: &[u8], payload: &[u8], response: &mut [u8]) -> Result<u8, Self::Error> {
+        let mut buff = [0u8; MAX_HEADER];
+        let buff = &mut buff[..header.len()];
+        buff.copy_from_slice(header);
+
+        self.spi.transaction(&mut [
+            spi::Operation::TransferInPlace(buff),
+            spi::Operation::Write(payload),
+            spi::Operation::Read(response),
+        ])?;
+
+        Ok(buff[0])
+    }
+}
@@ -1,55 +1,253 @@
-/// Example rust-embedded driver
-/// 
-/// This includes more options than you'll usually need, and is intended
-/// to be adapted (read: have bits removed) according to your use case.
+//! Example rust-embedded driver
+//!
+//! This includes more options than you'll usually need, and is intended
+//! to be adapted (read: have bits removed) according to your use case.
+//!
+//! The driver is `no_std`, enable the `std` feature for host-only helpers
+//! (see `host`), or the `defmt` feature to log driver errors on-target.
 
-use std::marker::PhantomData;
+#![no_std]
+
+#[cfg(feature = "std")]
+extern crate std;
+
+use core::fmt;
+use core::marker::PhantomData;
 
 extern crate embedded_hal;
-use embedded_hal::blocking::{delay, spi, i2c};
-use embedded_hal::digital::v2::{InputPin, OutputPin};
 
+pub mod interface;
+pub use interface::{Address, BusErrorKind, Interface, I2cInterface, SpiInterface};
+
+#[macro_use]
+mod macros;
+
+pub mod registers;
+use registers::{ChipId, Command, Control, IrqEnable, IrqStatus, Mode, Readable, Register, Revision, Status, Writable};
+
+pub mod state;
+use state::{Awake, Ready, Sleeping, Unconfigured};
+
+pub mod config;
+pub use config::{Config, ConfigError, Polarity, ReadyFallback};
+
+pub mod pins;
+pub use pins::{NoPin, OptionalInputPin, OptionalOutputPin};
+
+pub mod events;
+use events::EventQueue;
+
+pub mod fifo;
+
+pub mod protocol;
+
+pub mod checksum;
+pub use checksum::Checksum;
+
+pub mod smbus;
+pub use smbus::Smbus;
+use protocol::{CommandFrame, CommandInterface, Response};
 
-/// Error type combining SPI, I2C, and Pin errors
+pub mod recovery;
+
+pub mod retry;
+pub use retry::{RetryPolicy, RetryStats};
+use retry::Retry;
+
+pub mod timer;
+pub use timer::{Clock, Timer, WithClock};
+use timer::Timeout;
+
+#[cfg(feature = "hal-02")]
+pub mod hal02;
+
+#[cfg(feature = "async")]
+pub mod asynch;
+
+#[cfg(feature = "shared-bus")]
+pub mod bus;
+
+#[cfg(feature = "std")]
+pub mod host;
+
+#[cfg(feature = "mock")]
+pub mod mock;
+
+#[cfg(feature = "sim")]
+pub mod sim;
+
+
+/// Driver operation, used to give context to errors
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Operation {
+    /// Resetting the device
+    Reset,
+    /// Waiting for the device to become ready
+    WaitBusy,
+    /// Reading the register at the provided address
+    Read(u8),
+    /// Writing the register at the provided address
+    Write(u8),
+    /// Sending the command with the provided opcode
+    Command(u8),
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operation::Reset => write!(f, "resetting device"),
+            Operation::WaitBusy => write!(f, "waiting for device ready"),
+            Operation::Read(a) => write!(f, "reading register 0x{:02x}", a),
+            Operation::Write(a) => write!(f, "writing register 0x{:02x}", a),
+            Operation::Command(o) => write!(f, "sending command 0x{:02x}", o),
+        }
+    }
+}
+
+/// Error type combining interface and Pin errors
 /// You can remove anything you don't need / add anything you do
 /// (as well as additional driver-specific values) here
 #[derive(Debug, Clone, PartialEq)]
-pub enum Error<I2cError, SpiError, PinError> {
-    /// Underlying SPI device error
-    Spi(SpiError),
-    /// Underlying I2C device error
-    I2c(I2cError),
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error<IfaceError, PinError> {
+    /// Underlying interface (I2C / SPI) error
+    Interface {
+        /// Operation in progress
+        op: Operation,
+        /// Interface error
+        error: IfaceError,
+    },
     /// Underlying GPIO pin error
-    Pin(PinError),
-    
+    Pin {
+        /// Operation in progress
+        op: Operation,
+        /// Pin error
+        error: PinError,
+    },
+
+    /// Invalid driver configuration
+    Config(ConfigError),
+
     /// Device failed to resume from reset
-    ResetTimeout
+    ResetTimeout,
+
+    /// Register frame checksum mismatch
+    Checksum {
+        /// Operation in progress
+        op: Operation,
+        /// Checksum computed over the received frame
+        expected: u16,
+        /// Checksum received from the device
+        actual: u16,
+    },
+
+    /// Raw register write would sleep or reset the device, use the
+    /// `sleep()` / `reset()` transitions instead
+    StateChange(u8),
+
+    /// Device did not report the expected chip ID
+    UnexpectedDevice {
+        /// Chip ID read from the device
+        found: u8,
+        /// Expected chip ID
+        expected: u8,
+    },
+
+    /// Burst length exceeded `registers::MAX_BURST`
+    BurstLength(usize),
+}
+
+impl<IfaceError, PinError> Error<IfaceError, PinError> {
+    /// Fetch the operation in progress when the error occurred, if any
+    pub fn operation(&self) -> Option<Operation> {
+        match self {
+            Error::Interface { op, .. } | Error::Pin { op, .. } | Error::Checksum { op, .. } => Some(*op),
+            Error::Config(_) => None,
+            Error::ResetTimeout => Some(Operation::WaitBusy),
+            Error::StateChange(a) => Some(Operation::Write(*a)),
+            Error::UnexpectedDevice { .. } => Some(Operation::Read(ChipId::ADDRESS)),
+            Error::BurstLength(_) => None,
+        }
+    }
+
+    /// Classify the error for `RetryPolicy`, using `kind` for interface errors
+    ///
+    /// Errors other than from a bus transaction are never retried
+    fn bus_error_kind(&self, kind: impl FnOnce(&IfaceError) -> BusErrorKind) -> Option<BusErrorKind> {
+        match self {
+            Error::Interface { error, .. } => Some(kind(error)),
+            Error::Checksum { .. } => Some(BusErrorKind::Checksum),
+            _ => None,
+        }
+    }
+
+    /// Helper to wrap an interface error with the operation in progress
+    fn interface(op: Operation) -> impl FnOnce(IfaceError) -> Self {
+        move |error| Error::Interface { op, error }
+    }
+
+    /// Helper to wrap a pin error with the operation in progress
+    fn pin(op: Operation) -> impl FnOnce(PinError) -> Self {
+        move |error| Error::Pin { op, error }
+    }
 }
 
-/// Driver object is generic over peripheral traits 
+impl<IfaceError: fmt::Debug, PinError: fmt::Debug> fmt::Display for Error<IfaceError, PinError> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Interface { op, error } => write!(f, "interface error {}: {:?}", op, error),
+            Error::Pin { op, error } => write!(f, "pin error {}: {:?}", op, error),
+            Error::Config(e) => write!(f, "invalid configuration: {}", e),
+            Error::ResetTimeout => write!(f, "timeout waiting for device reset"),
+            Error::Checksum { op, expected, actual } => {
+                write!(f, "checksum mismatch {}, expected 0x{:04x} got 0x{:04x}", op, expected, actual)
+            }
+            Error::StateChange(a) => {
+                write!(f, "writing register 0x{:02x} would change the device state, use sleep() / reset()", a)
+            }
+            Error::UnexpectedDevice { found, expected } => {
+                write!(f, "unexpected device, found chip ID 0x{:02x} (expected 0x{:02x})", found, expected)
+            }
+            Error::BurstLength(n) => {
+                write!(f, "burst of {} bytes exceeds maximum of {}", n, registers::MAX_BURST)
+            }
+        }
+    }
+}
+
+impl<IfaceError: fmt::Debug, PinError: fmt::Debug> core::error::Error for Error<IfaceError, PinError> {}
+
+/// Driver object is generic over peripheral traits
 /// TODO: Find-and-replace `ExampleDriver` this to match your object
-/// 
-/// - You probably don't need both I2C and SPI, but they're here to show
-///   how they could be used
+///
+/// - Bus access goes through an `Interface` so the same driver logic can
+///   run over I2C (`I2cInterface`) or SPI (`SpiInterface`)
 /// - You should include a unique type for each pin object as some HALs will export different types per-pin or per-bus
-/// 
-pub struct ExampleDriver<I2c, I2cError, Spi, SpiError, CsPin, BusyPin, ResetPin, PinError, Delay> {
+/// - Drivers are written against embedded-hal 1.0, see the `hal02` module (`hal-02` feature)
+///   for adaptors if your HAL still implements 0.2
+/// - `Delay` is any `DelayNs`, or a `WithClock` to measure timeouts against a monotonic clock (see `timer`)
+/// - Reset and busy pins are optional, pass `NoPin` for lines that aren't connected (see `pins`)
+/// - Buses can be shared between drivers with embedded-hal-bus proxies (see `bus`)
+/// - I2C bus recovery can be run automatically on repeated errors (see `recovery`)
+/// - Adjacent registers can be read and written in a single transaction with
+///   `read_regs` and `write_regs`, see `I2cInterface::with_auto_increment`
+/// - Register frames can be protected with a CRC with `Config::checksum` (see `checksum`)
+/// - SMBus devices are supported over I2C with `Smbus` (see `smbus`)
+/// - Bus transactions are retried according to `Config::retry` (see `retry`)
+/// - The chip ID and revision are checked on initialisation (see `device_info`)
+/// - SPI devices can also be driven with opcode framed commands (see `protocol`)
+/// - Measurement frames can be streamed in bulk from the device FIFO (see `fifo`)
+/// - Device interrupts are latched by `on_interrupt` into an `EventQueue` (see `events`)
+/// - The device lifecycle is tracked by the `State` parameter (see `state`), so
+///   invalid operations (such as register access while sleeping) fail at compile time
+///
+pub struct ExampleDriver<Iface, BusyPin, ResetPin, Delay, State = Ready> {
     /// Device configuration
     config: Config,
 
-    /// I2C device
-    i2c: I2c,
-
-    /// SPI device
-    spi: Spi,
-
-    /// Chip select pin (for SPI)
-    /// Technically this _can_ be managed by the HAL, however:
-    ///  - often it is not
-    ///  - some hals do not expose transactional (write-read) methods
-    ///    which are required for interacting with some devices
-    /// So at this time it's easier to manage yourself
-    cs: CsPin,
+    /// Device interface
+    iface: Iface,
 
     /// Busy input pin
     busy: BusyPin,
@@ -60,82 +258,469 @@ pub struct ExampleDriver<I2c, I2cError, Spi, SpiError, CsPin, BusyPin, ResetPin,
     /// Delay implementation
     delay: Delay,
 
-    // Error types must be bound to the object
-    _i2c_err: PhantomData<I2cError>,
-    _spi_err: PhantomData<SpiError>,
-    _pin_err: PhantomData<PinError>,
+    /// Bus retry counters
+    stats: RetryStats,
+
+    /// Device identification, read by `configure()`
+    info: DeviceInfo,
+
+    /// Device state
+    _state: PhantomData<State>,
+}
+
+/// Result of a driver state transition, returning the driver in the `Next` state
+pub type Transition<Iface, BusyPin, ResetPin, Delay, PinError, Next> =
+    Result<ExampleDriver<Iface, BusyPin, ResetPin, Delay, Next>, Error<<Iface as Interface>::Error, PinError>>;
+
+/// (example) Control register value applied after reset
+///
+/// This is shared by the blocking and async drivers so device setup
+/// only needs to be described once
+pub fn init_control() -> Control {
+    let mut c = Control::default();
+    c.set_mode(Mode::Normal);
+    c
 }
 
-/// Driver configuration data
-pub struct Config {
-    /// Device polling time
-    pub poll_ms: u32,
+/// (example) Expected `ChipId` register value
+pub const CHIP_ID: u8 = 0x5a;
+
+/// Device identification, read during initialisation
+///
+/// Use the revision to enable any errata workarounds for your silicon
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DeviceInfo {
+    /// Chip ID, see `CHIP_ID`
+    pub chip_id: u8,
+    /// Silicon revision, see `Revision`
+    pub revision: u8,
 }
 
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            poll_ms: 100,
+impl DeviceInfo {
+    /// Major silicon revision
+    pub fn major(&self) -> u8 {
+        Revision(self.revision).major()
+    }
+
+    /// Minor silicon revision
+    pub fn minor(&self) -> u8 {
+        Revision(self.revision).minor()
+    }
+
+    /// Check the chip ID matches `CHIP_ID`
+    ///
+    /// This is shared by the blocking and async drivers
+    pub fn check<IfaceError, PinError>(&self) -> Result<(), Error<IfaceError, PinError>> {
+        if self.chip_id != CHIP_ID {
+            return Err(Error::UnexpectedDevice { found: self.chip_id, expected: CHIP_ID });
         }
+
+        Ok(())
+    }
+
+    /// Identify the device from its `ChipId` register, returning
+    /// `Error::UnexpectedDevice` if it does not match `CHIP_ID`
+    ///
+    /// This is shared by the blocking and async drivers, the revision
+    /// is only read once the chip ID has been checked
+    pub(crate) fn identify<IfaceError, PinError>(chip_id: ChipId) -> Result<Self, Error<IfaceError, PinError>> {
+        let info = Self { chip_id: chip_id.id(), revision: 0 };
+        info.check()?;
+
+        Ok(info)
     }
 }
 
-/// Device reset timeout
-pub const RESET_TIMEOUT_MS: u32 = 100;
+/// (example) `Command` opcode for a software reset, used when no reset pin is connected
+pub const SOFT_RESET: u8 = 0xb6;
 
-impl<I2c, I2cError, Spi, SpiError, CsPin, BusyPin, ResetPin, PinError, Delay> ExampleDriver <I2c, I2cError, Spi, SpiError, CsPin, BusyPin, ResetPin, PinError, Delay>
+/// (example) Command register value to trigger a software reset
+///
+/// This is shared by the blocking and async drivers
+pub fn soft_reset_command() -> Command {
+    let mut c = Command::default();
+    c.set_opcode(SOFT_RESET);
+    c
+}
+
+impl<Iface, BusyPin, ResetPin, PinError, Delay> ExampleDriver <Iface, BusyPin, ResetPin, Delay, Ready>
 where
-    I2c: i2c::Read<Error = I2cError> + i2c::Write<Error = I2cError>,
-    Spi: spi::Transfer<u8, Error = SpiError> + spi::Write<u8, Error = SpiError>,
-    CsPin: OutputPin<Error = PinError>,
-    BusyPin: InputPin<Error = PinError>,
-    ResetPin: OutputPin<Error = PinError>,
-    Delay: delay::DelayMs<u32>,
+    Iface: Interface,
+    BusyPin: OptionalInputPin<Error = PinError>,
+    ResetPin: OptionalOutputPin<Error = PinError>,
+    Delay: Timer,
 {
     /// Create and initialise a new driver
-    pub fn new(config: Config, i2c: I2c, spi: Spi, cs: CsPin, busy: BusyPin, reset: ResetPin, delay: Delay) -> Result<Self, Error<I2cError, SpiError, PinError>> {
+    pub fn new(config: Config, iface: Iface, busy: BusyPin, reset: ResetPin, delay: Delay) -> Result<Self, Error<Iface::Error, PinError>> {
         // Create the driver object
-        let mut s = Self { 
-            config, i2c, spi, cs, busy, reset, delay,
-            _i2c_err: PhantomData,
-            _spi_err: PhantomData,
-            _pin_err: PhantomData,
-        };
-
-        // Do some setup
-        // note: it's a good idea to check communication here by 
-        // reading out a device version register or similar to ensure
-        // you're actually talking to the device
+        let s = ExampleDriver::new_unconfigured(config, iface, busy, reset, delay)?;
+
+        // Reset, identify and configure the device
+        s.configure()
+    }
+
+    /// Put the device into low power sleep
+    pub fn sleep(mut self) -> Transition<Iface, BusyPin, ResetPin, Delay, PinError, Sleeping> {
+        let c = self.read_reg_unchecked::<Control>()?;
+        self.write_reg_unchecked(state::mode_control(c, Mode::Sleep))?;
+
+        Ok(self.into_state())
+    }
+
+    /// Reset the device, returning it to the unconfigured state
+    pub fn reset(mut self) -> Transition<Iface, BusyPin, ResetPin, Delay, PinError, Unconfigured> {
+        self.reset_device()?;
 
+        Ok(self.into_state())
+    }
+}
+
+impl<Iface, BusyPin, ResetPin, PinError, Delay> ExampleDriver <Iface, BusyPin, ResetPin, Delay, Unconfigured>
+where
+    Iface: Interface,
+    BusyPin: OptionalInputPin<Error = PinError>,
+    ResetPin: OptionalOutputPin<Error = PinError>,
+    Delay: Timer,
+{
+    /// Create a driver without touching the device, use `configure()`
+    /// to reset and configure the device before use
+    pub fn new_unconfigured(config: Config, iface: Iface, busy: BusyPin, reset: ResetPin, delay: Delay) -> Result<Self, Error<Iface::Error, PinError>> {
+        config.validate().map_err(Error::Config)?;
+
+        Ok(Self {
+            config, iface, busy, reset, delay,
+            stats: RetryStats::default(),
+            info: DeviceInfo::default(),
+            _state: PhantomData,
+        })
+    }
+
+    /// Reset and configure the device, returning `Error::UnexpectedDevice`
+    /// if the device does not report the expected chip ID
+    pub fn configure(mut self) -> Transition<Iface, BusyPin, ResetPin, Delay, PinError, Ready> {
         // (example) Reset device
-        s.reset.set_low().map_err(Error::Pin)?;
-        s.delay.delay_ms(10);
-        s.reset.set_high().map_err(Error::Pin)?;
+        self.reset_device()?;
+
+        // Check we're actually talking to the right device
+        self.info = DeviceInfo::identify(self.read_reg::<ChipId>()?)?;
+        self.info.revision = self.read_reg::<Revision>()?.0;
+
+        // (example) Configure the device
+        self.write_reg(init_control())?;
+
+        Ok(self.into_state())
+    }
+}
+
+impl<Iface, BusyPin, ResetPin, PinError, Delay> ExampleDriver <Iface, BusyPin, ResetPin, Delay, Sleeping>
+where
+    Iface: Interface,
+    BusyPin: OptionalInputPin<Error = PinError>,
+    ResetPin: OptionalOutputPin<Error = PinError>,
+    Delay: Timer,
+{
+    /// Wake the device from sleep
+    pub fn wake(mut self) -> Transition<Iface, BusyPin, ResetPin, Delay, PinError, Ready> {
+        let c = self.read_reg_unchecked::<Control>()?;
+        self.write_reg_unchecked(state::mode_control(c, Mode::Normal))?;
+
+        self.wait_busy()?;
+
+        Ok(self.into_state())
+    }
+}
+
+impl<Iface, BusyPin, ResetPin, PinError, Delay, State> ExampleDriver <Iface, BusyPin, ResetPin, Delay, State>
+where
+    Iface: Interface,
+    BusyPin: OptionalInputPin<Error = PinError>,
+    ResetPin: OptionalOutputPin<Error = PinError>,
+    Delay: Timer,
+    State: Awake,
+{
+    /// Read a register from the device
+    pub fn read_reg<R: Readable>(&mut self) -> Result<R, Error<Iface::Error, PinError>> {
+        self.read_reg_unchecked()
+    }
+
+    /// Write a register to the device
+    ///
+    /// Writes that would sleep or reset a `Ready` device return `Error::StateChange`
+    pub fn write_reg<R: Writable>(&mut self, r: R) -> Result<(), Error<Iface::Error, PinError>> {
+        let mut buff = [0u8; registers::MAX_WIDTH];
+        r.to_bytes(&mut buff[..R::WIDTH]);
+
+        self.write_regs(R::ADDRESS, &buff[..R::WIDTH])
+    }
+
+    /// Read `buff.len()` bytes from consecutive registers starting at `start`,
+    /// in a single transaction using the device address auto-increment
+    ///
+    /// Bursts are limited to `registers::MAX_BURST` bytes
+    pub fn read_regs(&mut self, start: u8, buff: &mut [u8]) -> Result<(), Error<Iface::Error, PinError>> {
+        self.read_burst(start, buff, true)
+    }
+
+    /// Write `data` to consecutive registers starting at `start`,
+    /// in a single transaction using the device address auto-increment
+    ///
+    /// Bursts are limited to `registers::MAX_BURST` bytes, and writes that would
+    /// sleep or reset a `Ready` device return `Error::StateChange`
+    pub fn write_regs(&mut self, start: u8, data: &[u8]) -> Result<(), Error<Iface::Error, PinError>> {
+        if data.len() <= registers::MAX_BURST {
+            state::check_write::<State>(start, data).map_err(Error::StateChange)?;
+        }
+
+        self.write_burst(start, data)
+    }
+
+    /// Read-modify-write a register, returning the value written
+    pub fn modify_reg<R, F>(&mut self, f: F) -> Result<R, Error<Iface::Error, PinError>>
+    where
+        R: Readable + Writable,
+        F: FnOnce(&mut R),
+    {
+        let mut r = self.read_reg::<R>()?;
+        f(&mut r);
 
-        // (example) Wait on busy
-        let mut timeout = 0;
-        while s.busy.is_low().map_err(Error::Pin)? {
+        self.write_reg(r)?;
+
+        Ok(r)
+    }
+
+    /// Enable the provided device interrupts, enabling the interrupt
+    /// output if any are set (or disabling it if none are)
+    pub fn enable_interrupts(&mut self, mask: IrqEnable) -> Result<(), Error<Iface::Error, PinError>> {
+        self.write_reg(mask)?;
+
+        self.modify_reg::<Control, _>(|c| {
+            c.set_irq_enable(mask.raw() != 0);
+        })?;
+
+        Ok(())
+    }
+
+    /// Handle a device interrupt, call this from your interrupt handler
+    ///
+    /// This reads (and so clears) the device interrupt status, pushing
+    /// the decoded events to `events` for the main loop to consume with
+    /// `EventQueue::poll_events`. Events are dropped if the queue is full.
+    pub fn on_interrupt<const N: usize>(&mut self, events: &EventQueue<N>) -> Result<IrqStatus, Error<Iface::Error, PinError>> {
+        let status = self.read_reg::<IrqStatus>()?;
+
+        events.latch(status);
+
+        Ok(status)
+    }
+}
+
+impl<Iface, BusyPin, ResetPin, PinError, Delay, State> ExampleDriver <Iface, BusyPin, ResetPin, Delay, State>
+where
+    Iface: CommandInterface,
+    BusyPin: OptionalInputPin<Error = PinError>,
+    ResetPin: OptionalOutputPin<Error = PinError>,
+    Delay: Timer,
+    State: Awake,
+{
+    /// Send a command with the provided address and payload,
+    /// returning the decoded status and response payload
+    pub fn command(&mut self, cmd: protocol::Command, address: u32, payload: &[u8]) -> Result<Response, Error<Iface::Error, PinError>> {
+        let mut frame = CommandFrame::new(&cmd, address);
+        let (header, buff) = frame.buffers();
+
+        let op = Operation::Command(cmd.opcode());
+        let status = self.with_retry(|iface| iface.command(header, payload, buff).map_err(Error::interface(op)))?;
+
+        Ok(frame.response(status))
+    }
+}
+
+impl<Iface, BusyPin, ResetPin, PinError, Delay, State> ExampleDriver <Iface, BusyPin, ResetPin, Delay, State>
+where
+    Iface: Interface,
+    BusyPin: OptionalInputPin<Error = PinError>,
+    ResetPin: OptionalOutputPin<Error = PinError>,
+    Delay: Timer,
+{
+    /// Release the driver, returning the owned peripherals
+    /// (this does not change the device state)
+    pub fn free(self) -> (Iface, BusyPin, ResetPin, Delay) {
+        (self.iface, self.busy, self.reset, self.delay)
+    }
+
+    /// Fetch the device identification read by `configure()`
+    pub fn device_info(&self) -> DeviceInfo {
+        self.info
+    }
+
+    /// Fetch bus transaction retry counters
+    pub fn retry_stats(&self) -> RetryStats {
+        self.stats
+    }
+
+    /// Clear bus transaction retry counters
+    pub fn clear_retry_stats(&mut self) {
+        self.stats = RetryStats::default();
+    }
+
+    /// Wait for the device to become ready, returning `Error::ResetTimeout`
+    /// if it does not become ready within the configured reset timeout
+    ///
+    /// This uses the busy pin if connected, otherwise `Config::ready_fallback`
+    pub fn wait_busy(&mut self) -> Result<(), Error<Iface::Error, PinError>> {
+        if !self.busy.is_connected() {
+            if let ReadyFallback::Delay(ms) = self.config.ready_fallback {
+                self.delay.delay_ms(ms);
+                return Ok(());
+            }
+        }
+
+        let mut timeout = Timeout::new(self.delay.now_ms(), self.config.reset_timeout_ms);
+        while self.is_busy()? {
             // Wait for the poll period
-            timeout += s.config.poll_ms;
-            s.delay.delay_ms(s.config.poll_ms);
+            self.delay.delay_ms(self.config.poll_ms);
 
             // Check for timeout
-            if timeout > RESET_TIMEOUT_MS {
+            if timeout.expired(self.delay.now_ms(), self.config.poll_ms) {
                 return Err(Error::ResetTimeout);
             }
         }
 
-        // (example) Write something to I2C
-        s.i2c.write(0x01, &[0x01, 0x02]).map_err(Error::I2c)?;
+        Ok(())
+    }
+
+    /// Check whether the device is busy, using the status register
+    /// if no busy pin is connected
+    fn is_busy(&mut self) -> Result<bool, Error<Iface::Error, PinError>> {
+        if !self.busy.is_connected() {
+            return Ok(self.read_reg_unchecked::<Status>()?.busy());
+        }
+
+        let high = self.busy.level().map_err(Error::pin(Operation::WaitBusy))?;
+
+        Ok(high == self.config.busy_polarity.level(true))
+    }
+
+    /// Assert or release the reset line
+    fn set_reset(&mut self, asserted: bool) -> Result<(), Error<Iface::Error, PinError>> {
+        let level = self.config.reset_polarity.level(asserted);
+
+        self.reset.set_level(level).map_err(Error::pin(Operation::Reset))
+    }
+
+    /// Reset the device and wait for it to become ready
+    ///
+    /// This pulses the reset line, or issues a software reset
+    /// if no reset pin is connected
+    fn reset_device(&mut self) -> Result<(), Error<Iface::Error, PinError>> {
+        if self.reset.is_connected() {
+            self.set_reset(true)?;
+            self.delay.delay_ms(self.config.reset_pulse_ms);
+            self.set_reset(false)?;
+        } else {
+            self.write_reg_unchecked(soft_reset_command())?;
+        }
+
+        // Some devices need time before the busy line is valid
+        if self.config.reset_settle_ms > 0 {
+            self.delay.delay_ms(self.config.reset_settle_ms);
+        }
+
+        self.wait_busy()
+    }
+
+    /// Read a register irrespective of device state
+    fn read_reg_unchecked<R: Readable>(&mut self) -> Result<R, Error<Iface::Error, PinError>> {
+        let mut buff = [0u8; registers::MAX_WIDTH];
+        self.read_burst(R::ADDRESS, &mut buff[..R::WIDTH], true)?;
+
+        Ok(R::from_bytes(&buff[..R::WIDTH]))
+    }
+
+    /// Write a register irrespective of device state
+    fn write_reg_unchecked<R: Writable>(&mut self, r: R) -> Result<(), Error<Iface::Error, PinError>> {
+        let mut buff = [0u8; registers::MAX_WIDTH];
+        r.to_bytes(&mut buff[..R::WIDTH]);
+
+        self.write_burst(R::ADDRESS, &buff[..R::WIDTH])
+    }
+
+    /// Read a burst from `start` in a single transaction, with or without auto-incrementing
+    /// the register address, checking the frame checksum if enabled
+    fn read_burst(&mut self, start: u8, data: &mut [u8], increment: bool) -> Result<(), Error<Iface::Error, PinError>> {
+        let checksum = self.config.checksum;
+
+        let mut buff = [0u8; checksum::MAX_FRAME];
+        let frame = checksum.frame(&mut buff, data.len())?;
 
-        // (example) Write something to SPI (using manual CS)
-        s.cs.set_low().map_err(Error::Pin)?;
-        s.spi.write(&[0x02, 0x03]).map_err(Error::Spi)?;
-        s.cs.set_high().map_err(Error::Pin)?;
+        // The checksum is checked within the retry, so mismatches are retried
+        self.with_retry(|iface| {
+            let r = match increment {
+                true => iface.read_register(start, frame),
+                false => iface.read_register_fixed(start, frame),
+            };
+            r.map_err(Error::interface(Operation::Read(start)))?;
 
-        // Return the object
-        Ok(s)
+            checksum.frame_verify(Operation::Read(start), frame, data, |len, header| {
+                let reg = match increment {
+                    true => iface.register_address(start, len),
+                    false => start,
+                };
+                iface.checksum_header(reg, true, header)
+            })
+        })
     }
 
+    /// Write consecutive registers in a single transaction, appending the frame checksum if enabled
+    fn write_burst(&mut self, start: u8, data: &[u8]) -> Result<(), Error<Iface::Error, PinError>> {
+        let checksum = self.config.checksum;
 
+        let mut buff = [0u8; checksum::MAX_FRAME];
+        let frame = checksum.frame(&mut buff, data.len())?;
+
+        let iface = &self.iface;
+        checksum.frame_write(frame, data, |len, header| {
+            iface.checksum_header(iface.register_address(start, len), false, header)
+        });
+
+        self.with_retry(|iface| iface.write_register(start, frame).map_err(Error::interface(Operation::Write(start))))
+    }
+
+    /// Run a bus transaction, retrying according to the configured retry policy
+    fn with_retry<T, F>(&mut self, mut f: F) -> Result<T, Error<Iface::Error, PinError>>
+    where
+        F: FnMut(&mut Iface) -> Result<T, Error<Iface::Error, PinError>>,
+    {
+        let mut retry = Retry::new();
+        loop {
+            let e = match f(&mut self.iface) {
+                Ok(v) => {
+                    retry.succeeded(&mut self.stats);
+                    return Ok(v);
+                }
+                Err(e) => e,
+            };
+
+            let kind = e.bus_error_kind(Iface::error_kind);
+            match retry.failed(&self.config.retry, &mut self.stats, kind) {
+                Some(backoff_ms) => self.delay.delay_ms(backoff_ms),
+                None => return Err(e),
+            }
+        }
+    }
+
+    /// Move the driver into a new state
+    fn into_state<Next>(self) -> ExampleDriver<Iface, BusyPin, ResetPin, Delay, Next> {
+        ExampleDriver {
+            config: self.config,
+            iface: self.iface,
+            busy: self.busy,
+            reset: self.reset,
+            delay: self.delay,
+            stats: self.stats,
+            info: self.info,
+            _state: PhantomData,
+        }
+    }
 }
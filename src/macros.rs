@@ -0,0 +1,186 @@
+//! Register definition macros
+//!
+//! `register!` generates a register type implementing `registers::Register`
+//! with typed getters and setters for each field, `field_enum!` generates
+//! enums for multi-bit fields.
+
+/// Define a register and its fields
+///
+/// Fields are specified datasheet-style as `[msb:lsb]` and may be `bool`,
+/// an unsigned integer, or an enum created with `field_enum!`. Field ranges
+/// are checked at compile time against each other and the register width.
+///
+/// ```
+/// # use driver_example::{register, field_enum};
+/// field_enum! {
+///     /// Operating mode
+///     pub enum Mode {
+///         Sleep = 0,
+///         Normal = 1,
+///     }
+/// }
+///
+/// register! {
+///     /// Control register
+///     pub struct Control: u8 {
+///         const ADDRESS = 0x01;
+///         const ACCESS = ReadWrite;
+///         const RESET = 0x00;
+///
+///         /// Device enable
+///         enable, set_enable: [0:0] as bool;
+///         /// Operating mode (returns `None` for reserved values)
+///         mode, set_mode: [2:1] as Mode;
+///     }
+/// }
+///
+/// let mut c = Control::default();
+/// c.set_enable(true).set_mode(Mode::Normal);
+/// assert_eq!(c.0, 0x03);
+/// ```
+///
+/// Overlapping fields (or fields wider than the register) fail to compile:
+///
+/// ```compile_fail
+/// # use driver_example::register;
+/// register! {
+///     pub struct Bad: u8 {
+///         const ADDRESS = 0x00;
+///         const ACCESS = ReadOnly;
+///         const RESET = 0x00;
+///
+///         a, set_a: [3:0] as u8;
+///         b, set_b: [4:3] as u8;
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! register {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident: $raw:ty {
+            const ADDRESS = $addr:expr;
+            const ACCESS = $access:ident;
+            const RESET = $reset:expr;
+
+            $(
+                $(#[$fmeta:meta])*
+                $get:ident, $set:ident: [$msb:literal : $lsb:literal] as $fty:ty;
+            )*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        $vis struct $name(pub $raw);
+
+        impl $crate::registers::Register for $name {
+            const ADDRESS: u8 = $addr;
+            const WIDTH: usize = ::core::mem::size_of::<$raw>();
+            const ACCESS: $crate::registers::Access = $crate::registers::Access::$access;
+            const RESET: Self = Self($reset);
+
+            fn from_raw(raw: u32) -> Self {
+                Self(raw as $raw)
+            }
+
+            fn raw(&self) -> u32 {
+                self.0 as u32
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                <Self as $crate::registers::Register>::RESET
+            }
+        }
+
+        $crate::register!(@access $name, $access);
+
+        #[allow(dead_code)]
+        impl $name {
+            $(
+                $(#[$fmeta])*
+                pub fn $get(&self) -> <$fty as $crate::registers::Field>::Read {
+                    let mask = $crate::registers::field_mask($msb, $lsb);
+                    <$fty as $crate::registers::Field>::from_bits((self.0 as u32 & mask) >> $lsb)
+                }
+
+                $(#[$fmeta])*
+                pub fn $set(&mut self, v: $fty) -> &mut Self {
+                    let mask = $crate::registers::field_mask($msb, $lsb);
+                    let bits = (<$fty as $crate::registers::Field>::into_bits(v) << $lsb) & mask;
+                    self.0 = ((self.0 as u32 & !mask) | bits) as $raw;
+                    self
+                }
+            )*
+        }
+
+        // Check fields are well formed, fit in the register, and don't overlap
+        const _: () = {
+            let width = ::core::mem::size_of::<$raw>() * 8;
+            #[allow(unused_mut)]
+            let mut used = 0u32;
+            $(
+                assert!($msb >= $lsb, concat!(stringify!($name), ".", stringify!($get), ": msb must be >= lsb"));
+                assert!($msb < width, concat!(stringify!($name), ".", stringify!($get), ": field exceeds register width"));
+
+                let mask = $crate::registers::field_mask($msb, $lsb);
+                assert!(used & mask == 0, concat!(stringify!($name), ".", stringify!($get), ": field overlaps another field"));
+                used |= mask;
+            )*
+            let _ = used;
+        };
+    };
+
+    (@access $name:ident, ReadOnly) => {
+        impl $crate::registers::Readable for $name {}
+    };
+    (@access $name:ident, ReadWrite) => {
+        impl $crate::registers::Readable for $name {}
+        impl $crate::registers::Writable for $name {}
+    };
+    (@access $name:ident, WriteOnly) => {
+        impl $crate::registers::Writable for $name {}
+    };
+}
+
+/// Define an enum for use as a multi-bit register field
+///
+/// Reading the field returns `None` if the device reports a value
+/// without a matching variant.
+#[macro_export]
+macro_rules! field_enum {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $(
+                $(#[$vmeta:meta])*
+                $variant:ident = $value:literal,
+            )*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        $vis enum $name {
+            $(
+                $(#[$vmeta])*
+                $variant = $value,
+            )*
+        }
+
+        impl $crate::registers::Field for $name {
+            type Read = Option<Self>;
+
+            fn from_bits(bits: u32) -> Option<Self> {
+                match bits {
+                    $( $value => Some(Self::$variant), )*
+                    _ => None,
+                }
+            }
+
+            fn into_bits(self) -> u32 {
+                self as u32
+            }
+        }
+    };
+}
@@ -0,0 +1,620 @@
+//! Mock peripherals for testing (requires the `mock` feature)
+//!
+//! Each mock is created with a list of expected transactions, which are
+//! checked (in order) as the driver uses the peripheral. Mocks are cheap to
+//! clone and clones share state, so keep a clone to call `done()` on once
+//! the driver has taken ownership.
+//!
+//! ```
+//! use driver_example::mock::{i2c, pin, delay};
+//!
+//! let mut i2c = i2c::Mock::new(&[
+//!     i2c::Transaction::write(0x01, &[0x01, 0x02]),
+//! ]);
+//!
+//! // ... exercise the driver with `i2c.clone()` ...
+//! # use embedded_hal::i2c::I2c;
+//! # i2c.clone().write(0x01, &[0x01, 0x02]).unwrap();
+//!
+//! i2c.done();
+//! ```
+//!
+//! With the `hal-02` feature the mocks also implement the embedded-hal 0.2
+//! blocking traits, for testing the `hal02` adaptors. Each 0.2 SPI call is
+//! checked as a separate transaction, as there is no chip select framing.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::vec;
+use std::vec::Vec;
+
+use embedded_hal::{digital, i2c as hal_i2c, spi as hal_spi};
+
+
+/// Mock error type, used by all mock peripherals
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MockError {
+    /// I2C error of the provided kind
+    I2c(hal_i2c::ErrorKind),
+    /// SPI error of the provided kind
+    Spi(hal_spi::ErrorKind),
+    /// GPIO pin error
+    Pin,
+}
+
+impl hal_i2c::Error for MockError {
+    fn kind(&self) -> hal_i2c::ErrorKind {
+        match self {
+            MockError::I2c(k) => *k,
+            _ => hal_i2c::ErrorKind::Other,
+        }
+    }
+}
+
+impl hal_spi::Error for MockError {
+    fn kind(&self) -> hal_spi::ErrorKind {
+        match self {
+            MockError::Spi(k) => *k,
+            _ => hal_spi::ErrorKind::Other,
+        }
+    }
+}
+
+impl digital::Error for MockError {
+    fn kind(&self) -> digital::ErrorKind {
+        digital::ErrorKind::Other
+    }
+}
+
+
+/// Generic mock, checks operations against a queue of expectations
+#[derive(Debug)]
+pub struct Generic<T> {
+    name: &'static str,
+    expected: Arc<Mutex<VecDeque<T>>>,
+}
+
+impl<T> Clone for Generic<T> {
+    fn clone(&self) -> Self {
+        Self { name: self.name, expected: self.expected.clone() }
+    }
+}
+
+impl<T: fmt::Debug + Clone> Generic<T> {
+    fn with_name(name: &'static str, expected: &[T]) -> Self {
+        Self {
+            name,
+            expected: Arc::new(Mutex::new(expected.iter().cloned().collect())),
+        }
+    }
+
+    /// Append expectations to the mock
+    pub fn expect(&mut self, expected: &[T]) {
+        self.expected.lock().unwrap().extend(expected.iter().cloned());
+    }
+
+    /// Assert all expectations have been consumed
+    pub fn done(&mut self) {
+        let e = self.expected.lock().unwrap();
+        assert!(e.is_empty(), "{} mock has unmet expectations: {:?}", self.name, e);
+    }
+
+    /// Fetch the next expectation, panicking if there are none remaining
+    fn next(&mut self) -> T {
+        match self.expected.lock().unwrap().pop_front() {
+            Some(t) => t,
+            None => panic!("unexpected {} operation, no expectations remaining", self.name),
+        }
+    }
+}
+
+
+/// Mock I2C bus
+pub mod i2c {
+    use super::*;
+    use embedded_hal::i2c::{ErrorKind, ErrorType, I2c, Operation, SevenBitAddress};
+
+    /// I2C transaction kind
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum Kind {
+        /// Write the provided bytes
+        Write(Vec<u8>),
+        /// Read, returning the provided bytes
+        Read(Vec<u8>),
+        /// Write then read (with a repeated start)
+        WriteRead(Vec<u8>, Vec<u8>),
+    }
+
+    /// Expected I2C transaction
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Transaction {
+        /// Device address
+        pub addr: u8,
+        /// Transaction kind
+        pub kind: Kind,
+        /// Error to return (after checking written data)
+        pub err: Option<MockError>,
+    }
+
+    impl Transaction {
+        /// Expect a write of `data` to `addr`
+        pub fn write(addr: u8, data: &[u8]) -> Self {
+            Self { addr, kind: Kind::Write(data.to_vec()), err: None }
+        }
+
+        /// Expect a read from `addr`, responding with `data`
+        pub fn read(addr: u8, data: &[u8]) -> Self {
+            Self { addr, kind: Kind::Read(data.to_vec()), err: None }
+        }
+
+        /// Expect a write of `write` then read from `addr`, responding with `read`
+        pub fn write_read(addr: u8, write: &[u8], read: &[u8]) -> Self {
+            Self { addr, kind: Kind::WriteRead(write.to_vec(), read.to_vec()), err: None }
+        }
+
+        /// Fail the transaction with the provided error kind
+        pub fn with_error(mut self, kind: ErrorKind) -> Self {
+            self.err = Some(MockError::I2c(kind));
+            self
+        }
+    }
+
+    /// Mock I2C bus
+    pub type Mock = Generic<Transaction>;
+
+    impl Mock {
+        /// Create a new mock I2C bus with the provided expectations
+        pub fn new(expected: &[Transaction]) -> Self {
+            Generic::with_name("i2c", expected)
+        }
+    }
+
+    impl ErrorType for Mock {
+        type Error = MockError;
+    }
+
+    /// Bus segment, adjacent operations of the same type are merged
+    /// as they are on the wire
+    enum Segment {
+        Write(Vec<u8>),
+        Read(Vec<usize>),
+    }
+
+    /// Copy expected read data into the read operations of a segment
+    fn fill(ops: &mut [Operation<'_>], indices: &[usize], data: &[u8]) {
+        let len: usize = indices.iter().map(|i| match &ops[*i] {
+            Operation::Read(b) => b.len(),
+            _ => 0,
+        }).sum();
+        assert_eq!(len, data.len(), "i2c read length mismatch");
+
+        let mut data = data;
+        for i in indices {
+            if let Operation::Read(b) = &mut ops[*i] {
+                let (d, rest) = data.split_at(b.len());
+                b.copy_from_slice(d);
+                data = rest;
+            }
+        }
+    }
+
+    impl I2c<SevenBitAddress> for Mock {
+        fn transaction(&mut self, address: u8, operations: &mut [Operation<'_>]) -> Result<(), Self::Error> {
+            // Merge operations into bus segments
+            let mut segments: Vec<Segment> = Vec::new();
+            for (i, op) in operations.iter().enumerate() {
+                match (op, segments.last_mut()) {
+                    (Operation::Write(d), Some(Segment::Write(w))) => w.extend_from_slice(d),
+                    (Operation::Write(d), _) => segments.push(Segment::Write(d.to_vec())),
+                    (Operation::Read(_), Some(Segment::Read(r))) => r.push(i),
+                    (Operation::Read(_), _) => segments.push(Segment::Read(vec![i])),
+                }
+            }
+
+            // Write then read is matched as a single transaction
+            if let [Segment::Write(w), Segment::Read(r)] = segments.as_slice() {
+                let t = self.next();
+                assert_eq!(t.addr, address, "i2c address mismatch");
+
+                match &t.kind {
+                    Kind::WriteRead(ew, er) => {
+                        assert_eq!(ew, w, "i2c write_read data mismatch");
+                        if let Some(e) = t.err {
+                            return Err(e);
+                        }
+                        fill(operations, r, er);
+                    }
+                    k => panic!("i2c expected {:?}, got write_read({:02x?})", k, w),
+                }
+
+                return Ok(());
+            }
+
+            for s in &segments {
+                let t = self.next();
+                assert_eq!(t.addr, address, "i2c address mismatch");
+
+                match (&t.kind, s) {
+                    (Kind::Write(e), Segment::Write(w)) => {
+                        assert_eq!(e, w, "i2c write data mismatch");
+                    }
+                    (Kind::Read(e), Segment::Read(r)) => {
+                        if t.err.is_none() {
+                            fill(operations, r, e);
+                        }
+                    }
+                    (k, Segment::Write(w)) => panic!("i2c expected {:?}, got write({:02x?})", k, w),
+                    (k, Segment::Read(_)) => panic!("i2c expected {:?}, got read", k),
+                }
+
+                if let Some(e) = t.err {
+                    return Err(e);
+                }
+            }
+
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "hal-02")]
+    impl embedded_hal_02::blocking::i2c::Write for Mock {
+        type Error = MockError;
+
+        fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+            I2c::write(self, address, bytes)
+        }
+    }
+
+    #[cfg(feature = "hal-02")]
+    impl embedded_hal_02::blocking::i2c::WriteRead for Mock {
+        type Error = MockError;
+
+        fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), Self::Error> {
+            I2c::write_read(self, address, bytes, buffer)
+        }
+    }
+}
+
+
+/// Mock SPI device
+pub mod spi {
+    use super::*;
+    use embedded_hal::spi::{ErrorKind, ErrorType, Operation, SpiDevice};
+
+    /// SPI operation within a (chip select framed) transaction
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum Op {
+        /// Write the provided bytes
+        Write(Vec<u8>),
+        /// Read, returning the provided bytes
+        Read(Vec<u8>),
+        /// Full duplex transfer, writing the first and returning the second
+        Transfer(Vec<u8>, Vec<u8>),
+        /// Delay within the transaction
+        DelayNs(u32),
+    }
+
+    /// Expected SPI transaction
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Transaction {
+        /// Operations within the transaction
+        pub ops: Vec<Op>,
+        /// Error to return (after checking written data)
+        pub err: Option<MockError>,
+    }
+
+    impl Transaction {
+        /// Expect a transaction containing the provided operations
+        ///
+        /// Adjacent writes (and reads) are merged as they are on the wire,
+        /// so a register write is `[Op::Write(vec![reg, data..])]`
+        pub fn new(ops: &[Op]) -> Self {
+            Self { ops: ops.to_vec(), err: None }
+        }
+
+        /// Expect a transaction writing `data`
+        pub fn write(data: &[u8]) -> Self {
+            Self::new(&[Op::Write(data.to_vec())])
+        }
+
+        /// Expect a transaction writing `write` then reading, responding with `read`
+        pub fn write_read(write: &[u8], read: &[u8]) -> Self {
+            Self::new(&[Op::Write(write.to_vec()), Op::Read(read.to_vec())])
+        }
+
+        /// Fail the transaction with the provided error kind
+        pub fn with_error(mut self, kind: ErrorKind) -> Self {
+            self.err = Some(MockError::Spi(kind));
+            self
+        }
+    }
+
+    /// Mock SPI device
+    pub type Mock = Generic<Transaction>;
+
+    impl Mock {
+        /// Create a new mock SPI device with the provided expectations
+        pub fn new(expected: &[Transaction]) -> Self {
+            Generic::with_name("spi", expected)
+        }
+    }
+
+    impl ErrorType for Mock {
+        type Error = MockError;
+    }
+
+    impl SpiDevice<u8> for Mock {
+        fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error> {
+            let t = self.next();
+
+            // Merge adjacent writes for comparison, noting where read data should go
+            // (empty writes and reads don't appear on the wire so are skipped)
+            let mut actual: Vec<Op> = Vec::new();
+            for op in operations.iter() {
+                match (op, actual.last_mut()) {
+                    (Operation::Write([]), _) | (Operation::Read([]), _) => (),
+                    (Operation::Write(d), Some(Op::Write(w))) => w.extend_from_slice(d),
+                    (Operation::Write(d), _) => actual.push(Op::Write(d.to_vec())),
+                    (Operation::Read(b), Some(Op::Read(r))) => r.resize(r.len() + b.len(), 0),
+                    (Operation::Read(b), _) => actual.push(Op::Read(vec![0; b.len()])),
+                    (Operation::Transfer(r, w), _) => actual.push(Op::Transfer(w.to_vec(), vec![0; r.len()])),
+                    (Operation::TransferInPlace(b), _) => actual.push(Op::Transfer(b.to_vec(), vec![0; b.len()])),
+                    (Operation::DelayNs(ns), _) => actual.push(Op::DelayNs(*ns)),
+                }
+            }
+
+            assert_eq!(t.ops.len(), actual.len(), "spi transaction mismatch, expected {:02x?} got {:02x?}", t.ops, actual);
+
+            // Check written data and collect read data
+            let mut reads: Vec<u8> = Vec::new();
+            for (e, a) in t.ops.iter().zip(actual.iter()) {
+                match (e, a) {
+                    (Op::Write(e), Op::Write(a)) => assert_eq!(e, a, "spi write data mismatch"),
+                    (Op::Read(e), Op::Read(a)) => {
+                        assert_eq!(e.len(), a.len(), "spi read length mismatch");
+                        reads.extend_from_slice(e);
+                    }
+                    (Op::Transfer(ew, er), Op::Transfer(aw, ar)) => {
+                        assert_eq!(ew, aw, "spi transfer data mismatch");
+                        assert_eq!(er.len(), ar.len(), "spi transfer length mismatch");
+                        reads.extend_from_slice(er);
+                    }
+                    (Op::DelayNs(e), Op::DelayNs(a)) => assert_eq!(e, a, "spi delay mismatch"),
+                    (e, a) => panic!("spi expected {:02x?}, got {:02x?}", e, a),
+                }
+            }
+
+            if let Some(e) = t.err {
+                return Err(e);
+            }
+
+            // Return read data
+            let mut reads = reads.as_slice();
+            for op in operations.iter_mut() {
+                let b: &mut [u8] = match op {
+                    Operation::Read(b) => b,
+                    Operation::Transfer(r, _) => r,
+                    Operation::TransferInPlace(b) => b,
+                    _ => continue,
+                };
+
+                let (d, rest) = reads.split_at(b.len());
+                b.copy_from_slice(d);
+                reads = rest;
+            }
+
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "hal-02")]
+    impl embedded_hal_02::blocking::spi::Write<u8> for Mock {
+        type Error = MockError;
+
+        fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+            SpiDevice::write(self, words)
+        }
+    }
+
+    #[cfg(feature = "hal-02")]
+    impl embedded_hal_02::blocking::spi::Transfer<u8> for Mock {
+        type Error = MockError;
+
+        fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Self::Error> {
+            SpiDevice::transfer_in_place(self, words)?;
+            Ok(words)
+        }
+    }
+}
+
+
+/// Mock GPIO pin
+pub mod pin {
+    use super::*;
+    use embedded_hal::digital::{ErrorType, InputPin, OutputPin};
+
+    /// Pin state
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum State {
+        /// Logic low
+        Low,
+        /// Logic high
+        High,
+    }
+
+    /// Pin transaction kind
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Kind {
+        /// Pin is set to the provided state
+        Set(State),
+        /// Pin is read, returning the provided state
+        Get(State),
+    }
+
+    /// Expected pin transaction
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct Transaction {
+        /// Transaction kind
+        pub kind: Kind,
+        /// Error to return
+        pub err: Option<MockError>,
+    }
+
+    impl Transaction {
+        /// Expect the pin to be set to `state`
+        pub fn set(state: State) -> Self {
+            Self { kind: Kind::Set(state), err: None }
+        }
+
+        /// Expect the pin to be read, returning `state`
+        pub fn get(state: State) -> Self {
+            Self { kind: Kind::Get(state), err: None }
+        }
+
+        /// Fail the operation with a pin error
+        pub fn with_error(mut self) -> Self {
+            self.err = Some(MockError::Pin);
+            self
+        }
+    }
+
+    /// Mock GPIO pin
+    pub type Mock = Generic<Transaction>;
+
+    impl Mock {
+        /// Create a new mock pin with the provided expectations
+        pub fn new(expected: &[Transaction]) -> Self {
+            Generic::with_name("pin", expected)
+        }
+
+        fn get(&mut self) -> Result<State, MockError> {
+            let t = self.next();
+            match (t.kind, t.err) {
+                (Kind::Get(_), Some(e)) => Err(e),
+                (Kind::Get(s), None) => Ok(s),
+                (k, _) => panic!("pin expected {:?}, got get", k),
+            }
+        }
+
+        fn set(&mut self, state: State) -> Result<(), MockError> {
+            let t = self.next();
+            match t.kind {
+                Kind::Set(s) => assert_eq!(s, state, "pin set state mismatch"),
+                k => panic!("pin expected {:?}, got set({:?})", k, state),
+            }
+            t.err.map_or(Ok(()), Err)
+        }
+    }
+
+    impl ErrorType for Mock {
+        type Error = MockError;
+    }
+
+    impl InputPin for Mock {
+        fn is_high(&mut self) -> Result<bool, Self::Error> {
+            self.get().map(|s| s == State::High)
+        }
+
+        fn is_low(&mut self) -> Result<bool, Self::Error> {
+            self.get().map(|s| s == State::Low)
+        }
+    }
+
+    impl OutputPin for Mock {
+        fn set_low(&mut self) -> Result<(), Self::Error> {
+            self.set(State::Low)
+        }
+
+        fn set_high(&mut self) -> Result<(), Self::Error> {
+            self.set(State::High)
+        }
+    }
+
+    #[cfg(feature = "hal-02")]
+    impl embedded_hal_02::digital::v2::InputPin for Mock {
+        type Error = MockError;
+
+        fn is_high(&self) -> Result<bool, Self::Error> {
+            self.clone().get().map(|s| s == State::High)
+        }
+
+        fn is_low(&self) -> Result<bool, Self::Error> {
+            self.clone().get().map(|s| s == State::Low)
+        }
+    }
+
+    #[cfg(feature = "hal-02")]
+    impl embedded_hal_02::digital::v2::OutputPin for Mock {
+        type Error = MockError;
+
+        fn set_low(&mut self) -> Result<(), Self::Error> {
+            self.set(State::Low)
+        }
+
+        fn set_high(&mut self) -> Result<(), Self::Error> {
+            self.set(State::High)
+        }
+    }
+}
+
+
+/// Mock delay
+pub mod delay {
+    use super::*;
+    use embedded_hal::delay::DelayNs;
+
+    /// Expected delay
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Transaction {
+        /// Delay in nanoseconds
+        Ns(u32),
+        /// Delay in microseconds
+        Us(u32),
+        /// Delay in milliseconds
+        Ms(u32),
+    }
+
+    /// Mock delay, returns immediately after checking the requested delay
+    pub type Mock = Generic<Transaction>;
+
+    impl Mock {
+        /// Create a new mock delay with the provided expectations
+        pub fn new(expected: &[Transaction]) -> Self {
+            Generic::with_name("delay", expected)
+        }
+
+        fn check(&mut self, actual: Transaction) {
+            let t = self.next();
+            assert_eq!(t, actual, "delay mismatch");
+        }
+    }
+
+    impl DelayNs for Mock {
+        fn delay_ns(&mut self, ns: u32) {
+            self.check(Transaction::Ns(ns))
+        }
+
+        fn delay_us(&mut self, us: u32) {
+            self.check(Transaction::Us(us))
+        }
+
+        fn delay_ms(&mut self, ms: u32) {
+            self.check(Transaction::Ms(ms))
+        }
+    }
+
+    #[cfg(feature = "hal-02")]
+    impl embedded_hal_02::blocking::delay::DelayUs<u32> for Mock {
+        fn delay_us(&mut self, us: u32) {
+            self.check(Transaction::Us(us))
+        }
+    }
+
+    #[cfg(feature = "hal-02")]
+    impl embedded_hal_02::blocking::delay::DelayMs<u32> for Mock {
+        fn delay_ms(&mut self, ms: u32) {
+            self.check(Transaction::Ms(ms))
+        }
+    }
+}
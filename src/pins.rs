@@ -0,0 +1,153 @@
+//! Optional pin support
+//!
+//! Not every board connects the device reset and busy lines, so the driver
+//! accepts any `OptionalOutputPin` / `OptionalInputPin`. These are implemented
+//! for all embedded-hal pins, and for `NoPin` where a line is not connected.
+//! In this case the driver falls back to a software reset command, and
+//! `Config::ready_fallback` to determine when the device is ready.
+//!
+//! Use `NoPin::new()` where neither line is connected, or `NoPin::default()`
+//! alongside a real pin so the error types match:
+//!
+//! ```
+//! # use driver_example::sim::SimulatedDevice;
+//! use driver_example::{Config, ExampleDriver, I2cInterface, NoPin};
+//!
+//! # let dev = SimulatedDevice::new();
+//! # let (i2c, address, delay) = (dev.i2c(), dev.address(), dev.delay());
+//! let iface = I2cInterface::new(i2c, address);
+//! let d = ExampleDriver::new(Config::default(), iface, NoPin::new(), NoPin::new(), delay).unwrap();
+//! ```
+
+use core::convert::Infallible;
+use core::marker::PhantomData;
+
+use embedded_hal::digital::{self, ErrorType, InputPin, OutputPin};
+
+
+/// Placeholder for an unconnected pin
+///
+/// `E` is the pin error type, which must match any other (connected) pin
+/// passed to the driver.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NoPin<E = Infallible> {
+    _error: PhantomData<E>,
+}
+
+impl NoPin {
+    /// Create an unconnected pin with an `Infallible` error type
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<E> Default for NoPin<E> {
+    fn default() -> Self {
+        Self { _error: PhantomData }
+    }
+}
+
+impl<E: digital::Error> ErrorType for NoPin<E> {
+    type Error = E;
+}
+
+
+/// Input pin which may not be connected
+pub trait OptionalInputPin: ErrorType {
+    /// Check whether the pin is connected
+    fn is_connected(&self) -> bool;
+
+    /// Read the pin level (`true` for high), unconnected pins read low
+    fn level(&mut self) -> Result<bool, Self::Error>;
+}
+
+impl<P: InputPin> OptionalInputPin for P {
+    fn is_connected(&self) -> bool {
+        true
+    }
+
+    fn level(&mut self) -> Result<bool, Self::Error> {
+        self.is_high()
+    }
+}
+
+impl<E: digital::Error> OptionalInputPin for NoPin<E> {
+    fn is_connected(&self) -> bool {
+        false
+    }
+
+    fn level(&mut self) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+}
+
+
+/// Output pin which may not be connected
+pub trait OptionalOutputPin: ErrorType {
+    /// Check whether the pin is connected
+    fn is_connected(&self) -> bool;
+
+    /// Set the pin level (`true` for high), this is a no-op for unconnected pins
+    fn set_level(&mut self, high: bool) -> Result<(), Self::Error>;
+}
+
+impl<P: OutputPin> OptionalOutputPin for P {
+    fn is_connected(&self) -> bool {
+        true
+    }
+
+    fn set_level(&mut self, high: bool) -> Result<(), Self::Error> {
+        match high {
+            true => self.set_high(),
+            false => self.set_low(),
+        }
+    }
+}
+
+impl<E: digital::Error> OptionalOutputPin for NoPin<E> {
+    fn is_connected(&self) -> bool {
+        false
+    }
+
+    fn set_level(&mut self, _high: bool) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+
+/// Async input pin which may not be connected (requires the `async` feature)
+#[cfg(feature = "async")]
+#[allow(async_fn_in_trait)]
+pub trait OptionalWait: ErrorType {
+    /// Check whether the pin is connected
+    fn is_connected(&self) -> bool;
+
+    /// Wait for the pin to reach the provided level (`true` for high),
+    /// this never completes for unconnected pins
+    async fn wait_for_level(&mut self, high: bool) -> Result<(), Self::Error>;
+}
+
+#[cfg(feature = "async")]
+impl<P: embedded_hal_async::digital::Wait> OptionalWait for P {
+    fn is_connected(&self) -> bool {
+        true
+    }
+
+    async fn wait_for_level(&mut self, high: bool) -> Result<(), Self::Error> {
+        match high {
+            true => self.wait_for_high().await,
+            false => self.wait_for_low().await,
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<E: digital::Error> OptionalWait for NoPin<E> {
+    fn is_connected(&self) -> bool {
+        false
+    }
+
+    async fn wait_for_level(&mut self, _high: bool) -> Result<(), Self::Error> {
+        core::future::pending().await
+    }
+}
@@ -0,0 +1,200 @@
+//! SPI command / response protocol
+//!
+//! Many SPI devices are driven by commands rather than (or as well as)
+//! register accesses. Each command is a single chip select framed transfer:
+//!
+//! ```text
+//! MOSI: | opcode | address (0-4 bytes) | dummy (0-4 bytes) | payload .. | 0x00 ..  |
+//! MISO: | status | ..                                                  | response |
+//! ```
+//!
+//! The device clocks back its status register while receiving the opcode,
+//! and the response is read after any payload has been written. Commands
+//! are described with `Command` and sent with `ExampleDriver::command`:
+//!
+//! ```text
+//! let r = d.command(READ_DATA, 0x001000, &[])?;
+//! if !r.status.error() {
+//!     handle(r.payload());
+//! }
+//! ```
+//!
+//! TODO: replace the example commands with those from your device datasheet
+
+use crate::interface::Interface;
+use crate::registers::Status;
+
+
+/// Maximum number of dummy bytes following the address
+pub const MAX_DUMMY: usize = 4;
+
+/// Maximum command header length (opcode, address and dummy bytes)
+pub const MAX_HEADER: usize = 1 + 4 + MAX_DUMMY;
+
+/// Maximum command response length
+pub const MAX_RESPONSE: usize = 32;
+
+/// Command address width
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AddressWidth {
+    /// No address
+    None,
+    /// 8-bit address
+    Bits8,
+    /// 16-bit address
+    Bits16,
+    /// 24-bit address
+    Bits24,
+    /// 32-bit address
+    Bits32,
+}
+
+impl AddressWidth {
+    /// Number of address bytes sent
+    pub const fn bytes(&self) -> usize {
+        match self {
+            AddressWidth::None => 0,
+            AddressWidth::Bits8 => 1,
+            AddressWidth::Bits16 => 2,
+            AddressWidth::Bits24 => 3,
+            AddressWidth::Bits32 => 4,
+        }
+    }
+}
+
+/// Command description
+///
+/// Commands are intended to be declared as constants, so invalid
+/// dummy or response lengths fail at compile time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Command {
+    opcode: u8,
+    address: AddressWidth,
+    dummy: usize,
+    response: usize,
+}
+
+impl Command {
+    /// Create a command with the provided opcode, no address, dummy bytes or response
+    pub const fn new(opcode: u8) -> Self {
+        Self { opcode, address: AddressWidth::None, dummy: 0, response: 0 }
+    }
+
+    /// Set the command address width
+    pub const fn with_address(self, address: AddressWidth) -> Self {
+        Self { address, ..self }
+    }
+
+    /// Set the number of dummy bytes (8 clock cycles each) sent after the address
+    pub const fn with_dummy(self, dummy: usize) -> Self {
+        assert!(dummy <= MAX_DUMMY, "too many dummy bytes");
+        Self { dummy, ..self }
+    }
+
+    /// Set the response length in bytes
+    pub const fn with_response(self, response: usize) -> Self {
+        assert!(response <= MAX_RESPONSE, "response too long");
+        Self { response, ..self }
+    }
+
+    /// Command opcode
+    pub const fn opcode(&self) -> u8 {
+        self.opcode
+    }
+
+    /// Command response length
+    pub const fn response_len(&self) -> usize {
+        self.response
+    }
+
+    /// Encode the command header to `buff`, returning the header length
+    ///
+    /// The address is sent big-endian, truncated to the command address width
+    pub fn header(&self, address: u32, buff: &mut [u8; MAX_HEADER]) -> usize {
+        let n = self.address.bytes();
+
+        buff[0] = self.opcode;
+        buff[1..][..n].copy_from_slice(&address.to_be_bytes()[4 - n..]);
+        buff[1 + n..][..self.dummy].fill(0);
+
+        1 + n + self.dummy
+    }
+}
+
+/// Decoded command response
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    /// Device status, clocked back during the opcode
+    pub status: Status,
+    data: [u8; MAX_RESPONSE],
+    len: usize,
+}
+
+impl Response {
+    /// Create a response from the status byte and payload
+    pub(crate) fn new(status: u8, payload: &[u8]) -> Self {
+        let mut data = [0u8; MAX_RESPONSE];
+        data[..payload.len()].copy_from_slice(payload);
+
+        Self { status: Status(status), data, len: payload.len() }
+    }
+
+    /// Response payload
+    pub fn payload(&self) -> &[u8] {
+        &self.data[..self.len]
+    }
+}
+
+/// Encoded command header and response buffer for a single command
+///
+/// This is shared by the blocking and async drivers, which only
+/// run the transfer
+#[derive(Debug)]
+pub(crate) struct CommandFrame {
+    header: [u8; MAX_HEADER],
+    header_len: usize,
+    response: [u8; MAX_RESPONSE],
+    response_len: usize,
+}
+
+impl CommandFrame {
+    /// Encode `cmd` with the provided address
+    pub(crate) fn new(cmd: &Command, address: u32) -> Self {
+        let mut header = [0u8; MAX_HEADER];
+        let header_len = cmd.header(address, &mut header);
+
+        Self { header, header_len, response: [0u8; MAX_RESPONSE], response_len: cmd.response_len() }
+    }
+
+    /// Fetch the header to send and the buffer to read the response into
+    pub(crate) fn buffers(&mut self) -> (&[u8], &mut [u8]) {
+        (&self.header[..self.header_len], &mut self.response[..self.response_len])
+    }
+
+    /// Decode the response following a transfer returning `status`
+    pub(crate) fn response(&self, status: u8) -> Response {
+        Response::new(status, &self.response[..self.response_len])
+    }
+}
+
+/// Interface supporting command framing, implemented by `SpiInterface`
+pub trait CommandInterface: Interface {
+    /// Send `header` and `payload` then read `response` in a single
+    /// transaction, returning the status byte clocked back with the opcode
+    fn command(&mut self, header: &[u8], payload: &[u8], response: &mut [u8]) -> Result<u8, Self::Error>;
+}
+
+
+/// (example) Read the chip ID and revision
+pub const READ_ID: Command = Command::new(0x9f).with_response(2);
+
+/// (example) Read data from the provided 24-bit address, with one dummy byte
+pub const READ_DATA: Command = Command::new(0x0b)
+    .with_address(AddressWidth::Bits24)
+    .with_dummy(1)
+    .with_response(MAX_RESPONSE);
+
+/// (example) Write data to the provided 24-bit address
+pub const WRITE_DATA: Command = Command::new(0x02).with_address(AddressWidth::Bits24);
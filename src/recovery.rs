@@ -0,0 +1,140 @@
+//! I2C bus recovery
+//!
+//! If the device is reset mid-transaction it can be left holding SDA low,
+//! so every following transaction fails. The standard recovery is to take
+//! over SCL and SDA as GPIOs, clock SCL until the device releases SDA (at
+//! most nine pulses), then issue a STOP.
+//!
+//! Taking over the I2C pins is HAL specific, so this is provided as the
+//! `BusRecovery` hook, which reconfigures the pins, calls `clock_out`, then
+//! restores the I2C function. Attach it with `I2cInterface::with_recovery`
+//! and it is run automatically after a number of consecutive I2C errors:
+//!
+//! ```text
+//! let recovery = |i2c: &mut MyI2c| {
+//!     let (mut scl, mut sda) = i2c.pins_as_gpio();
+//!     recovery::clock_out(&mut scl, &mut sda, &mut delay)?;
+//!     i2c.restore_pins(scl, sda);
+//!     Ok::<_, MyPinError>(())
+//! };
+//! let iface = I2cInterface::new(i2c, address).with_recovery(recovery, 3);
+//! ```
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{InputPin, OutputPin};
+
+
+/// Maximum number of SCL pulses to clock out during recovery
+pub const RECOVERY_PULSES: usize = 9;
+
+/// SCL half period for recovery (5us for 100kHz)
+pub const RECOVERY_HALF_PERIOD_US: u32 = 5;
+
+/// Bus recovery hook, called with the I2C bus when recovery is required
+///
+/// This is implemented for closures taking `&mut Bus`.
+pub trait BusRecovery<Bus> {
+    /// Recovery error type
+    type Error;
+
+    /// Recover the bus, see `clock_out`
+    fn recover(&mut self, bus: &mut Bus) -> Result<(), Self::Error>;
+}
+
+impl<Bus, F, E> BusRecovery<Bus> for F
+where
+    F: FnMut(&mut Bus) -> Result<(), E>,
+{
+    type Error = E;
+
+    fn recover(&mut self, bus: &mut Bus) -> Result<(), E> {
+        self(bus)
+    }
+}
+
+/// Placeholder for interfaces without bus recovery
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NoRecovery;
+
+impl<Bus> BusRecovery<Bus> for NoRecovery {
+    type Error = core::convert::Infallible;
+
+    fn recover(&mut self, _bus: &mut Bus) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Clock out up to nine SCL pulses until SDA is released, then issue a STOP
+///
+/// `scl` and `sda` must be configured as open-drain outputs (with SDA
+/// readable). Returns whether SDA was released.
+pub fn clock_out<Scl, Sda, Delay, E>(scl: &mut Scl, sda: &mut Sda, delay: &mut Delay) -> Result<bool, E>
+where
+    Scl: OutputPin<Error = E>,
+    Sda: InputPin<Error = E> + OutputPin<Error = E>,
+    Delay: DelayNs,
+{
+    // Release SDA so the device can drive it
+    sda.set_high()?;
+    scl.set_high()?;
+    delay.delay_us(RECOVERY_HALF_PERIOD_US);
+
+    // Clock SCL until the device finishes its byte and releases SDA
+    for _ in 0..RECOVERY_PULSES {
+        if sda.is_high()? {
+            break;
+        }
+
+        scl.set_low()?;
+        delay.delay_us(RECOVERY_HALF_PERIOD_US);
+        scl.set_high()?;
+        delay.delay_us(RECOVERY_HALF_PERIOD_US);
+    }
+
+    // STOP, SDA rising while SCL is high
+    scl.set_low()?;
+    delay.delay_us(RECOVERY_HALF_PERIOD_US);
+    sda.set_low()?;
+    delay.delay_us(RECOVERY_HALF_PERIOD_US);
+    scl.set_high()?;
+    delay.delay_us(RECOVERY_HALF_PERIOD_US);
+    sda.set_high()?;
+    delay.delay_us(RECOVERY_HALF_PERIOD_US);
+
+    sda.is_high()
+}
+
+
+/// Tracks consecutive bus errors to decide when to run recovery
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct RecoveryTrigger {
+    /// Consecutive errors before running recovery (zero disables recovery)
+    after_errors: u32,
+    /// Consecutive errors seen
+    errors: u32,
+    /// Number of recoveries run
+    pub(crate) recoveries: u32,
+}
+
+impl RecoveryTrigger {
+    pub(crate) fn new(after_errors: u32) -> Self {
+        Self { after_errors, errors: 0, recoveries: 0 }
+    }
+
+    /// Record a transaction result, returning `true` if recovery should be run
+    pub(crate) fn record(&mut self, ok: bool) -> bool {
+        if ok || self.after_errors == 0 {
+            self.errors = 0;
+            return false;
+        }
+
+        self.errors += 1;
+        if self.errors < self.after_errors {
+            return false;
+        }
+
+        self.errors = 0;
+        self.recoveries = self.recoveries.saturating_add(1);
+        true
+    }
+}
@@ -0,0 +1,129 @@
+//! Device register map
+//!
+//! Each register is a type implementing `Register`, which describes its
+//! address, width, access mode and reset value. Registers are read and written
+//! via `ExampleDriver::read_reg`, `write_reg` and `modify_reg`, with the
+//! `Readable` and `Writable` markers ensuring at compile time that read-only
+//! registers can't be written (and vice versa). Runs of adjacent registers
+//! can be accessed as raw bytes in a single transaction with `read_regs`
+//! and `write_regs`.
+//!
+//! Registers and their fields are defined using the `register!` and
+//! `field_enum!` macros (see `macros.rs`), which can be written by hand or
+//! generated from a device description with `driver-codegen`.
+//!
+//! TODO: replace `device.toml` with the registers from your device datasheet
+
+/// Maximum register width in bytes
+pub const MAX_WIDTH: usize = 4;
+
+/// Maximum burst length in bytes, see `ExampleDriver::read_regs`
+pub const MAX_BURST: usize = 32;
+
+/// Register access mode
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Access {
+    /// Read only
+    ReadOnly,
+    /// Read / write
+    ReadWrite,
+    /// Write only
+    WriteOnly,
+}
+
+/// Register definition
+///
+/// Values are transferred MSB first, override `from_bytes` and `to_bytes`
+/// if your device differs.
+pub trait Register: Copy {
+    /// Register address
+    const ADDRESS: u8;
+    /// Register width in bytes (up to `MAX_WIDTH`)
+    const WIDTH: usize;
+    /// Register access mode
+    const ACCESS: Access;
+    /// Register value following device reset
+    const RESET: Self;
+
+    /// Create a register from a raw value
+    fn from_raw(raw: u32) -> Self;
+
+    /// Fetch the raw register value
+    fn raw(&self) -> u32;
+
+    /// Decode a register from `WIDTH` bytes read from the device
+    fn from_bytes(buff: &[u8]) -> Self {
+        let raw = buff.iter().fold(0u32, |a, b| (a << 8) | *b as u32);
+        Self::from_raw(raw)
+    }
+
+    /// Encode a register into `WIDTH` bytes to write to the device
+    fn to_bytes(&self, buff: &mut [u8]) {
+        let raw = self.raw();
+        for (i, b) in buff.iter_mut().rev().enumerate() {
+            *b = (raw >> (i * 8)) as u8;
+        }
+    }
+}
+
+/// Marker for registers that may be read
+pub trait Readable: Register {}
+
+/// Marker for registers that may be written
+pub trait Writable: Register {}
+
+
+/// Register field value, implemented for `bool`, unsigned integers,
+/// and enums created with `field_enum!`
+pub trait Field: Sized {
+    /// Type returned when reading the field
+    type Read;
+
+    /// Decode a field from its (shifted) bits
+    fn from_bits(bits: u32) -> Self::Read;
+
+    /// Encode a field into its (unshifted) bits
+    fn into_bits(self) -> u32;
+}
+
+impl Field for bool {
+    type Read = bool;
+
+    fn from_bits(bits: u32) -> bool {
+        bits != 0
+    }
+
+    fn into_bits(self) -> u32 {
+        self as u32
+    }
+}
+
+macro_rules! impl_field_int {
+    ($($t:ty),*) => {
+        $(
+            impl Field for $t {
+                type Read = $t;
+
+                fn from_bits(bits: u32) -> $t {
+                    bits as $t
+                }
+
+                fn into_bits(self) -> u32 {
+                    self as u32
+                }
+            }
+        )*
+    };
+}
+
+impl_field_int!(u8, u16, u32);
+
+/// Compute the mask for a field spanning bits `msb` to `lsb` (inclusive)
+pub const fn field_mask(msb: u32, lsb: u32) -> u32 {
+    (((1u64 << (msb - lsb + 1)) - 1) << lsb) as u32
+}
+
+
+// Device registers are generated from `device.toml` using `driver-codegen`
+mod device;
+pub use device::*;
@@ -0,0 +1,215 @@
+//! Example device register definitions
+//!
+//! Generated by driver-codegen from `device.toml`, do not edit
+
+field_enum! {
+    /// (example) Device operating mode
+    pub enum Mode {
+        /// Low power sleep
+        Sleep = 0,
+        /// Normal operation
+        Normal = 1,
+        /// High rate operation
+        Fast = 2,
+    }
+}
+
+field_enum! {
+    /// (example) FIFO behaviour when full
+    pub enum FifoMode {
+        /// Discard the oldest frame to make room
+        Stream = 0,
+        /// Discard new frames until there is room
+        StopOnFull = 1,
+    }
+}
+
+register! {
+    /// (example) Device status register
+    pub struct Status: u8 {
+        const ADDRESS = 0x00;
+        const ACCESS = ReadOnly;
+        const RESET = 0x00;
+
+        /// Device is busy
+        busy, set_busy: [0:0] as bool;
+        /// Device error flag
+        error, set_error: [1:1] as bool;
+        /// Current operating mode
+        mode, set_mode: [3:2] as Mode;
+    }
+}
+
+register! {
+    /// (example) Device control register
+    pub struct Control: u8 {
+        const ADDRESS = 0x01;
+        const ACCESS = ReadWrite;
+        const RESET = 0x00;
+
+        /// Device enable
+        enable, set_enable: [0:0] as bool;
+        /// Operating mode
+        mode, set_mode: [2:1] as Mode;
+        /// Interrupt output enable
+        irq_enable, set_irq_enable: [7:7] as bool;
+    }
+}
+
+register! {
+    /// (example) Two byte threshold register
+    pub struct Threshold: u16 {
+        const ADDRESS = 0x02;
+        const ACCESS = ReadWrite;
+        const RESET = 0x0100;
+
+        /// Threshold level
+        level, set_level: [11:0] as u16;
+    }
+}
+
+register! {
+    /// (example) Device command register
+    pub struct Command: u8 {
+        const ADDRESS = 0x04;
+        const ACCESS = WriteOnly;
+        const RESET = 0x00;
+
+        /// Command opcode
+        opcode, set_opcode: [7:0] as u8;
+    }
+}
+
+register! {
+    /// (example) Interrupt status register, cleared on read
+    pub struct IrqStatus: u8 {
+        const ADDRESS = 0x05;
+        const ACCESS = ReadOnly;
+        const RESET = 0x00;
+
+        /// New data available
+        data_ready, set_data_ready: [0:0] as bool;
+        /// Threshold level crossed
+        threshold, set_threshold: [1:1] as bool;
+        /// Device fault
+        fault, set_fault: [2:2] as bool;
+        /// FIFO level reached the watermark
+        fifo_watermark, set_fifo_watermark: [3:3] as bool;
+        /// FIFO overflowed and frames were dropped
+        fifo_overflow, set_fifo_overflow: [4:4] as bool;
+    }
+}
+
+register! {
+    /// (example) Interrupt enable register
+    pub struct IrqEnable: u8 {
+        const ADDRESS = 0x06;
+        const ACCESS = ReadWrite;
+        const RESET = 0x00;
+
+        /// Enable the data ready interrupt
+        data_ready, set_data_ready: [0:0] as bool;
+        /// Enable the threshold interrupt
+        threshold, set_threshold: [1:1] as bool;
+        /// Enable the fault interrupt
+        fault, set_fault: [2:2] as bool;
+        /// Enable the FIFO watermark interrupt
+        fifo_watermark, set_fifo_watermark: [3:3] as bool;
+        /// Enable the FIFO overflow interrupt
+        fifo_overflow, set_fifo_overflow: [4:4] as bool;
+    }
+}
+
+register! {
+    /// (example) FIFO control register
+    pub struct FifoControl: u8 {
+        const ADDRESS = 0x07;
+        const ACCESS = ReadWrite;
+        const RESET = 0x00;
+
+        /// FIFO enable
+        enable, set_enable: [0:0] as bool;
+        /// Behaviour when full
+        mode, set_mode: [1:1] as FifoMode;
+        /// Discard the FIFO contents (self-clearing)
+        flush, set_flush: [2:2] as bool;
+    }
+}
+
+register! {
+    /// (example) FIFO watermark register
+    pub struct FifoWatermark: u8 {
+        const ADDRESS = 0x08;
+        const ACCESS = ReadWrite;
+        const RESET = 0x10;
+
+        /// Watermark level in frames
+        level, set_level: [5:0] as u8;
+    }
+}
+
+register! {
+    /// (example) FIFO status register, flags are cleared on read
+    pub struct FifoStatus: u8 {
+        const ADDRESS = 0x09;
+        const ACCESS = ReadOnly;
+        const RESET = 0x00;
+
+        /// Number of frames in the FIFO
+        level, set_level: [5:0] as u8;
+        /// FIFO overflowed since the last read
+        overflow, set_overflow: [6:6] as bool;
+        /// FIFO data was read while empty since the last read
+        underrun, set_underrun: [7:7] as bool;
+    }
+}
+
+register! {
+    /// (example) FIFO dropped frame counter, saturating and cleared on read
+    pub struct FifoDropped: u8 {
+        const ADDRESS = 0x0a;
+        const ACCESS = ReadOnly;
+        const RESET = 0x00;
+
+        /// Frames dropped due to overflow
+        count, set_count: [7:0] as u8;
+    }
+}
+
+register! {
+    /// (example) FIFO data register, each read pops the next FIFO byte
+    pub struct FifoData: u8 {
+        const ADDRESS = 0x0b;
+        const ACCESS = ReadOnly;
+        const RESET = 0x00;
+
+        /// FIFO data byte
+        data, set_data: [7:0] as u8;
+    }
+}
+
+register! {
+    /// (example) Silicon revision register
+    pub struct Revision: u8 {
+        const ADDRESS = 0x0e;
+        const ACCESS = ReadOnly;
+        const RESET = 0x10;
+
+        /// Minor revision
+        minor, set_minor: [3:0] as u8;
+        /// Major revision
+        major, set_major: [7:4] as u8;
+    }
+}
+
+register! {
+    /// (example) Chip identification register
+    pub struct ChipId: u8 {
+        const ADDRESS = 0x0f;
+        const ACCESS = ReadOnly;
+        const RESET = 0x5a;
+
+        /// Chip ID, see `CHIP_ID`
+        id, set_id: [7:0] as u8;
+    }
+}
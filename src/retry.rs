@@ -0,0 +1,123 @@
+//! Retry of transient bus errors
+//!
+//! Every bus transaction is retried according to `Config::retry`, with
+//! errors classified by `Interface::error_kind`. Frame checksum mismatches
+//! (see `Config::checksum`) and SMBus PEC mismatches (see `Smbus::with_pec`)
+//! are `BusErrorKind::Checksum`, and retry the whole transaction. Retries are counted in
+//! `RetryStats` (see `ExampleDriver::retry_stats`) to help monitor link quality.
+//!
+//! Errors that can't be classified are `BusErrorKind::Other` and never retried
+//! by default. This includes all errors from the `hal02` interfaces, as
+//! embedded-hal 0.2 bus errors don't expose a kind.
+
+use crate::interface::BusErrorKind;
+
+
+/// Retry policy for bus transactions
+///
+/// Only errors the interface can classify are retried, so interfaces that
+/// don't implement `Interface::error_kind` (such as the `hal02` adaptors)
+/// are not retried unless `BusErrorKind::Other` is made retryable
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts per transaction, `1` disables retries
+    pub max_attempts: u32,
+
+    /// Delay before the first retry, doubling with each subsequent retry
+    pub backoff_ms: u32,
+
+    /// Error kinds to retry, other errors are returned immediately
+    pub retryable: &'static [BusErrorKind],
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            backoff_ms: 1,
+            retryable: &[BusErrorKind::NoAcknowledge, BusErrorKind::ArbitrationLoss, BusErrorKind::Bus, BusErrorKind::Checksum],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Check whether to retry following a failed `attempt` (starting from 1),
+    /// returning the backoff delay in milliseconds if so
+    pub fn retry(&self, attempt: u32, kind: BusErrorKind) -> Option<u32> {
+        if attempt >= self.max_attempts || !self.retryable.contains(&kind) {
+            return None;
+        }
+
+        let shift = (attempt - 1).min(31);
+
+        Some(self.backoff_ms.saturating_mul(1 << shift))
+    }
+}
+
+
+/// Bus transaction retry counters
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RetryStats {
+    /// Total number of retries
+    pub retries: u32,
+    /// Transactions that succeeded after retrying
+    pub recovered: u32,
+    /// Transactions that failed after retrying
+    pub failed: u32,
+}
+
+impl RetryStats {
+    /// Record the outcome of a transaction that took `attempts` attempts
+    pub(crate) fn record(&mut self, attempts: u32, ok: bool) {
+        if attempts <= 1 {
+            return;
+        }
+
+        self.retries = self.retries.saturating_add(attempts - 1);
+
+        match ok {
+            true => self.recovered = self.recovered.saturating_add(1),
+            false => self.failed = self.failed.saturating_add(1),
+        }
+    }
+}
+
+
+/// Retry decision for a single bus transaction
+///
+/// This is shared by the blocking and async drivers, which only
+/// run the transaction and wait for the backoff
+#[derive(Debug)]
+pub(crate) struct Retry {
+    /// Current attempt, starting from 1
+    attempt: u32,
+}
+
+impl Retry {
+    /// Start a new transaction
+    pub(crate) fn new() -> Self {
+        Self { attempt: 1 }
+    }
+
+    /// Record a failed attempt with error `kind` (`None` for errors that aren't
+    /// from a bus transaction), returning the backoff delay in milliseconds
+    /// before the next attempt, or `None` to give up
+    pub(crate) fn failed(&mut self, policy: &RetryPolicy, stats: &mut RetryStats, kind: Option<BusErrorKind>) -> Option<u32> {
+        match kind.and_then(|k| policy.retry(self.attempt, k)) {
+            Some(backoff_ms) => {
+                self.attempt += 1;
+                Some(backoff_ms)
+            }
+            None => {
+                stats.record(self.attempt, false);
+                None
+            }
+        }
+    }
+
+    /// Record a successful attempt
+    pub(crate) fn succeeded(self, stats: &mut RetryStats) {
+        stats.record(self.attempt, true);
+    }
+}
@@ -0,0 +1,1052 @@
+//! Behavioural device simulator (requires the `sim` feature)
+//!
+//! Unlike the expectation-based mocks, `SimulatedDevice` models the device
+//! itself: a register file initialised from the register map, the reset line,
+//! and a busy output driven from simulated time. Handles implementing the HAL
+//! traits are fetched from the device so the driver can be run end-to-end:
+//!
+//! ```
+//! use driver_example::sim::SimulatedDevice;
+//! use driver_example::{Config, ExampleDriver, I2cInterface};
+//! use driver_example::registers::{Control, Mode};
+//!
+//! let dev = SimulatedDevice::new();
+//!
+//! let iface = I2cInterface::new(dev.i2c(), dev.address());
+//! let mut d = ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+//!
+//! assert_eq!(d.read_reg::<Control>().unwrap().mode(), Some(Mode::Normal));
+//! ```
+//!
+//! With the `async` feature the I2C, SPI, busy and delay handles also implement
+//! the embedded-hal-async traits, for use with `crate::asynch::ExampleDriver`.
+//!
+//! TODO: update the simulated behaviour to match your device
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::vec::Vec;
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{self, InputPin, OutputPin};
+use embedded_hal::i2c::{self, I2c, NoAcknowledgeSource, SevenBitAddress, TenBitAddress};
+use embedded_hal::spi::{self, SpiBus, SpiDevice};
+
+use crate::config::Polarity;
+use crate::fifo::{Frame, FIFO_DEPTH, FRAME_SIZE};
+use crate::checksum::{self, Checksum};
+use crate::interface::{i2c_checksum_header, Address, SPI_READ_FLAG};
+use crate::registers::{self, Access, FifoMode, Register};
+use crate::timer::Clock;
+
+#[cfg(feature = "async")]
+mod asynch;
+
+
+/// Default simulated I2C address
+pub const DEFAULT_ADDRESS: u8 = crate::interface::DEFAULT_ADDRESS;
+
+/// Default time from reset release to the device becoming ready
+pub const DEFAULT_READY_AFTER_MS: u32 = 20;
+
+/// Simulator error type
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SimError {
+    /// I2C transaction was not acknowledged (wrong address or device in reset)
+    Nack,
+    /// I2C bus is stuck with SDA held low
+    Bus,
+}
+
+impl i2c::Error for SimError {
+    fn kind(&self) -> i2c::ErrorKind {
+        match self {
+            SimError::Nack => i2c::ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address),
+            SimError::Bus => i2c::ErrorKind::Bus,
+        }
+    }
+}
+
+impl spi::Error for SimError {
+    fn kind(&self) -> spi::ErrorKind {
+        spi::ErrorKind::Other
+    }
+}
+
+impl digital::Error for SimError {
+    fn kind(&self) -> digital::ErrorKind {
+        digital::ErrorKind::Other
+    }
+}
+
+
+/// Simulated device state
+#[derive(Debug)]
+struct State {
+    /// I2C address
+    address: Address,
+    /// Register file, byte addressed
+    regs: [u8; 256],
+    /// Register access modes (unmapped addresses are `None`)
+    access: [Option<Access>; 256],
+    /// Register values following reset
+    reset_values: [u8; 256],
+
+    /// Device is held in reset
+    in_reset: bool,
+    /// Number of resets seen
+    resets: u32,
+    /// Time the device last left reset
+    reset_at_ns: u64,
+    /// Time from reset to ready
+    ready_after_ns: u64,
+    /// Reset input polarity
+    reset_polarity: Polarity,
+    /// Busy output polarity
+    busy_polarity: Polarity,
+    /// Time taken by each bus transaction
+    transaction_ns: u64,
+    /// Number of upcoming I2C transactions to fail with a NACK
+    nacks: u32,
+    /// Frame checksum appended to I2C reads and checked on writes
+    checksum: Checksum,
+    /// Number of upcoming checksummed I2C reads to corrupt
+    corrupt_reads: u32,
+
+    /// I2C bus is stuck until the device releases SDA and sees a STOP
+    bus_stuck: bool,
+    /// SCL pulses until the device releases SDA
+    sda_held_clocks: u32,
+    /// SCL level driven by the controller (when taken over as a GPIO)
+    scl: bool,
+    /// SDA level driven by the controller (when taken over as a GPIO)
+    sda: bool,
+
+    /// SPI chip select is asserted (when on a shared `SimBus`)
+    selected: bool,
+    /// Current SPI frame direction, register address and address step
+    spi_cmd: Option<(bool, u8, u8)>,
+    /// Register address auto-increment flag, zero if the device always auto-increments
+    auto_increment: u8,
+
+    /// FIFO contents, whole frames are pushed but may be read bytewise
+    fifo: VecDeque<u8>,
+
+    /// Simulated time
+    now_ns: u64,
+}
+
+impl State {
+    fn busy(&self) -> bool {
+        self.in_reset || self.now_ns < self.reset_at_ns + self.ready_after_ns
+    }
+
+    /// Define a register from the register map
+    fn define<R: Register>(&mut self) {
+        let mut buff = [0u8; registers::MAX_WIDTH];
+        let buff = &mut buff[..R::WIDTH];
+        R::RESET.to_bytes(buff);
+
+        for (i, b) in buff.iter().enumerate() {
+            let a = R::ADDRESS as usize + i;
+            self.reset_values[a] = *b;
+            self.access[a] = Some(R::ACCESS);
+        }
+    }
+
+    /// Restore registers to their reset values
+    fn reset_registers(&mut self) {
+        self.regs = self.reset_values;
+        self.fifo.clear();
+    }
+
+    /// Number of whole frames in the FIFO
+    fn fifo_level(&self) -> usize {
+        self.fifo.len() / FRAME_SIZE
+    }
+
+    /// Push a frame to the FIFO if enabled, dropping a frame if it is full
+    fn push_fifo(&mut self, frame: Frame) {
+        let control = registers::FifoControl(self.regs[registers::FifoControl::ADDRESS as usize]);
+        if !control.enable() {
+            return;
+        }
+
+        let mut irq = registers::IrqStatus(self.regs[registers::IrqStatus::ADDRESS as usize]);
+
+        if self.fifo_level() >= FIFO_DEPTH {
+            let status = &mut self.regs[registers::FifoStatus::ADDRESS as usize];
+            *status = registers::FifoStatus(*status).set_overflow(true).0;
+
+            let dropped = &mut self.regs[registers::FifoDropped::ADDRESS as usize];
+            *dropped = dropped.saturating_add(1);
+
+            irq.set_fifo_overflow(true);
+
+            match control.mode() {
+                Some(FifoMode::StopOnFull) | None => {
+                    self.regs[registers::IrqStatus::ADDRESS as usize] = irq.0;
+                    return;
+                }
+                Some(FifoMode::Stream) => {
+                    self.fifo.drain(..FRAME_SIZE);
+                }
+            }
+        }
+
+        self.fifo.extend(frame.to_bytes());
+
+        let watermark = registers::FifoWatermark(self.regs[registers::FifoWatermark::ADDRESS as usize]);
+        if self.fifo_level() >= watermark.level() as usize {
+            irq.set_fifo_watermark(true);
+        }
+
+        self.regs[registers::IrqStatus::ADDRESS as usize] = irq.0;
+    }
+
+    fn read(&mut self, addr: u8) -> u8 {
+        match self.access[addr as usize] {
+            Some(Access::WriteOnly) => 0,
+            // Interrupt status is cleared on read
+            _ if addr == registers::IrqStatus::ADDRESS => {
+                core::mem::take(&mut self.regs[addr as usize])
+            }
+            // FIFO level is live, flags are cleared on read
+            _ if addr == registers::FifoStatus::ADDRESS => {
+                let mut s = registers::FifoStatus(core::mem::take(&mut self.regs[addr as usize]));
+                s.set_level(self.fifo_level() as u8);
+                s.0
+            }
+            _ if addr == registers::FifoDropped::ADDRESS => {
+                core::mem::take(&mut self.regs[addr as usize])
+            }
+            // Reading an empty FIFO flags an underrun
+            _ if addr == registers::FifoData::ADDRESS => {
+                self.fifo.pop_front().unwrap_or_else(|| {
+                    let status = &mut self.regs[registers::FifoStatus::ADDRESS as usize];
+                    *status = registers::FifoStatus(*status).set_underrun(true).0;
+                    0
+                })
+            }
+            // Status reflects the live busy state
+            _ if addr == registers::Status::ADDRESS => {
+                let mut s = registers::Status(self.regs[addr as usize]);
+                s.set_busy(self.busy());
+                s.0
+            }
+            _ => self.regs[addr as usize],
+        }
+    }
+
+    fn write(&mut self, addr: u8, value: u8) {
+        match self.access[addr as usize] {
+            Some(Access::ReadOnly) => (),
+            // Software reset command
+            _ if addr == registers::Command::ADDRESS && value == crate::SOFT_RESET => {
+                self.resets += 1;
+                self.reset_registers();
+                self.reset_at_ns = self.now_ns;
+            }
+            // FIFO flush is self-clearing
+            _ if addr == registers::FifoControl::ADDRESS => {
+                let mut c = registers::FifoControl(value);
+                if c.flush() {
+                    self.fifo.clear();
+                    c.set_flush(false);
+                }
+                self.regs[addr as usize] = c.0;
+            }
+            _ => self.regs[addr as usize] = value,
+        }
+    }
+
+    /// Decode a register address byte, returning the register address and
+    /// the address step applied after each byte
+    ///
+    /// With an auto-increment flag set the address only increments if the
+    /// flag is set in the address byte. Otherwise it always increments,
+    /// other than for the FIFO data register so it can be read in bursts
+    fn pointer(&self, addr: u8) -> (u8, u8) {
+        match self.auto_increment {
+            0 if addr == registers::FifoData::ADDRESS => (addr, 0),
+            0 => (addr, 1),
+            flag => (addr & !flag, (addr & flag != 0) as u8),
+        }
+    }
+
+    /// Clock a byte through the SPI interface, returning the byte clocked in
+    ///
+    /// The first byte of a frame is the address (with read flag),
+    /// following bytes are read or written with auto-increment
+    fn spi_clock(&mut self, out: u8) -> u8 {
+        match self.spi_cmd {
+            None => {
+                let (a, step) = self.pointer(out & !SPI_READ_FLAG);
+                self.spi_cmd = Some((out & SPI_READ_FLAG != 0, a, step));
+                0
+            }
+            Some((true, a, step)) => {
+                self.spi_cmd = Some((true, a.wrapping_add(step), step));
+                self.read(a)
+            }
+            Some((false, a, step)) => {
+                self.spi_cmd = Some((false, a.wrapping_add(step), step));
+                self.write(a, out);
+                0
+            }
+        }
+    }
+
+    /// Run an I2C register read or write with frame checksums, writes
+    /// with a bad checksum are not acknowledged
+    fn checksum_transaction(&mut self, address: Address, operations: &mut [i2c::Operation<'_>]) -> Result<(), SimError> {
+        let checksum = self.checksum;
+
+        // The register address and any data written, then the read buffer
+        let mut written = Vec::new();
+        let mut read = None;
+        for op in operations.iter_mut() {
+            match op {
+                i2c::Operation::Write(data) => written.extend_from_slice(data),
+                i2c::Operation::Read(buff) => read = Some(buff),
+            }
+        }
+
+        let (reg, data) = match written.split_first() {
+            Some((reg, data)) => (*reg, data),
+            None => return Ok(()),
+        };
+        let (addr, step) = self.pointer(reg);
+
+        let mut header = [0u8; checksum::MAX_HEADER];
+        let n = i2c_checksum_header(address, reg, read.is_some(), &mut header);
+
+        match read {
+            Some(buff) if buff.len() > checksum.bytes() => {
+                let len = buff.len() - checksum.bytes();
+                self.read_burst(addr, step, &mut buff[..len]);
+                checksum.append(&header[..n], buff);
+
+                if self.corrupt_reads > 0 {
+                    self.corrupt_reads -= 1;
+                    buff[0] ^= 0x01;
+                }
+            }
+            Some(buff) => self.read_burst(addr, step, buff),
+            None => {
+                if data.len() < checksum.bytes() || checksum.verify(&header[..n], data).is_err() {
+                    return Err(SimError::Nack);
+                }
+
+                self.write_burst(addr, step, &data[..data.len() - checksum.bytes()]);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read `buff.len()` registers, advancing the address by `step` after each
+    fn read_burst(&mut self, addr: u8, step: u8, buff: &mut [u8]) {
+        for (i, b) in buff.iter_mut().enumerate() {
+            *b = self.read(addr.wrapping_add(step.wrapping_mul(i as u8)));
+        }
+    }
+
+    /// Write `data` to registers, advancing the address by `step` after each
+    fn write_burst(&mut self, addr: u8, step: u8, data: &[u8]) {
+        for (i, b) in data.iter().enumerate() {
+            self.write(addr.wrapping_add(step.wrapping_mul(i as u8)), *b);
+        }
+    }
+}
+
+
+/// Simulated device
+///
+/// Clones share the same underlying device.
+#[derive(Debug, Clone)]
+pub struct SimulatedDevice {
+    state: Arc<Mutex<State>>,
+}
+
+impl Default for SimulatedDevice {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SimulatedDevice {
+    /// Create a new simulated device with the default address and timing
+    pub fn new() -> Self {
+        let mut s = State {
+            address: Address::SevenBit(DEFAULT_ADDRESS),
+            regs: [0; 256],
+            access: [None; 256],
+            reset_values: [0; 256],
+            in_reset: false,
+            resets: 0,
+            reset_at_ns: 0,
+            ready_after_ns: DEFAULT_READY_AFTER_MS as u64 * 1_000_000,
+            reset_polarity: Polarity::ActiveLow,
+            busy_polarity: Polarity::ActiveLow,
+            transaction_ns: 0,
+            nacks: 0,
+            checksum: Checksum::None,
+            corrupt_reads: 0,
+            bus_stuck: false,
+            sda_held_clocks: 0,
+            scl: true,
+            sda: true,
+            selected: false,
+            spi_cmd: None,
+            auto_increment: 0,
+            fifo: VecDeque::new(),
+            now_ns: 0,
+        };
+
+        s.define::<registers::Status>();
+        s.define::<registers::Control>();
+        s.define::<registers::Threshold>();
+        s.define::<registers::Command>();
+        s.define::<registers::IrqStatus>();
+        s.define::<registers::IrqEnable>();
+        s.define::<registers::FifoControl>();
+        s.define::<registers::FifoWatermark>();
+        s.define::<registers::FifoStatus>();
+        s.define::<registers::FifoDropped>();
+        s.define::<registers::FifoData>();
+        s.define::<registers::Revision>();
+        s.define::<registers::ChipId>();
+
+        s.reset_registers();
+
+        Self { state: Arc::new(Mutex::new(s)) }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    /// Set a register value, persisting across resets
+    fn set_reset_value(&self, addr: u8, value: u8) {
+        let mut s = self.state();
+        s.reset_values[addr as usize] = value;
+        s.regs[addr as usize] = value;
+    }
+
+    /// Set the device 7-bit I2C address
+    pub fn with_address(self, address: u8) -> Self {
+        self.state().address = Address::SevenBit(address);
+        self
+    }
+
+    /// Set a 10-bit I2C address, the device then only responds to 10-bit transactions
+    pub fn with_ten_bit_address(self, address: u16) -> Self {
+        self.state().address = Address::TenBit(address);
+        self
+    }
+
+    /// Set the chip ID reported by the device
+    pub fn with_chip_id(self, id: u8) -> Self {
+        self.set_reset_value(registers::ChipId::ADDRESS, id);
+        self
+    }
+
+    /// Set the silicon revision reported by the device
+    pub fn with_revision(self, revision: u8) -> Self {
+        self.set_reset_value(registers::Revision::ADDRESS, revision);
+        self
+    }
+
+    /// Only auto-increment the register address during burst accesses when
+    /// `flag` is set in the address byte (by default the device always
+    /// auto-increments), see `I2cInterface::with_auto_increment`
+    pub fn with_auto_increment(self, flag: u8) -> Self {
+        self.state().auto_increment = flag;
+        self
+    }
+
+    /// Append frame checksums to I2C register reads and check them on
+    /// writes, see `Config::checksum`
+    pub fn with_checksum(self, checksum: Checksum) -> Self {
+        self.state().checksum = checksum;
+        self
+    }
+
+    /// Set the time from reset release to the device becoming ready
+    pub fn with_ready_after_ms(self, ms: u32) -> Self {
+        self.state().ready_after_ns = ms as u64 * 1_000_000;
+        self
+    }
+
+    /// Set the reset input polarity
+    pub fn with_reset_polarity(self, polarity: Polarity) -> Self {
+        self.state().reset_polarity = polarity;
+        self
+    }
+
+    /// Set the busy output polarity
+    pub fn with_busy_polarity(self, polarity: Polarity) -> Self {
+        self.state().busy_polarity = polarity;
+        self
+    }
+
+    /// Set the time taken by each bus transaction (defaults to zero)
+    pub fn with_transaction_time_us(self, us: u32) -> Self {
+        self.state().transaction_ns = us as u64 * 1_000;
+        self
+    }
+
+    /// Fail the next `n` I2C transactions with a NACK, to simulate a noisy bus
+    pub fn inject_nacks(&self, n: u32) {
+        self.state().nacks = n;
+    }
+
+    /// Corrupt a data bit in the next `n` checksummed I2C register reads,
+    /// to simulate noise the checksum detects
+    pub fn corrupt_reads(&self, n: u32) {
+        self.state().corrupt_reads = n;
+    }
+
+    /// Simulate the device being interrupted mid-transaction, holding SDA
+    /// low until it sees `clocks` SCL pulses. I2C transactions fail until
+    /// the bus is recovered (see `scl` and `sda`)
+    pub fn hold_sda(&self, clocks: u32) {
+        let mut s = self.state();
+        s.bus_stuck = true;
+        s.sda_held_clocks = clocks;
+    }
+
+    /// Check whether the I2C bus is stuck
+    pub fn bus_stuck(&self) -> bool {
+        self.state().bus_stuck
+    }
+
+    /// Fetch the I2C SCL line as an open-drain GPIO, for bus recovery
+    pub fn scl(&self) -> SimScl {
+        SimScl(self.clone())
+    }
+
+    /// Fetch the I2C SDA line as an open-drain GPIO, for bus recovery
+    pub fn sda(&self) -> SimSda {
+        SimSda(self.clone())
+    }
+
+    /// Fetch the device 7-bit I2C address
+    ///
+    /// This panics if the device uses a 10-bit address, see `i2c_address`
+    pub fn address(&self) -> u8 {
+        match self.state().address {
+            Address::SevenBit(a) => a,
+            Address::TenBit(_) => panic!("simulated device uses a 10-bit address"),
+        }
+    }
+
+    /// Fetch the device I2C address
+    pub fn i2c_address(&self) -> Address {
+        self.state().address
+    }
+
+    /// Fetch an I2C bus handle connected to the device
+    pub fn i2c(&self) -> SimI2c {
+        SimI2c(self.clone())
+    }
+
+    /// Fetch an SPI device handle connected to the device
+    pub fn spi(&self) -> SimSpi {
+        SimSpi(self.clone())
+    }
+
+    /// Fetch the device SPI chip select, active low, for use with a shared `SimBus`
+    pub fn cs(&self) -> SimCs {
+        SimCs(self.clone())
+    }
+
+    /// Fetch the device busy output pin
+    pub fn busy(&self) -> SimBusy {
+        SimBusy(self.clone())
+    }
+
+    /// Fetch the device reset input pin
+    pub fn reset(&self) -> SimReset {
+        SimReset(self.clone())
+    }
+
+    /// Fetch a delay that advances simulated time
+    pub fn delay(&self) -> SimDelay {
+        SimDelay(self.clone())
+    }
+
+    /// Fetch the device interrupt output pin (active low)
+    pub fn irq(&self) -> SimIrq {
+        SimIrq(self.clone())
+    }
+
+    /// Raise device interrupts by setting `IrqStatus` bits, the interrupt
+    /// output is asserted if any raised interrupts are enabled
+    pub fn raise_interrupt(&self, bits: u8) {
+        self.state().regs[registers::IrqStatus::ADDRESS as usize] |= bits;
+    }
+
+    /// Push a measurement frame to the device FIFO, as if sampled by the
+    /// device. Frames are ignored unless the FIFO is enabled, and raise the
+    /// FIFO watermark and overflow interrupts
+    pub fn push_fifo(&self, frame: Frame) {
+        self.state().push_fifo(frame);
+    }
+
+    /// Fetch the number of frames in the device FIFO
+    pub fn fifo_level(&self) -> usize {
+        self.state().fifo_level()
+    }
+
+    /// Fetch a monotonic clock reading simulated time
+    pub fn clock(&self) -> SimClock {
+        SimClock(self.clone())
+    }
+
+    /// Peek at the raw register file (bypassing access modes)
+    pub fn peek(&self, addr: u8) -> u8 {
+        self.state().regs[addr as usize]
+    }
+
+    /// Poke the raw register file (bypassing access modes), for example
+    /// to set read-only status bits
+    pub fn poke(&self, addr: u8, value: u8) {
+        self.state().regs[addr as usize] = value;
+    }
+
+    /// Fetch the number of times the device has been reset
+    pub fn resets(&self) -> u32 {
+        self.state().resets
+    }
+
+    /// Fetch the elapsed simulated time in milliseconds
+    pub fn now_ms(&self) -> u64 {
+        self.state().now_ns / 1_000_000
+    }
+}
+
+
+/// Simulated I2C bus handle
+#[derive(Debug, Clone)]
+pub struct SimI2c(SimulatedDevice);
+
+impl i2c::ErrorType for SimI2c {
+    type Error = SimError;
+}
+
+impl I2c<SevenBitAddress> for SimI2c {
+    fn transaction(&mut self, address: u8, operations: &mut [i2c::Operation<'_>]) -> Result<(), Self::Error> {
+        self.transaction_at(Address::SevenBit(address), operations)
+    }
+}
+
+impl I2c<TenBitAddress> for SimI2c {
+    fn transaction(&mut self, address: u16, operations: &mut [i2c::Operation<'_>]) -> Result<(), Self::Error> {
+        self.transaction_at(Address::TenBit(address), operations)
+    }
+}
+
+impl SimI2c {
+    fn transaction_at(&mut self, address: Address, operations: &mut [i2c::Operation<'_>]) -> Result<(), SimError> {
+        let mut s = self.0.state();
+        s.now_ns += s.transaction_ns;
+
+        if address != s.address || s.in_reset {
+            return Err(SimError::Nack);
+        }
+
+        if s.bus_stuck {
+            return Err(SimError::Bus);
+        }
+
+        if s.nacks > 0 {
+            s.nacks -= 1;
+            return Err(SimError::Nack);
+        }
+
+        if !s.checksum.is_none() {
+            return s.checksum_transaction(address, operations);
+        }
+
+        // The first byte written sets the register pointer, following
+        // bytes are written from the pointer with auto-increment
+        let mut ptr: Option<(u8, u8)> = None;
+
+        for op in operations {
+            match op {
+                i2c::Operation::Write(data) => {
+                    let ((p, step), data) = match ptr {
+                        Some(p) => (p, &data[..]),
+                        None if data.is_empty() => continue,
+                        None => (s.pointer(data[0]), &data[1..]),
+                    };
+
+                    s.write_burst(p, step, data);
+                    ptr = Some((p.wrapping_add(step.wrapping_mul(data.len() as u8)), step));
+                }
+                i2c::Operation::Read(buff) => {
+                    let (p, step) = ptr.unwrap_or((0, 1));
+
+                    s.read_burst(p, step, buff);
+                    ptr = Some((p.wrapping_add(step.wrapping_mul(buff.len() as u8)), step));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+
+/// Simulated SPI device handle
+#[derive(Debug, Clone)]
+pub struct SimSpi(SimulatedDevice);
+
+impl spi::ErrorType for SimSpi {
+    type Error = SimError;
+}
+
+impl SpiDevice for SimSpi {
+    fn transaction(&mut self, operations: &mut [spi::Operation<'_, u8>]) -> Result<(), Self::Error> {
+        let mut s = self.0.state();
+        s.now_ns += s.transaction_ns;
+
+        // Device ignores the bus while held in reset
+        if s.in_reset {
+            return Ok(());
+        }
+
+        // Each transaction is a complete frame
+        s.spi_cmd = None;
+        let clock = |s: &mut State, out: u8| s.spi_clock(out);
+
+        for op in operations {
+            match op {
+                spi::Operation::Write(data) => {
+                    for b in data.iter() {
+                        clock(&mut s, *b);
+                    }
+                }
+                spi::Operation::Read(buff) => {
+                    for b in buff.iter_mut() {
+                        *b = clock(&mut s, 0x00);
+                    }
+                }
+                spi::Operation::Transfer(read, write) => {
+                    let n = read.len().max(write.len());
+                    let out: Vec<u8> = (0..n).map(|i| write.get(i).copied().unwrap_or(0)).collect();
+                    for (i, o) in out.iter().enumerate() {
+                        let r = clock(&mut s, *o);
+                        if let Some(b) = read.get_mut(i) {
+                            *b = r;
+                        }
+                    }
+                }
+                spi::Operation::TransferInPlace(buff) => {
+                    for b in buff.iter_mut() {
+                        *b = clock(&mut s, *b);
+                    }
+                }
+                spi::Operation::DelayNs(ns) => {
+                    s.now_ns += *ns as u64;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+
+/// Simulated bus shared by multiple devices
+///
+/// I2C transactions are routed to the device with the matching address,
+/// and SPI bytes to the devices selected by their `SimulatedDevice::cs`.
+#[derive(Debug, Clone, Default)]
+pub struct SimBus {
+    devices: Vec<SimulatedDevice>,
+}
+
+impl SimBus {
+    /// Create a bus with the provided devices attached
+    pub fn new(devices: &[&SimulatedDevice]) -> Self {
+        Self { devices: devices.iter().map(|d| (*d).clone()).collect() }
+    }
+
+    /// Clock a byte out to all selected devices, returning the byte clocked in
+    fn clock(&mut self, out: u8) -> u8 {
+        let mut r = 0;
+
+        for d in &self.devices {
+            let mut s = d.state();
+            if s.selected && !s.in_reset {
+                r |= s.spi_clock(out);
+            }
+        }
+
+        r
+    }
+}
+
+impl i2c::ErrorType for SimBus {
+    type Error = SimError;
+}
+
+impl I2c<SevenBitAddress> for SimBus {
+    fn transaction(&mut self, address: u8, operations: &mut [i2c::Operation<'_>]) -> Result<(), Self::Error> {
+        let address = Address::SevenBit(address);
+
+        match self.devices.iter().find(|d| d.state().address == address) {
+            Some(d) => d.i2c().transaction_at(address, operations),
+            None => Err(SimError::Nack),
+        }
+    }
+}
+
+impl spi::ErrorType for SimBus {
+    type Error = SimError;
+}
+
+impl SpiBus for SimBus {
+    fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        for w in words.iter_mut() {
+            *w = self.clock(0x00);
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        for w in words.iter() {
+            self.clock(*w);
+        }
+        Ok(())
+    }
+
+    fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+        for i in 0..read.len().max(write.len()) {
+            let r = self.clock(write.get(i).copied().unwrap_or(0));
+            if let Some(b) = read.get_mut(i) {
+                *b = r;
+            }
+        }
+        Ok(())
+    }
+
+    fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        for w in words.iter_mut() {
+            *w = self.clock(*w);
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+
+/// Simulated SPI chip select input, active low
+#[derive(Debug, Clone)]
+pub struct SimCs(SimulatedDevice);
+
+impl digital::ErrorType for SimCs {
+    type Error = SimError;
+}
+
+impl OutputPin for SimCs {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        let mut s = self.0.state();
+
+        // Selecting the device starts a new frame
+        s.selected = true;
+        s.spi_cmd = None;
+
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.0.state().selected = false;
+        Ok(())
+    }
+}
+
+
+/// Simulated busy output
+#[derive(Debug, Clone)]
+pub struct SimBusy(SimulatedDevice);
+
+impl digital::ErrorType for SimBusy {
+    type Error = SimError;
+}
+
+impl InputPin for SimBusy {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        let s = self.0.state();
+        Ok(s.busy_polarity.level(s.busy()))
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        self.is_high().map(|h| !h)
+    }
+}
+
+
+/// Simulated interrupt output, active low
+#[derive(Debug, Clone)]
+pub struct SimIrq(SimulatedDevice);
+
+impl digital::ErrorType for SimIrq {
+    type Error = SimError;
+}
+
+impl InputPin for SimIrq {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        self.is_low().map(|l| !l)
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        let s = self.0.state();
+
+        let control = registers::Control(s.regs[registers::Control::ADDRESS as usize]);
+        let pending = s.regs[registers::IrqStatus::ADDRESS as usize] & s.regs[registers::IrqEnable::ADDRESS as usize];
+
+        Ok(control.irq_enable() && pending != 0)
+    }
+}
+
+
+/// Simulated I2C SCL line, as an open-drain GPIO
+#[derive(Debug, Clone)]
+pub struct SimScl(SimulatedDevice);
+
+impl digital::ErrorType for SimScl {
+    type Error = SimError;
+}
+
+impl OutputPin for SimScl {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.0.state().scl = false;
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        let mut s = self.0.state();
+
+        // Device shifts out a bit on each rising edge
+        if !s.scl && s.sda_held_clocks > 0 {
+            s.sda_held_clocks -= 1;
+        }
+        s.scl = true;
+
+        Ok(())
+    }
+}
+
+
+/// Simulated I2C SDA line, as an open-drain GPIO
+#[derive(Debug, Clone)]
+pub struct SimSda(SimulatedDevice);
+
+impl digital::ErrorType for SimSda {
+    type Error = SimError;
+}
+
+impl OutputPin for SimSda {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.0.state().sda = false;
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        let mut s = self.0.state();
+
+        // SDA rising while SCL is high is a STOP, which resets
+        // the device bus interface once it has released SDA
+        if s.scl && !s.sda && s.sda_held_clocks == 0 {
+            s.bus_stuck = false;
+        }
+        s.sda = true;
+
+        Ok(())
+    }
+}
+
+impl InputPin for SimSda {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        let s = self.0.state();
+        Ok(s.sda && s.sda_held_clocks == 0)
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        self.is_high().map(|h| !h)
+    }
+}
+
+
+/// Simulated reset input
+#[derive(Debug, Clone)]
+pub struct SimReset(SimulatedDevice);
+
+impl SimReset {
+    fn set(&mut self, high: bool) {
+        let mut s = self.0.state();
+        let asserted = high == s.reset_polarity.level(true);
+
+        if asserted && !s.in_reset {
+            s.in_reset = true;
+            s.resets += 1;
+            s.reset_registers();
+        } else if !asserted && s.in_reset {
+            s.in_reset = false;
+            s.reset_at_ns = s.now_ns;
+        }
+    }
+}
+
+impl digital::ErrorType for SimReset {
+    type Error = SimError;
+}
+
+impl OutputPin for SimReset {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.set(false);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.set(true);
+        Ok(())
+    }
+}
+
+
+/// Simulated delay, advances simulated time without blocking
+#[derive(Debug, Clone)]
+pub struct SimDelay(SimulatedDevice);
+
+impl DelayNs for SimDelay {
+    fn delay_ns(&mut self, ns: u32) {
+        self.0.state().now_ns += ns as u64;
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        self.0.state().now_ns += us as u64 * 1_000;
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        self.0.state().now_ns += ms as u64 * 1_000_000;
+    }
+}
+
+
+/// Simulated monotonic clock
+#[derive(Debug, Clone)]
+pub struct SimClock(SimulatedDevice);
+
+impl Clock for SimClock {
+    fn now_ms(&mut self) -> u32 {
+        self.0.now_ms() as u32
+    }
+}
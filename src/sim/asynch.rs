@@ -0,0 +1,105 @@
+//! Async implementations of the simulated handles (requires the `async` feature)
+//!
+//! Simulated time only advances while a delay is pending, in steps of at
+//! most `TICK_NS`, so a busy wait racing a timeout resolves in time order.
+
+use core::future::poll_fn;
+use core::task::Poll;
+
+use embedded_hal::digital::InputPin;
+use embedded_hal::i2c::{self, SevenBitAddress, TenBitAddress};
+use embedded_hal::spi;
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::digital::Wait;
+
+use super::{SimBusy, SimDelay, SimError, SimI2c, SimSpi, SimulatedDevice};
+
+/// Simulated time advanced per poll of a pending delay
+const TICK_NS: u64 = 1_000_000;
+
+impl embedded_hal_async::i2c::I2c<SevenBitAddress> for SimI2c {
+    async fn transaction(&mut self, address: u8, operations: &mut [i2c::Operation<'_>]) -> Result<(), Self::Error> {
+        i2c::I2c::<SevenBitAddress>::transaction(self, address, operations)
+    }
+}
+
+impl embedded_hal_async::i2c::I2c<TenBitAddress> for SimI2c {
+    async fn transaction(&mut self, address: u16, operations: &mut [i2c::Operation<'_>]) -> Result<(), Self::Error> {
+        i2c::I2c::<TenBitAddress>::transaction(self, address, operations)
+    }
+}
+
+impl embedded_hal_async::spi::SpiDevice for SimSpi {
+    async fn transaction(&mut self, operations: &mut [spi::Operation<'_, u8>]) -> Result<(), Self::Error> {
+        spi::SpiDevice::transaction(self, operations)
+    }
+}
+
+/// Advance simulated time by `ns`, yielding once per tick
+async fn advance(dev: &SimulatedDevice, ns: u64) {
+    let deadline = dev.state().now_ns + ns;
+
+    poll_fn(|cx| {
+        let mut s = dev.state();
+        s.now_ns = deadline.min(s.now_ns + TICK_NS);
+
+        if s.now_ns >= deadline {
+            return Poll::Ready(());
+        }
+
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }).await
+}
+
+impl DelayNs for SimDelay {
+    async fn delay_ns(&mut self, ns: u32) {
+        advance(&self.0, ns as u64).await
+    }
+
+    async fn delay_us(&mut self, us: u32) {
+        advance(&self.0, us as u64 * 1_000).await
+    }
+
+    async fn delay_ms(&mut self, ms: u32) {
+        advance(&self.0, ms as u64 * 1_000_000).await
+    }
+}
+
+impl SimBusy {
+    /// Wait for the busy output to reach the provided level
+    async fn wait_level(&mut self, high: bool) -> Result<(), SimError> {
+        poll_fn(|cx| match self.is_high() {
+            Ok(h) if h != high => {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+            r => Poll::Ready(r.map(|_| ())),
+        }).await
+    }
+}
+
+impl Wait for SimBusy {
+    async fn wait_for_high(&mut self) -> Result<(), Self::Error> {
+        self.wait_level(true).await
+    }
+
+    async fn wait_for_low(&mut self) -> Result<(), Self::Error> {
+        self.wait_level(false).await
+    }
+
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Self::Error> {
+        self.wait_level(false).await?;
+        self.wait_level(true).await
+    }
+
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Self::Error> {
+        self.wait_level(true).await?;
+        self.wait_level(false).await
+    }
+
+    async fn wait_for_any_edge(&mut self) -> Result<(), Self::Error> {
+        let high = self.is_high()?;
+        self.wait_level(!high).await
+    }
+}
@@ -0,0 +1,300 @@
+//! SMBus protocol over I2C
+//!
+//! `Smbus` implements the SMBus transfer types over an embedded-hal `I2c`
+//! bus, with optional Packet Error Checking (PEC). It also implements
+//! `Interface`, so SMBus devices can be driven by `ExampleDriver` with
+//! register reads and writes mapped to byte / word data transfers:
+//!
+//! ```text
+//! let mut smbus = Smbus::new(i2c, address).with_pec(true);
+//! let v = smbus.read_word(0x10)?;
+//!
+//! let d = ExampleDriver::new(config, smbus, busy, reset, delay)?;
+//! ```
+//!
+//! When using PEC here leave `Config::checksum` as `Checksum::None`,
+//! otherwise the PEC byte is applied twice.
+
+use embedded_hal::i2c::{self, I2c, SevenBitAddress};
+
+use crate::checksum::{self, crc8_smbus};
+use crate::interface::{i2c_checksum_header, Address, BusErrorKind, Interface};
+
+
+/// Maximum SMBus block length
+pub const BLOCK_MAX: usize = 32;
+
+/// Maximum write length, command, count, block and PEC
+const WRITE_MAX: usize = BLOCK_MAX + 3;
+
+/// SMBus error type
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SmbusError<E> {
+    /// Underlying I2C error
+    I2c(E),
+    /// Received PEC did not match
+    Pec {
+        /// PEC computed over the received frame
+        expected: u8,
+        /// PEC received from the device
+        actual: u8,
+    },
+    /// Block length exceeded `BLOCK_MAX` or the provided buffer
+    BlockLength(usize),
+}
+
+impl<E: i2c::Error> i2c::Error for SmbusError<E> {
+    fn kind(&self) -> i2c::ErrorKind {
+        match self {
+            SmbusError::I2c(e) => e.kind(),
+            _ => i2c::ErrorKind::Other,
+        }
+    }
+}
+
+/// SMBus device
+pub struct Smbus<Bus> {
+    /// I2C bus
+    i2c: Bus,
+
+    /// Device address
+    address: SevenBitAddress,
+
+    /// Packet Error Checking enabled
+    pec: bool,
+}
+
+impl<Bus> Smbus<Bus>
+where
+    Bus: I2c,
+{
+    /// Create a new SMBus device at the provided address, with PEC disabled
+    pub fn new(i2c: Bus, address: SevenBitAddress) -> Self {
+        Self { i2c, address, pec: false }
+    }
+
+    /// Enable or disable Packet Error Checking
+    pub fn with_pec(self, pec: bool) -> Self {
+        Self { pec, ..self }
+    }
+
+    /// Release the SMBus device, returning the I2C bus
+    pub fn free(self) -> Bus {
+        self.i2c
+    }
+
+    /// Send a byte (with no command code)
+    pub fn send_byte(&mut self, value: u8) -> Result<(), SmbusError<Bus::Error>> {
+        self.write(&[value])
+    }
+
+    /// Receive a byte (with no command code)
+    pub fn receive_byte(&mut self) -> Result<u8, SmbusError<Bus::Error>> {
+        let mut buff = [0u8; 2];
+        let n = 1 + self.pec as usize;
+
+        self.i2c.read(self.address, &mut buff[..n]).map_err(SmbusError::I2c)?;
+
+        // Receive byte PEC covers only the read address and data
+        self.check_pec(&[self.read_address()], &buff[..n])?;
+
+        Ok(buff[0])
+    }
+
+    /// Write a byte to the provided command code
+    pub fn write_byte(&mut self, command: u8, value: u8) -> Result<(), SmbusError<Bus::Error>> {
+        self.write(&[command, value])
+    }
+
+    /// Read a byte from the provided command code
+    pub fn read_byte(&mut self, command: u8) -> Result<u8, SmbusError<Bus::Error>> {
+        let mut buff = [0u8; 1];
+        self.write_read(&[command], &mut buff)?;
+
+        Ok(buff[0])
+    }
+
+    /// Write a word (little-endian) to the provided command code
+    pub fn write_word(&mut self, command: u8, value: u16) -> Result<(), SmbusError<Bus::Error>> {
+        let [lo, hi] = value.to_le_bytes();
+        self.write(&[command, lo, hi])
+    }
+
+    /// Read a word (little-endian) from the provided command code
+    pub fn read_word(&mut self, command: u8) -> Result<u16, SmbusError<Bus::Error>> {
+        let mut buff = [0u8; 2];
+        self.write_read(&[command], &mut buff)?;
+
+        Ok(u16::from_le_bytes(buff))
+    }
+
+    /// Write a word to the provided command code, returning the word read back
+    pub fn process_call(&mut self, command: u8, value: u16) -> Result<u16, SmbusError<Bus::Error>> {
+        let [lo, hi] = value.to_le_bytes();
+
+        let mut buff = [0u8; 2];
+        self.write_read(&[command, lo, hi], &mut buff)?;
+
+        Ok(u16::from_le_bytes(buff))
+    }
+
+    /// Write a block of up to `BLOCK_MAX` bytes to the provided command code
+    pub fn block_write(&mut self, command: u8, data: &[u8]) -> Result<(), SmbusError<Bus::Error>> {
+        if data.len() > BLOCK_MAX {
+            return Err(SmbusError::BlockLength(data.len()));
+        }
+
+        let mut buff = [0u8; WRITE_MAX];
+        buff[0] = command;
+        buff[1] = data.len() as u8;
+        buff[2..][..data.len()].copy_from_slice(data);
+
+        self.write(&buff[..data.len() + 2])
+    }
+
+    /// Read a block from the provided command code into `buff`, returning
+    /// the block length
+    ///
+    /// Transactions can't be extended once started, so `buff.len()` bytes
+    /// are always read and `buff` should be sized for the expected block.
+    pub fn block_read(&mut self, command: u8, buff: &mut [u8]) -> Result<usize, SmbusError<Bus::Error>> {
+        if buff.len() > BLOCK_MAX {
+            return Err(SmbusError::BlockLength(buff.len()));
+        }
+
+        // Count byte and block (and PEC if enabled)
+        let mut frame = [0u8; BLOCK_MAX + 2];
+        let frame = &mut frame[..buff.len() + 1 + self.pec as usize];
+
+        self.i2c.write_read(self.address, &[command], frame).map_err(SmbusError::I2c)?;
+
+        let count = frame[0] as usize;
+        if count > buff.len() {
+            return Err(SmbusError::BlockLength(count));
+        }
+
+        // PEC follows the last block byte, anything after it is padding
+        if self.pec {
+            let header = [self.write_address(), command, self.read_address()];
+            let (data, rest) = frame.split_at(count + 1);
+            self.verify(&header, data, rest[0])?;
+        }
+
+        buff[..count].copy_from_slice(&frame[1..][..count]);
+
+        Ok(count)
+    }
+
+    /// Write `data`, appending the PEC if enabled
+    fn write(&mut self, data: &[u8]) -> Result<(), SmbusError<Bus::Error>> {
+        let mut buff = [0u8; WRITE_MAX + 1];
+        buff[..data.len()].copy_from_slice(data);
+
+        let mut n = data.len();
+        if self.pec {
+            buff[n] = crc8_smbus(crc8_smbus(0, &[self.write_address()]), data);
+            n += 1;
+        }
+
+        self.i2c.write(self.address, &buff[..n]).map_err(SmbusError::I2c)
+    }
+
+    /// Write `data` then read `buff.len()` bytes, checking the PEC if enabled
+    fn write_read(&mut self, data: &[u8], buff: &mut [u8]) -> Result<(), SmbusError<Bus::Error>> {
+        if data.len() > WRITE_MAX || buff.len() > BLOCK_MAX + 1 {
+            return Err(SmbusError::BlockLength(data.len().max(buff.len())));
+        }
+
+        let mut frame = [0u8; BLOCK_MAX + 2];
+        let frame = &mut frame[..buff.len() + self.pec as usize];
+
+        self.i2c.write_read(self.address, data, frame).map_err(SmbusError::I2c)?;
+
+        if self.pec {
+            let mut header = [0u8; WRITE_MAX + 2];
+            header[0] = self.write_address();
+            header[1..][..data.len()].copy_from_slice(data);
+            header[1 + data.len()] = self.read_address();
+
+            self.check_pec(&header[..data.len() + 2], frame)?;
+        }
+
+        buff.copy_from_slice(&frame[..buff.len()]);
+
+        Ok(())
+    }
+
+    /// Check the trailing PEC of `frame`, if enabled
+    fn check_pec(&self, header: &[u8], frame: &[u8]) -> Result<(), SmbusError<Bus::Error>> {
+        if !self.pec {
+            return Ok(());
+        }
+
+        let (data, pec) = frame.split_at(frame.len() - 1);
+        self.verify(header, data, pec[0])
+    }
+
+    /// Check `pec` matches the PEC over `header` and `data`
+    fn verify(&self, header: &[u8], data: &[u8], pec: u8) -> Result<(), SmbusError<Bus::Error>> {
+        let expected = crc8_smbus(crc8_smbus(0, header), data);
+
+        match expected == pec {
+            true => Ok(()),
+            false => Err(SmbusError::Pec { expected, actual: pec }),
+        }
+    }
+
+    fn write_address(&self) -> u8 {
+        self.address << 1
+    }
+
+    fn read_address(&self) -> u8 {
+        (self.address << 1) | 1
+    }
+}
+
+/// Register reads and writes are byte / word data transfers (for one and
+/// two byte registers). Words are sent little-endian as SMBus requires, and
+/// converted to and from the register byte order (most significant byte
+/// first). Longer accesses return `SmbusError::BlockLength`.
+impl<Bus> Interface for Smbus<Bus>
+where
+    Bus: I2c,
+{
+    type Error = SmbusError<Bus::Error>;
+
+    fn read_register(&mut self, reg: u8, buff: &mut [u8]) -> Result<(), Self::Error> {
+        match buff.len() {
+            1 => buff[0] = self.read_byte(reg)?,
+            2 => buff.copy_from_slice(&self.read_word(reg)?.to_be_bytes()),
+            n => return Err(SmbusError::BlockLength(n)),
+        }
+
+        Ok(())
+    }
+
+    fn write_register(&mut self, reg: u8, data: &[u8]) -> Result<(), Self::Error> {
+        match *data {
+            [value] => self.write_byte(reg, value),
+            [hi, lo] => self.write_word(reg, u16::from_be_bytes([hi, lo])),
+            _ => Err(SmbusError::BlockLength(data.len())),
+        }
+    }
+
+    fn transfer(&mut self, data: &[u8], buff: &mut [u8]) -> Result<(), Self::Error> {
+        self.write_read(data, buff)
+    }
+
+    fn error_kind(error: &Self::Error) -> BusErrorKind {
+        match error {
+            // PEC mismatches are corrupted frames, so retried like checksum mismatches
+            SmbusError::Pec { .. } => BusErrorKind::Checksum,
+            e => i2c::Error::kind(e).into(),
+        }
+    }
+
+    fn checksum_header(&self, reg: u8, read: bool, buff: &mut [u8; checksum::MAX_HEADER]) -> usize {
+        i2c_checksum_header(Address::SevenBit(self.address), reg, read, buff)
+    }
+}
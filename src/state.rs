@@ -0,0 +1,123 @@
+//! Device lifecycle states
+//!
+//! `ExampleDriver` carries one of these as a type parameter so operations
+//! that aren't valid in the current state fail at compile time. Transitions
+//! consume the driver and return it in the new state:
+//!
+//! ```text
+//! Unconfigured --configure()--> Ready --sleep()--> Sleeping
+//!      ^                          |  <--wake()---
+//!      +---------reset()----------+
+//! ```
+//!
+//! Register access is only available while the device is awake, and
+//! `sleep()` / `reset()` only once it has been configured:
+//!
+//! ```
+//! # use driver_example::sim::SimulatedDevice;
+//! use driver_example::{Config, ExampleDriver, I2cInterface, registers::Status};
+//!
+//! # let dev = SimulatedDevice::new();
+//! # let iface = I2cInterface::new(dev.i2c(), dev.address());
+//! let mut d = ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+//! d.read_reg::<Status>().unwrap();
+//!
+//! let d = d.sleep().unwrap();
+//! let mut d = d.wake().unwrap();
+//! d.read_reg::<Status>().unwrap();
+//! ```
+//!
+//! ```compile_fail
+//! # use driver_example::sim::SimulatedDevice;
+//! use driver_example::{Config, ExampleDriver, I2cInterface, registers::Status};
+//!
+//! # let dev = SimulatedDevice::new();
+//! # let iface = I2cInterface::new(dev.i2c(), dev.address());
+//! let d = ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+//!
+//! let mut d = d.sleep().unwrap();
+//! d.read_reg::<Status>().unwrap();
+//! ```
+//!
+//! ```compile_fail
+//! # use driver_example::sim::SimulatedDevice;
+//! use driver_example::{Config, ExampleDriver, I2cInterface};
+//!
+//! # let dev = SimulatedDevice::new();
+//! # let iface = I2cInterface::new(dev.i2c(), dev.address());
+//! let d = ExampleDriver::new_unconfigured(Config::default(), iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+//!
+//! let d = d.sleep().unwrap();
+//! ```
+//!
+//! While `Ready`, raw register writes that would put the device to sleep
+//! or reset it are refused with `Error::StateChange`, as the driver's
+//! state would no longer match the device. Use the transitions instead.
+
+use crate::registers::{Command, Control, Mode, Register};
+use crate::SOFT_RESET;
+
+/// Device has been reset (or not yet initialised) and is not configured
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Unconfigured;
+
+/// Device is configured and ready for use
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ready;
+
+/// Device is configured and in low power sleep
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sleeping;
+
+/// Marker for states in which the device responds to register access
+pub trait Awake: private::Sealed {}
+
+impl Awake for Unconfigured {}
+impl Awake for Ready {}
+
+/// Check a raw write of `data` to the registers starting at `start` leaves
+/// the device in `State`, returning the address of the offending register
+///
+/// This is shared by the blocking and async drivers
+pub(crate) fn check_write<State: Awake>(start: u8, data: &[u8]) -> Result<(), u8> {
+    if !State::CONFIGURED {
+        return Ok(());
+    }
+
+    let value = |addr: u8| addr.checked_sub(start).and_then(|i| data.get(i as usize)).copied();
+
+    if let Some(c) = value(Control::ADDRESS) {
+        if Control(c).mode() == Some(Mode::Sleep) {
+            return Err(Control::ADDRESS);
+        }
+    }
+
+    if value(Command::ADDRESS) == Some(SOFT_RESET) {
+        return Err(Command::ADDRESS);
+    }
+
+    Ok(())
+}
+
+/// Control register value switching the device from `c` into `mode`,
+/// for the `sleep()` / `wake()` transitions
+///
+/// This is shared by the blocking and async drivers
+pub(crate) fn mode_control(mut c: Control, mode: Mode) -> Control {
+    c.set_mode(mode);
+    c
+}
+
+mod private {
+    pub trait Sealed {
+        /// Device has been configured, so must not be slept or reset behind the driver's back
+        const CONFIGURED: bool;
+    }
+
+    impl Sealed for super::Unconfigured {
+        const CONFIGURED: bool = false;
+    }
+    impl Sealed for super::Ready {
+        const CONFIGURED: bool = true;
+    }
+}
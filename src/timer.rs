@@ -0,0 +1,146 @@
+//! Timeout measurement
+//!
+//! By default the driver measures timeouts by summing the delays it has
+//! performed, which ignores time spent on bus transactions. Wrap the delay
+//! with a `Clock` using `WithClock` to measure timeouts against a real
+//! monotonic counter instead.
+//!
+//! `Clock` is deliberately minimal so it can be implemented over whatever
+//! timer your platform provides, for example with a `fugit` based
+//! monotonic:
+//!
+//! ```text
+//! impl Clock for MyMonotonic {
+//!     fn now_ms(&mut self) -> u32 {
+//!         Self::now().duration_since_epoch().to_millis() as u32
+//!     }
+//! }
+//! ```
+
+use embedded_hal::delay::DelayNs;
+
+
+/// Monotonic millisecond clock
+pub trait Clock {
+    /// Fetch the current time in milliseconds
+    ///
+    /// The epoch is arbitrary and the value may wrap, only the
+    /// (wrapping) difference between readings is used.
+    fn now_ms(&mut self) -> u32;
+}
+
+/// Delay with an optional clock, used by the driver to wait and measure timeouts
+///
+/// This is implemented for all `DelayNs` implementations (without a clock),
+/// and for `WithClock`.
+pub trait Timer {
+    /// Block for `ms` milliseconds
+    fn delay_ms(&mut self, ms: u32);
+
+    /// Fetch the current time in milliseconds, if a clock is available
+    fn now_ms(&mut self) -> Option<u32>;
+}
+
+impl<D: DelayNs> Timer for D {
+    fn delay_ms(&mut self, ms: u32) {
+        DelayNs::delay_ms(self, ms)
+    }
+
+    fn now_ms(&mut self) -> Option<u32> {
+        None
+    }
+}
+
+
+/// Delay combined with a monotonic clock for accurate timeouts
+#[derive(Debug, Clone, PartialEq)]
+pub struct WithClock<Delay, Clk> {
+    /// Delay implementation
+    pub delay: Delay,
+    /// Clock implementation
+    pub clock: Clk,
+}
+
+impl<Delay, Clk> WithClock<Delay, Clk> {
+    /// Combine a delay and a clock
+    pub fn new(delay: Delay, clock: Clk) -> Self {
+        Self { delay, clock }
+    }
+
+    /// Split back into the delay and clock
+    pub fn split(self) -> (Delay, Clk) {
+        (self.delay, self.clock)
+    }
+}
+
+impl<Delay: DelayNs, Clk: Clock> Timer for WithClock<Delay, Clk> {
+    fn delay_ms(&mut self, ms: u32) {
+        self.delay.delay_ms(ms)
+    }
+
+    fn now_ms(&mut self) -> Option<u32> {
+        Some(self.clock.now_ms())
+    }
+}
+
+
+/// Async delay with an optional clock (requires the `async` feature)
+#[cfg(feature = "async")]
+#[allow(async_fn_in_trait)]
+pub trait AsyncTimer {
+    /// Wait for `ms` milliseconds
+    async fn delay_ms(&mut self, ms: u32);
+
+    /// Fetch the current time in milliseconds, if a clock is available
+    fn now_ms(&mut self) -> Option<u32>;
+}
+
+#[cfg(feature = "async")]
+impl<D: embedded_hal_async::delay::DelayNs> AsyncTimer for D {
+    async fn delay_ms(&mut self, ms: u32) {
+        embedded_hal_async::delay::DelayNs::delay_ms(self, ms).await
+    }
+
+    fn now_ms(&mut self) -> Option<u32> {
+        None
+    }
+}
+
+#[cfg(feature = "async")]
+impl<Delay: embedded_hal_async::delay::DelayNs, Clk: Clock> AsyncTimer for WithClock<Delay, Clk> {
+    async fn delay_ms(&mut self, ms: u32) {
+        self.delay.delay_ms(ms).await
+    }
+
+    fn now_ms(&mut self) -> Option<u32> {
+        Some(self.clock.now_ms())
+    }
+}
+
+
+/// Timeout tracker, using the clock where available and otherwise
+/// falling back to summing poll periods
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Timeout {
+    start: Option<u32>,
+    elapsed: u32,
+    limit: u32,
+}
+
+impl Timeout {
+    /// Start a timeout of `limit_ms` at time `now`
+    pub(crate) fn new(now: Option<u32>, limit_ms: u32) -> Self {
+        Self { start: now, elapsed: 0, limit: limit_ms }
+    }
+
+    /// Record a poll of `poll_ms` ending at time `now`,
+    /// returning `true` if the timeout has expired
+    pub(crate) fn expired(&mut self, now: Option<u32>, poll_ms: u32) -> bool {
+        self.elapsed = self.elapsed.saturating_add(poll_ms);
+
+        match (self.start, now) {
+            (Some(start), Some(now)) => now.wrapping_sub(start) > self.limit,
+            _ => self.elapsed > self.limit,
+        }
+    }
+}
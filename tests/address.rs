@@ -0,0 +1,130 @@
+//! I2C addressing and probe tests
+
+use embedded_hal::i2c::{SevenBitAddress, TenBitAddress};
+
+use driver_example::interface::{probe, DEFAULT_ADDRESS};
+use driver_example::mock::pin;
+use driver_example::registers::{ChipId, Control, Mode, Register};
+use driver_example::sim::SimulatedDevice;
+use driver_example::{Address, Config, ConfigError, ExampleDriver, I2cInterface};
+
+#[test]
+fn address_from_config() {
+    let dev = SimulatedDevice::new().with_address(0x42);
+
+    let config = Config { address: Address::SevenBit(0x42), ..Default::default() };
+    let iface = I2cInterface::from_config(dev.i2c(), &config).unwrap();
+    assert_eq!(iface.address(), Address::SevenBit(0x42));
+
+    let mut d = ExampleDriver::new(config, iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+    assert_eq!(d.read_reg::<Control>().unwrap().mode(), Some(Mode::Normal));
+}
+
+#[test]
+fn default_address() {
+    let dev = SimulatedDevice::new();
+
+    let config = Config::default();
+    assert_eq!(config.address, Address::SevenBit(DEFAULT_ADDRESS));
+
+    let iface = I2cInterface::from_config(dev.i2c(), &config).unwrap();
+    ExampleDriver::new(config, iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+}
+
+#[test]
+fn ten_bit_address() {
+    let dev = SimulatedDevice::new().with_ten_bit_address(0x2a5);
+
+    let config = Config { address: Address::TenBit(0x2a5), ..Default::default() };
+    let iface = I2cInterface::from_config_ten_bit(dev.i2c(), &config).unwrap();
+
+    let mut d = ExampleDriver::new(config, iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+    assert_eq!(d.read_reg::<Control>().unwrap().mode(), Some(Mode::Normal));
+
+    // The device doesn't respond to 7-bit transactions
+    let iface = I2cInterface::new(dev.i2c(), 0xa5);
+    assert!(ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay()).is_err());
+
+    let iface = I2cInterface::new_ten_bit(dev.i2c(), 0x2a5);
+    ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+}
+
+#[test]
+fn invalid_addresses() {
+    let dev = SimulatedDevice::new();
+
+    // Address mode must match the interface
+    let config = Config { address: Address::TenBit(0x2a5), ..Default::default() };
+    assert!(matches!(I2cInterface::from_config(dev.i2c(), &config), Err(ConfigError::Address)));
+
+    let config = Config { address: Address::SevenBit(0x25), ..Default::default() };
+    assert!(matches!(I2cInterface::from_config_ten_bit(dev.i2c(), &config), Err(ConfigError::Address)));
+
+    // And be in range
+    let config = Config { address: Address::SevenBit(0x80), ..Default::default() };
+    assert_eq!(config.validate(), Err(ConfigError::Address));
+    assert!(matches!(I2cInterface::from_config(dev.i2c(), &config), Err(ConfigError::Address)));
+
+    let config = Config { address: Address::TenBit(0x400), ..Default::default() };
+    assert_eq!(config.validate(), Err(ConfigError::Address));
+}
+
+#[test]
+fn strapped_address() {
+    assert_eq!(Address::strapped(0), Address::SevenBit(DEFAULT_ADDRESS));
+    assert_eq!(Address::strapped(3), Address::SevenBit(DEFAULT_ADDRESS + 3));
+
+    // ADDR0 high, ADDR1 low
+    let mut pins = [
+        pin::Mock::new(&[pin::Transaction::get(pin::State::High)]),
+        pin::Mock::new(&[pin::Transaction::get(pin::State::Low)]),
+    ];
+    assert_eq!(Address::from_strap_pins(&mut pins).unwrap(), Address::SevenBit(DEFAULT_ADDRESS + 1));
+
+    for p in pins.iter_mut() {
+        p.done();
+    }
+
+    // Both high selects the last address
+    let mut pins = [
+        pin::Mock::new(&[pin::Transaction::get(pin::State::High)]),
+        pin::Mock::new(&[pin::Transaction::get(pin::State::High)]),
+    ];
+    assert_eq!(Address::from_strap_pins(&mut pins).unwrap(), Address::SevenBit(DEFAULT_ADDRESS + 3));
+
+    for p in pins.iter_mut() {
+        p.done();
+    }
+}
+
+#[test]
+fn probe_finds_device() {
+    let dev = SimulatedDevice::new().with_address(DEFAULT_ADDRESS + 2);
+    let candidates: Vec<_> = (0..4).map(Address::strapped).collect();
+
+    let found = probe::<_, SevenBitAddress>(&mut dev.i2c(), &candidates).unwrap();
+    assert_eq!(found, Address::strapped(2));
+
+    let config = Config { address: found, ..Default::default() };
+    let iface = I2cInterface::from_config(dev.i2c(), &config).unwrap();
+    ExampleDriver::new(config, iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+}
+
+#[test]
+fn probe_checks_chip_id() {
+    let dev = SimulatedDevice::new();
+    dev.poke(ChipId::ADDRESS, 0x00);
+
+    assert_eq!(probe::<_, SevenBitAddress>(&mut dev.i2c(), &[Address::default()]), None);
+}
+
+#[test]
+fn probe_ten_bit() {
+    let dev = SimulatedDevice::new().with_ten_bit_address(0x123);
+    let candidates = [Address::SevenBit(0x23), Address::TenBit(0x122), Address::TenBit(0x123)];
+
+    // 7-bit probe only tries 7-bit candidates
+    assert_eq!(probe::<_, SevenBitAddress>(&mut dev.i2c(), &candidates), None);
+
+    assert_eq!(probe::<_, TenBitAddress>(&mut dev.i2c(), &candidates), Some(Address::TenBit(0x123)));
+}
@@ -0,0 +1,158 @@
+//! Async driver tests against the simulated device (requires the `async` feature)
+
+#![cfg(feature = "async")]
+
+use core::future::Future;
+use core::pin::pin;
+use core::task::{Context, Poll, Waker};
+
+use driver_example::asynch::{ExampleDriver, I2cInterface, SpiInterface};
+use driver_example::fifo::{FifoConfig, FifoRead, Frame, FRAME_SIZE};
+use driver_example::registers::{Control, Mode, Register, Threshold};
+use driver_example::sim::SimulatedDevice;
+use driver_example::{Checksum, Config, DeviceInfo, Error, Operation, CHIP_ID};
+
+/// Run a future to completion by polling it in a loop
+///
+/// (the simulator wakes immediately, so there is nothing to wait on)
+fn block_on<F: Future>(f: F) -> F::Output {
+    let mut f = pin!(f);
+    let mut cx = Context::from_waker(Waker::noop());
+
+    loop {
+        if let Poll::Ready(r) = f.as_mut().poll(&mut cx) {
+            return r;
+        }
+    }
+}
+
+#[test]
+fn init_over_i2c() {
+    let dev = SimulatedDevice::new().with_revision(0x23);
+
+    let iface = I2cInterface::new(dev.i2c(), dev.address());
+    let mut d = block_on(ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay())).unwrap();
+
+    assert_eq!(dev.resets(), 1);
+    assert_eq!(d.device_info(), DeviceInfo { chip_id: CHIP_ID, revision: 0x23 });
+    assert_eq!(block_on(d.read_reg::<Control>()).unwrap().mode(), Some(Mode::Normal));
+}
+
+#[test]
+fn init_over_ten_bit_i2c() {
+    let dev = SimulatedDevice::new().with_ten_bit_address(0x2a5);
+
+    let iface = I2cInterface::new_ten_bit(dev.i2c(), 0x2a5);
+    let mut d = block_on(ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay())).unwrap();
+
+    assert_eq!(block_on(d.read_reg::<Control>()).unwrap().mode(), Some(Mode::Normal));
+}
+
+#[test]
+fn unexpected_device_fails() {
+    let dev = SimulatedDevice::new().with_chip_id(0x42);
+
+    let iface = SpiInterface::new(dev.spi());
+    let r = block_on(ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay()));
+
+    assert!(matches!(r, Err(Error::UnexpectedDevice { found: 0x42, expected: CHIP_ID })));
+}
+
+#[test]
+fn register_read_back() {
+    let dev = SimulatedDevice::new();
+
+    let iface = SpiInterface::new(dev.spi());
+    let mut d = block_on(ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay())).unwrap();
+
+    block_on(d.write_reg(Threshold(0x0abc))).unwrap();
+
+    assert_eq!(block_on(d.read_reg::<Threshold>()).unwrap().level(), 0x0abc);
+    assert_eq!((dev.peek(0x02), dev.peek(0x03)), (0x0a, 0xbc));
+}
+
+#[test]
+fn checksum_mismatch_reports_operation() {
+    let dev = SimulatedDevice::new().with_checksum(Checksum::Crc8Smbus);
+
+    let config = Config { checksum: Checksum::Crc8Smbus, ..Default::default() };
+    let iface = I2cInterface::new(dev.i2c(), dev.address());
+    let mut d = block_on(ExampleDriver::new(config, iface, dev.busy(), dev.reset(), dev.delay())).unwrap();
+
+    dev.corrupt_reads(1);
+    let e = block_on(d.read_reg::<Control>()).unwrap_err();
+    assert!(matches!(e, Error::Checksum { op: Operation::Read(Control::ADDRESS), .. }));
+    assert_eq!(e.operation(), Some(Operation::Read(Control::ADDRESS)));
+}
+
+#[test]
+fn busy_wait_within_timeout() {
+    let dev = SimulatedDevice::new().with_ready_after_ms(50);
+
+    let iface = I2cInterface::new(dev.i2c(), dev.address());
+    block_on(ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay())).unwrap();
+
+    // Waited on the busy pin rather than the full timeout
+    assert!(dev.now_ms() >= 50);
+    assert!(dev.now_ms() < Config::default().reset_timeout_ms as u64);
+}
+
+#[test]
+fn busy_wait_times_out() {
+    let config = Config::default();
+    let timeout = config.reset_timeout_ms;
+    let dev = SimulatedDevice::new().with_ready_after_ms(timeout + 50);
+
+    let iface = I2cInterface::new(dev.i2c(), dev.address());
+    let r = block_on(ExampleDriver::new(config, iface, dev.busy(), dev.reset(), dev.delay()));
+
+    assert!(matches!(r, Err(Error::ResetTimeout)));
+
+    // Driver gives up at the timeout, without waiting for the device
+    assert!(dev.now_ms() <= (timeout + 20) as u64);
+}
+
+#[test]
+fn sleep_and_wake() {
+    let dev = SimulatedDevice::new();
+
+    let iface = I2cInterface::new(dev.i2c(), dev.address());
+    let mut d = block_on(ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay())).unwrap();
+
+    // Sleeping is only possible through the state transition
+    let e = block_on(d.modify_reg::<Control, _>(|c| {
+        c.set_mode(Mode::Sleep);
+    })).unwrap_err();
+    assert_eq!(e, Error::StateChange(Control::ADDRESS));
+
+    let d = block_on(d.sleep()).unwrap();
+    assert_eq!(Control(dev.peek(Control::ADDRESS)).mode(), Some(Mode::Sleep));
+
+    let mut d = block_on(d.wake()).unwrap();
+    assert_eq!(block_on(d.read_reg::<Control>()).unwrap().mode(), Some(Mode::Normal));
+}
+
+#[test]
+fn fifo_read() {
+    let dev = SimulatedDevice::new().with_auto_increment(0x40);
+
+    // Frames are read without the auto-increment flag
+    let iface = SpiInterface::new(dev.spi()).with_auto_increment(0x40);
+    let mut d = block_on(ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay())).unwrap();
+
+    block_on(d.configure_fifo(FifoConfig::default())).unwrap();
+
+    for i in 0..12 {
+        dev.push_fifo(Frame { x: i, y: -i, z: 0x1234 });
+    }
+
+    // More than one burst of frames
+    let mut buff = [0u8; 16 * FRAME_SIZE];
+    let r = block_on(d.read_fifo(&mut buff)).unwrap();
+    assert_eq!(r, FifoRead { count: 12, ..Default::default() });
+
+    let frames: Vec<_> = r.frames(&buff).collect();
+    assert_eq!(frames.len(), 12);
+    assert_eq!(frames[11], Frame { x: 11, y: -11, z: 0x1234 });
+    assert_eq!(dev.fifo_level(), 0);
+}
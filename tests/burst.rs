@@ -0,0 +1,140 @@
+//! Burst (multi-register) access tests
+
+use driver_example::mock::{delay, i2c, pin, spi};
+use driver_example::registers::{Control, Mode, Register, Threshold, MAX_BURST};
+use driver_example::sim::SimulatedDevice;
+use driver_example::{Config, Error, ExampleDriver, I2cInterface, SpiInterface};
+
+const ADDR: u8 = 0x01;
+
+/// Pins and delay for a reset where the device is immediately ready
+fn pins() -> (pin::Mock, pin::Mock, delay::Mock) {
+    (
+        pin::Mock::new(&[pin::Transaction::get(pin::State::High)]),
+        pin::Mock::new(&[pin::Transaction::set(pin::State::Low), pin::Transaction::set(pin::State::High)]),
+        delay::Mock::new(&[delay::Transaction::Ms(10)]),
+    )
+}
+
+#[test]
+fn i2c_burst_sets_auto_increment_flag() {
+    let (busy, reset, delay) = pins();
+
+    let mut i2c = i2c::Mock::new(&[
+        i2c::Transaction::write_read(ADDR, &[0x0f], &[0x5a]),
+        i2c::Transaction::write_read(ADDR, &[0x0e], &[0x10]),
+        i2c::Transaction::write(ADDR, &[0x01, 0x02]),
+        // Burst read is a write then read with a repeated START
+        i2c::Transaction::write_read(ADDR, &[0x81], &[0x02, 0x01, 0x00]),
+        i2c::Transaction::write(ADDR, &[0x82, 0x01, 0x23]),
+        // Single byte accesses leave the flag clear
+        i2c::Transaction::write_read(ADDR, &[0x01], &[0x02]),
+        // Multi-byte registers are bursts too
+        i2c::Transaction::write_read(ADDR, &[0x82], &[0x01, 0x23]),
+    ]);
+
+    let iface = I2cInterface::new(i2c.clone(), ADDR).with_auto_increment(0x80);
+    let mut d = ExampleDriver::new(Config::default(), iface, busy, reset, delay).unwrap();
+
+    let mut buff = [0u8; 3];
+    d.read_regs(Control::ADDRESS, &mut buff).unwrap();
+    assert_eq!(buff, [0x02, 0x01, 0x00]);
+
+    d.write_regs(Threshold::ADDRESS, &[0x01, 0x23]).unwrap();
+
+    let mut buff = [0u8; 1];
+    d.read_regs(Control::ADDRESS, &mut buff).unwrap();
+
+    assert_eq!(d.read_reg::<Threshold>().unwrap().level(), 0x123);
+
+    i2c.done();
+}
+
+#[test]
+fn spi_burst_sets_auto_increment_flag() {
+    let (busy, reset, delay) = pins();
+
+    let mut spi = spi::Mock::new(&[
+        spi::Transaction::write_read(&[0x8f], &[0x5a]),
+        spi::Transaction::write_read(&[0x8e], &[0x10]),
+        spi::Transaction::write(&[0x01, 0x02]),
+        // Read and auto-increment flags are both set
+        spi::Transaction::write_read(&[0xc1], &[0x02, 0x01, 0x00]),
+        spi::Transaction::write(&[0x42, 0x01, 0x23]),
+    ]);
+
+    let iface = SpiInterface::new(spi.clone()).with_auto_increment(0x40);
+    let mut d = ExampleDriver::new(Config::default(), iface, busy, reset, delay).unwrap();
+
+    let mut buff = [0u8; 3];
+    d.read_regs(Control::ADDRESS, &mut buff).unwrap();
+    assert_eq!(buff, [0x02, 0x01, 0x00]);
+
+    d.write_regs(Threshold::ADDRESS, &[0x01, 0x23]).unwrap();
+
+    spi.done();
+}
+
+#[test]
+fn burst_round_trip_over_i2c() {
+    let dev = SimulatedDevice::new();
+
+    let iface = I2cInterface::new(dev.i2c(), dev.address());
+    let mut d = ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+
+    // Control and threshold in a single write
+    d.write_regs(Control::ADDRESS, &[0x05, 0x02, 0x34]).unwrap();
+
+    assert_eq!(d.read_reg::<Control>().unwrap().mode(), Some(Mode::Fast));
+    assert_eq!(d.read_reg::<Threshold>().unwrap().level(), 0x234);
+
+    let mut buff = [0u8; 3];
+    d.read_regs(Control::ADDRESS, &mut buff).unwrap();
+    assert_eq!(buff, [0x05, 0x02, 0x34]);
+}
+
+#[test]
+fn burst_with_auto_increment_flag_over_spi() {
+    let dev = SimulatedDevice::new().with_auto_increment(0x40);
+
+    let iface = SpiInterface::new(dev.spi()).with_auto_increment(0x40);
+    let mut d = ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+
+    d.write_regs(Threshold::ADDRESS, &[0x04, 0x56]).unwrap();
+    assert_eq!((dev.peek(0x02), dev.peek(0x03)), (0x04, 0x56));
+
+    let mut buff = [0u8; 2];
+    d.read_regs(Threshold::ADDRESS, &mut buff).unwrap();
+    assert_eq!(buff, [0x04, 0x56]);
+}
+
+#[test]
+fn missing_auto_increment_flag_repeats_register() {
+    let dev = SimulatedDevice::new().with_auto_increment(0x80);
+
+    let iface = I2cInterface::new(dev.i2c(), dev.address());
+    let mut d = ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+
+    // Without the flag every byte goes to the first register
+    d.write_regs(Threshold::ADDRESS, &[0x04, 0x56]).unwrap();
+    assert_eq!((dev.peek(0x02), dev.peek(0x03)), (0x56, 0x00));
+
+    let mut buff = [0u8; 2];
+    d.read_regs(Threshold::ADDRESS, &mut buff).unwrap();
+    assert_eq!(buff, [0x56, 0x56]);
+}
+
+#[test]
+fn oversized_burst_fails() {
+    let dev = SimulatedDevice::new();
+
+    let iface = I2cInterface::new(dev.i2c(), dev.address());
+    let mut d = ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+
+    let mut buff = [0u8; MAX_BURST + 1];
+    let e = d.read_regs(0x00, &mut buff).unwrap_err();
+    assert_eq!(e, Error::BurstLength(MAX_BURST + 1));
+    assert_eq!(e.operation(), None);
+
+    assert!(matches!(d.write_regs(0x00, &buff), Err(Error::BurstLength(33))));
+}
@@ -0,0 +1,194 @@
+//! Frame checksum tests
+
+use embedded_hal::i2c::ErrorKind;
+
+use driver_example::checksum::{crc16_ccitt, crc8_smbus, Checksum, MAX_HEADER};
+use driver_example::mock::{delay, i2c, pin, spi};
+use driver_example::registers::{Control, Mode, Register, Threshold};
+use driver_example::sim::SimulatedDevice;
+use driver_example::{Config, Error, ExampleDriver, I2cInterface, Interface, Operation, SpiInterface};
+
+const ADDR: u8 = 0x01;
+
+/// Pins and delay for a reset where the device is immediately ready
+fn pins() -> (pin::Mock, pin::Mock, delay::Mock) {
+    (
+        pin::Mock::new(&[pin::Transaction::get(pin::State::High)]),
+        pin::Mock::new(&[pin::Transaction::set(pin::State::Low), pin::Transaction::set(pin::State::High)]),
+        delay::Mock::new(&[delay::Transaction::Ms(10)]),
+    )
+}
+
+#[test]
+fn crc_check_values() {
+    assert_eq!(crc8_smbus(0, b"123456789"), 0xf4);
+    assert_eq!(crc16_ccitt(0xffff, b"123456789"), 0x29b1);
+
+    // Checksums can be computed incrementally
+    assert_eq!(Checksum::Crc8Smbus.compute(b"1234", b"56789"), 0xf4);
+    assert_eq!(Checksum::Crc16Ccitt.compute(b"", b"123456789"), 0x29b1);
+}
+
+#[test]
+fn append_and_verify() {
+    let mut frame = [0x12, 0x34, 0x00, 0x00];
+    Checksum::Crc16Ccitt.append(&[0x01], &mut frame);
+    assert_eq!(Checksum::Crc16Ccitt.verify(&[0x01], &frame), Ok(()));
+
+    frame[1] ^= 0x01;
+    assert!(Checksum::Crc16Ccitt.verify(&[0x01], &frame).is_err());
+
+    // No checksum always passes
+    assert_eq!(Checksum::None.verify(&[0x01], &[0x12]), Ok(()));
+}
+
+#[test]
+fn i2c_headers() {
+    let mut buff = [0u8; MAX_HEADER];
+
+    let iface = I2cInterface::new(i2c::Mock::new(&[]), 0x01);
+    let n = iface.checksum_header(0x0f, false, &mut buff);
+    assert_eq!(&buff[..n], &[0x02, 0x0f]);
+    let n = iface.checksum_header(0x0f, true, &mut buff);
+    assert_eq!(&buff[..n], &[0x02, 0x0f, 0x03]);
+
+    // 10-bit reads only repeat the first address byte
+    let iface = I2cInterface::new_ten_bit(SimulatedDevice::new().i2c(), 0x355);
+    let n = iface.checksum_header(0x0f, true, &mut buff);
+    assert_eq!(&buff[..n], &[0xf6, 0x55, 0x0f, 0xf7]);
+
+    let iface = SpiInterface::new(spi::Mock::new(&[]));
+    let n = iface.checksum_header(0x0f, true, &mut buff);
+    assert_eq!(&buff[..n], &[0x8f]);
+}
+
+#[test]
+fn smbus_pec_over_i2c() {
+    let (busy, reset, delay) = pins();
+
+    let mut i2c = i2c::Mock::new(&[
+        // Chip ID and revision, with PEC
+        i2c::Transaction::write_read(ADDR, &[0x0f], &[0x5a, 0xd5]),
+        i2c::Transaction::write_read(ADDR, &[0x0e], &[0x10, 0x4f]),
+        // Control register, with PEC
+        i2c::Transaction::write(ADDR, &[0x01, 0x02, 0xcd]),
+        // Threshold with a corrupted PEC
+        i2c::Transaction::write_read(ADDR, &[0x02], &[0x01, 0x00, 0x41]),
+        // Then corrupted data
+        i2c::Transaction::write_read(ADDR, &[0x02], &[0x01, 0x01, 0x40]),
+        // Then correct
+        i2c::Transaction::write_read(ADDR, &[0x02], &[0x01, 0x00, 0x40]),
+    ]);
+
+    let config = Config { checksum: Checksum::Crc8Smbus, ..Default::default() };
+    let iface = I2cInterface::new(i2c.clone(), ADDR);
+    let mut d = ExampleDriver::new(config, iface, busy, reset, delay).unwrap();
+
+    let e = d.read_reg::<Threshold>().unwrap_err();
+    assert_eq!(e, Error::Checksum { op: Operation::Read(Threshold::ADDRESS), expected: 0x40, actual: 0x41 });
+    assert_eq!(e.operation(), Some(Operation::Read(Threshold::ADDRESS)));
+    assert_eq!(e.to_string(), "checksum mismatch reading register 0x02, expected 0x0040 got 0x0041");
+
+    assert!(matches!(d.read_reg::<Threshold>(), Err(Error::Checksum { actual: 0x40, .. })));
+
+    assert_eq!(d.read_reg::<Threshold>().unwrap(), Threshold::default());
+
+    i2c.done();
+}
+
+#[test]
+fn pec_covers_auto_increment_flag() {
+    let (busy, reset, delay) = pins();
+
+    // The register byte in the PEC is as sent, with the flag set
+    // (the checksum byte makes every frame a multi-byte access)
+    let mut i2c = i2c::Mock::new(&[
+        i2c::Transaction::write_read(ADDR, &[0x8f], &[0x5a, 0xde]),
+        i2c::Transaction::write_read(ADDR, &[0x8e], &[0x10, 0x44]),
+        i2c::Transaction::write(ADDR, &[0x81, 0x02, 0x7b]),
+        i2c::Transaction::write(ADDR, &[0x82, 0x01, 0x23, 0x0d]),
+        // PEC computed over the register without the flag
+        i2c::Transaction::write_read(ADDR, &[0x82], &[0x01, 0x23, 0xa9]),
+        i2c::Transaction::write_read(ADDR, &[0x82], &[0x01, 0x23, 0x98]),
+    ]);
+
+    let config = Config { checksum: Checksum::Crc8Smbus, ..Default::default() };
+    let iface = I2cInterface::new(i2c.clone(), ADDR).with_auto_increment(0x80);
+    let mut d = ExampleDriver::new(config, iface, busy, reset, delay).unwrap();
+
+    d.write_regs(Threshold::ADDRESS, &[0x01, 0x23]).unwrap();
+
+    let mut buff = [0u8; 2];
+    let e = d.read_regs(Threshold::ADDRESS, &mut buff).unwrap_err();
+    assert_eq!(e, Error::Checksum { op: Operation::Read(Threshold::ADDRESS), expected: 0x98, actual: 0xa9 });
+
+    d.read_regs(Threshold::ADDRESS, &mut buff).unwrap();
+    assert_eq!(buff, [0x01, 0x23]);
+
+    i2c.done();
+}
+
+#[test]
+fn crc16_over_spi() {
+    let (busy, reset, delay) = pins();
+
+    let mut spi = spi::Mock::new(&[
+        spi::Transaction::write_read(&[0x8f], &[0x5a, 0xed, 0x16]),
+        spi::Transaction::write_read(&[0x8e], &[0x10, 0x37, 0xa9]),
+        spi::Transaction::write(&[0x01, 0x02, 0x0e, 0x7c]),
+        // Corrupted control register read
+        spi::Transaction::write_read(&[0x81], &[0x03, 0x00, 0x00]),
+    ]);
+
+    let config = Config { checksum: Checksum::Crc16Ccitt, ..Default::default() };
+    let iface = SpiInterface::new(spi.clone());
+    let mut d = ExampleDriver::new(config, iface, busy, reset, delay).unwrap();
+
+    assert!(matches!(d.read_reg::<Control>(), Err(Error::Checksum { actual: 0x0000, .. })));
+
+    spi.done();
+}
+
+#[test]
+fn corrupted_chip_id_fails_init() {
+    let (busy, reset, delay) = pins();
+
+    let mut i2c = i2c::Mock::new(&[
+        i2c::Transaction::write_read(ADDR, &[0x0f], &[0x5a, 0x00]),
+    ]);
+
+    let config = Config { checksum: Checksum::Crc8Smbus, ..Default::default() };
+    let iface = I2cInterface::new(i2c.clone(), ADDR);
+    let r = ExampleDriver::new(config, iface, busy, reset, delay);
+
+    assert!(matches!(r, Err(Error::Checksum { op: Operation::Read(0x0f), expected: 0xd5, actual: 0x00 })));
+
+    i2c.done();
+}
+
+#[test]
+fn bus_errors_take_precedence() {
+    let (busy, reset, delay) = pins();
+
+    let mut i2c = i2c::Mock::new(&[
+        i2c::Transaction::write_read(ADDR, &[0x0f], &[0x00, 0x00]).with_error(ErrorKind::Bus),
+    ]);
+
+    let config = Config { checksum: Checksum::Crc8Smbus, ..Default::default() };
+    let iface = I2cInterface::new(i2c.clone(), ADDR);
+    let r = ExampleDriver::new(config, iface, busy, reset, delay);
+
+    assert!(matches!(r, Err(Error::Interface { .. })));
+
+    i2c.done();
+}
+
+#[test]
+fn checksums_disabled_by_default() {
+    let dev = SimulatedDevice::new();
+
+    let iface = I2cInterface::new(dev.i2c(), dev.address());
+    let mut d = ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+
+    assert_eq!(d.read_reg::<Control>().unwrap().mode(), Some(Mode::Normal));
+}
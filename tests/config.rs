@@ -0,0 +1,124 @@
+//! Reset and busy line configuration tests
+
+use driver_example::mock::{delay, i2c, pin};
+use driver_example::{Config, ConfigError, Error, ExampleDriver, I2cInterface, Polarity};
+
+const ADDR: u8 = 0x01;
+
+fn level(high: bool) -> pin::State {
+    if high { pin::State::High } else { pin::State::Low }
+}
+
+/// Run `new()` with mocks expecting the reset sequence for `config`,
+/// with the device reporting busy for `busy_polls` polls
+fn check_reset_sequence(config: Config, busy_polls: usize) {
+    let reset_asserted = config.reset_polarity == Polarity::ActiveHigh;
+    let busy_asserted = config.busy_polarity == Polarity::ActiveHigh;
+
+    let mut reset = pin::Mock::new(&[
+        pin::Transaction::set(level(reset_asserted)),
+        pin::Transaction::set(level(!reset_asserted)),
+    ]);
+
+    let mut busy = pin::Mock::new(&vec![pin::Transaction::get(level(busy_asserted)); busy_polls]);
+    busy.expect(&[pin::Transaction::get(level(!busy_asserted))]);
+
+    let mut delay = delay::Mock::new(&[delay::Transaction::Ms(config.reset_pulse_ms)]);
+    if config.reset_settle_ms > 0 {
+        delay.expect(&[delay::Transaction::Ms(config.reset_settle_ms)]);
+    }
+    delay.expect(&vec![delay::Transaction::Ms(config.poll_ms); busy_polls]);
+
+    let mut i2c = i2c::Mock::new(&[
+        // Chip ID and revision
+        i2c::Transaction::write_read(ADDR, &[0x0f], &[0x5a]),
+        i2c::Transaction::write_read(ADDR, &[0x0e], &[0x10]),
+        i2c::Transaction::write(ADDR, &[0x01, 0x02]),
+    ]);
+
+    let iface = I2cInterface::new(i2c.clone(), ADDR);
+    ExampleDriver::new(config, iface, busy.clone(), reset.clone(), delay.clone()).unwrap();
+
+    i2c.done();
+    busy.done();
+    reset.done();
+    delay.done();
+}
+
+#[test]
+fn reset_and_busy_polarities() {
+    let polarities = [Polarity::ActiveLow, Polarity::ActiveHigh];
+
+    for reset_polarity in polarities.iter().copied() {
+        for busy_polarity in polarities.iter().copied() {
+            for busy_polls in 0..3 {
+                let config = Config { poll_ms: 10, reset_polarity, busy_polarity, ..Default::default() };
+                check_reset_sequence(config, busy_polls);
+            }
+        }
+    }
+}
+
+#[test]
+fn reset_pulse_and_settle_time() {
+    let config = Config { reset_pulse_ms: 50, reset_settle_ms: 25, ..Default::default() };
+    check_reset_sequence(config, 1);
+}
+
+#[test]
+fn no_settle_time() {
+    let config = Config { reset_pulse_ms: 1, reset_settle_ms: 0, ..Default::default() };
+    check_reset_sequence(config, 0);
+}
+
+#[test]
+fn reset_timeout() {
+    // 20 polls at 10ms fit within a 200ms timeout
+    let config = Config { poll_ms: 10, reset_timeout_ms: 200, ..Default::default() };
+    check_reset_sequence(config, 20);
+
+    // But the 21st exceeds it
+    let config = Config { poll_ms: 10, reset_timeout_ms: 200, ..Default::default() };
+    let mut busy = pin::Mock::new(&[pin::Transaction::get(pin::State::Low); 21]);
+    let mut delay = delay::Mock::new(&[delay::Transaction::Ms(config.reset_pulse_ms)]);
+    delay.expect(&[delay::Transaction::Ms(10); 21]);
+    let reset = pin::Mock::new(&[
+        pin::Transaction::set(pin::State::Low),
+        pin::Transaction::set(pin::State::High),
+    ]);
+
+    let iface = I2cInterface::new(i2c::Mock::new(&[]), ADDR);
+    let r = ExampleDriver::new(config, iface, busy.clone(), reset, delay.clone());
+
+    assert!(matches!(r, Err(Error::ResetTimeout)));
+    busy.done();
+    delay.done();
+}
+
+#[test]
+fn default_config_is_valid() {
+    assert_eq!(Config::default().validate(), Ok(()));
+}
+
+#[test]
+fn validation() {
+    let c = Config { poll_ms: 0, ..Default::default() };
+    assert_eq!(c.validate(), Err(ConfigError::PollPeriod));
+
+    let c = Config { reset_pulse_ms: 0, ..Default::default() };
+    assert_eq!(c.validate(), Err(ConfigError::ResetPulse));
+
+    let c = Config { poll_ms: 100, reset_timeout_ms: 50, ..Default::default() };
+    assert_eq!(c.validate(), Err(ConfigError::ResetTimeout));
+}
+
+#[test]
+fn invalid_config_rejected_without_touching_device() {
+    let config = Config { poll_ms: 0, ..Default::default() };
+
+    // Mocks with no expectations panic if used
+    let iface = I2cInterface::new(i2c::Mock::new(&[]), ADDR);
+    let r = ExampleDriver::new(config, iface, pin::Mock::new(&[]), pin::Mock::new(&[]), delay::Mock::new(&[]));
+
+    assert!(matches!(r, Err(Error::Config(ConfigError::PollPeriod))));
+}
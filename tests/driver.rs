@@ -0,0 +1,317 @@
+//! Driver tests using mock peripherals
+
+use embedded_hal::i2c::ErrorKind;
+
+use driver_example::mock::{delay, i2c, pin, spi, MockError};
+use driver_example::registers::{Control, Mode, Status, Threshold};
+use driver_example::{Config, ConfigError, Error, ExampleDriver, I2cInterface, Operation, SpiInterface};
+
+const RESET_PULSE_MS: u32 = 10;
+
+const ADDR: u8 = 0x01;
+
+/// Pin and delay expectations for a reset where the device is immediately ready
+fn reset_ok() -> (Vec<pin::Transaction>, Vec<pin::Transaction>, Vec<delay::Transaction>) {
+    (
+        vec![pin::Transaction::get(pin::State::High)],
+        vec![pin::Transaction::set(pin::State::Low), pin::Transaction::set(pin::State::High)],
+        vec![delay::Transaction::Ms(RESET_PULSE_MS)],
+    )
+}
+
+#[test]
+fn new_resets_and_configures_over_i2c() {
+    let (busy, reset, delay) = reset_ok();
+    let mut busy = pin::Mock::new(&busy);
+    let mut reset = pin::Mock::new(&reset);
+    let mut delay = delay::Mock::new(&delay);
+
+    // Control register set to normal mode
+    let mut i2c = i2c::Mock::new(&[
+        // Chip ID and revision
+        i2c::Transaction::write_read(ADDR, &[0x0f], &[0x5a]),
+        i2c::Transaction::write_read(ADDR, &[0x0e], &[0x10]),
+        i2c::Transaction::write(ADDR, &[0x01, 0x02]),
+    ]);
+
+    let iface = I2cInterface::new(i2c.clone(), ADDR);
+    ExampleDriver::new(Config::default(), iface, busy.clone(), reset.clone(), delay.clone()).unwrap();
+
+    i2c.done();
+    busy.done();
+    reset.done();
+    delay.done();
+}
+
+#[test]
+fn new_resets_and_configures_over_spi() {
+    let (busy, reset, delay) = reset_ok();
+    let mut busy = pin::Mock::new(&busy);
+    let mut reset = pin::Mock::new(&reset);
+    let mut delay = delay::Mock::new(&delay);
+
+    let mut spi = spi::Mock::new(&[
+        // Chip ID and revision
+        spi::Transaction::write_read(&[0x8f], &[0x5a]),
+        spi::Transaction::write_read(&[0x8e], &[0x10]),
+        spi::Transaction::write(&[0x01, 0x02]),
+    ]);
+
+    let iface = SpiInterface::new(spi.clone());
+    ExampleDriver::new(Config::default(), iface, busy.clone(), reset.clone(), delay.clone()).unwrap();
+
+    spi.done();
+    busy.done();
+    reset.done();
+    delay.done();
+}
+
+#[test]
+fn new_polls_busy_until_ready() {
+    let mut busy = pin::Mock::new(&[
+        pin::Transaction::get(pin::State::Low),
+        pin::Transaction::get(pin::State::Low),
+        pin::Transaction::get(pin::State::High),
+    ]);
+    let mut reset = pin::Mock::new(&[
+        pin::Transaction::set(pin::State::Low),
+        pin::Transaction::set(pin::State::High),
+    ]);
+    let mut delay = delay::Mock::new(&[
+        delay::Transaction::Ms(RESET_PULSE_MS),
+        delay::Transaction::Ms(10),
+        delay::Transaction::Ms(10),
+    ]);
+    let mut i2c = i2c::Mock::new(&[
+        // Chip ID and revision
+        i2c::Transaction::write_read(ADDR, &[0x0f], &[0x5a]),
+        i2c::Transaction::write_read(ADDR, &[0x0e], &[0x10]),
+        i2c::Transaction::write(ADDR, &[0x01, 0x02]),
+    ]);
+
+    let config = Config { poll_ms: 10, ..Default::default() };
+    let iface = I2cInterface::new(i2c.clone(), ADDR);
+    ExampleDriver::new(config, iface, busy.clone(), reset.clone(), delay.clone()).unwrap();
+
+    i2c.done();
+    busy.done();
+    reset.done();
+    delay.done();
+}
+
+#[test]
+fn new_times_out_when_busy() {
+    // Polling every 50ms, the third poll exceeds the 100ms timeout
+    let mut busy = pin::Mock::new(&[pin::Transaction::get(pin::State::Low); 3]);
+    let mut reset = pin::Mock::new(&[
+        pin::Transaction::set(pin::State::Low),
+        pin::Transaction::set(pin::State::High),
+    ]);
+    let mut delay = delay::Mock::new(&[
+        delay::Transaction::Ms(RESET_PULSE_MS),
+        delay::Transaction::Ms(50),
+        delay::Transaction::Ms(50),
+        delay::Transaction::Ms(50),
+    ]);
+    let mut i2c = i2c::Mock::new(&[]);
+
+    let config = Config { poll_ms: 50, ..Default::default() };
+    let iface = I2cInterface::new(i2c.clone(), ADDR);
+    let r = ExampleDriver::new(config, iface, busy.clone(), reset.clone(), delay.clone());
+
+    assert!(matches!(r, Err(Error::ResetTimeout)));
+
+    i2c.done();
+    busy.done();
+    reset.done();
+    delay.done();
+}
+
+#[test]
+fn new_reports_i2c_errors() {
+    let (busy, reset, delay) = reset_ok();
+
+    let mut i2c = i2c::Mock::new(&[
+        i2c::Transaction::write_read(ADDR, &[0x0f], &[0x00]).with_error(ErrorKind::Other),
+    ]);
+
+    let iface = I2cInterface::new(i2c.clone(), ADDR);
+    let r = ExampleDriver::new(Config::default(), iface, pin::Mock::new(&busy), pin::Mock::new(&reset), delay::Mock::new(&delay));
+
+    assert!(matches!(r, Err(Error::Interface { op: Operation::Read(0x0f), error: MockError::I2c(ErrorKind::Other) })));
+
+    i2c.done();
+}
+
+#[test]
+fn new_checks_chip_id() {
+    let (busy, reset, delay) = reset_ok();
+
+    let mut i2c = i2c::Mock::new(&[
+        // The revision isn't read from an unexpected device
+        i2c::Transaction::write_read(ADDR, &[0x0f], &[0x33]),
+    ]);
+
+    let iface = I2cInterface::new(i2c.clone(), ADDR);
+    let r = ExampleDriver::new(Config::default(), iface, pin::Mock::new(&busy), pin::Mock::new(&reset), delay::Mock::new(&delay));
+
+    let e = r.err().unwrap();
+    assert_eq!(e, Error::UnexpectedDevice { found: 0x33, expected: 0x5a });
+    assert_eq!(e.to_string(), "unexpected device, found chip ID 0x33 (expected 0x5a)");
+
+    i2c.done();
+}
+
+#[test]
+fn new_reports_pin_errors() {
+    let mut reset = pin::Mock::new(&[
+        pin::Transaction::set(pin::State::Low).with_error(),
+    ]);
+
+    let iface = I2cInterface::new(i2c::Mock::new(&[]), ADDR);
+    let r = ExampleDriver::new(Config::default(), iface, pin::Mock::new(&[]), reset.clone(), delay::Mock::new(&[]));
+
+    assert!(matches!(r, Err(Error::Pin { op: Operation::Reset, error: MockError::Pin })));
+
+    reset.done();
+}
+
+#[test]
+fn busy_pin_errors_report_operation() {
+    let mut busy = pin::Mock::new(&[
+        pin::Transaction::get(pin::State::High).with_error(),
+    ]);
+    let (_, reset, delay) = reset_ok();
+
+    let iface = I2cInterface::new(i2c::Mock::new(&[]), ADDR);
+    let r = ExampleDriver::new(Config::default(), iface, busy.clone(), pin::Mock::new(&reset), delay::Mock::new(&delay));
+
+    let e = r.err().unwrap();
+    assert_eq!(e.operation(), Some(Operation::WaitBusy));
+    assert_eq!(e, Error::Pin { op: Operation::WaitBusy, error: MockError::Pin });
+
+    busy.done();
+}
+
+#[test]
+fn register_errors_report_address() {
+    let (busy, reset, delay) = reset_ok();
+
+    let mut i2c = i2c::Mock::new(&[
+        // Chip ID and revision
+        i2c::Transaction::write_read(ADDR, &[0x0f], &[0x5a]),
+        i2c::Transaction::write_read(ADDR, &[0x0e], &[0x10]),
+        i2c::Transaction::write(ADDR, &[0x01, 0x02]),
+        i2c::Transaction::write_read(ADDR, &[0x02], &[0x00, 0x00]).with_error(ErrorKind::Bus),
+    ]);
+
+    let iface = I2cInterface::new(i2c.clone(), ADDR);
+    let mut d = ExampleDriver::new(Config::default(), iface, pin::Mock::new(&busy), pin::Mock::new(&reset), delay::Mock::new(&delay)).unwrap();
+
+    let e = d.read_reg::<Threshold>().unwrap_err();
+    assert_eq!(e.operation(), Some(Operation::Read(0x02)));
+    assert_eq!(e.to_string(), "interface error reading register 0x02: I2c(Bus)");
+
+    i2c.done();
+}
+
+#[test]
+fn error_display() {
+    let e: Error<MockError, MockError> = Error::Pin { op: Operation::Reset, error: MockError::Pin };
+    assert_eq!(e.to_string(), "pin error resetting device: Pin");
+
+    let e: Error<MockError, MockError> = Error::Interface { op: Operation::Write(0x04), error: MockError::Spi(embedded_hal::spi::ErrorKind::Overrun) };
+    assert_eq!(e.to_string(), "interface error writing register 0x04: Spi(Overrun)");
+
+    let e: Error<MockError, MockError> = Error::Config(ConfigError::PollPeriod);
+    assert_eq!(e.to_string(), "invalid configuration: poll period must be non-zero");
+    assert_eq!(e.operation(), None);
+
+    let e: Error<MockError, MockError> = Error::ResetTimeout;
+    assert_eq!(e.to_string(), "timeout waiting for device reset");
+    assert_eq!(e.operation(), Some(Operation::WaitBusy));
+
+    // Usable as a boxed error
+    let _: Box<dyn std::error::Error> = Box::new(e);
+}
+
+#[test]
+fn registers_over_i2c() {
+    let (busy, reset, delay) = reset_ok();
+
+    let mut i2c = i2c::Mock::new(&[
+        // Chip ID and revision
+        i2c::Transaction::write_read(ADDR, &[0x0f], &[0x5a]),
+        i2c::Transaction::write_read(ADDR, &[0x0e], &[0x10]),
+        i2c::Transaction::write(ADDR, &[0x01, 0x02]),
+        // Read status
+        i2c::Transaction::write_read(ADDR, &[0x00], &[0x05]),
+        // Modify control
+        i2c::Transaction::write_read(ADDR, &[0x01], &[0x02]),
+        i2c::Transaction::write(ADDR, &[0x01, 0x83]),
+    ]);
+
+    let iface = I2cInterface::new(i2c.clone(), ADDR);
+    let mut d = ExampleDriver::new(Config::default(), iface, pin::Mock::new(&busy), pin::Mock::new(&reset), delay::Mock::new(&delay)).unwrap();
+
+    let s = d.read_reg::<Status>().unwrap();
+    assert!(s.busy());
+    assert_eq!(s.mode(), Some(Mode::Normal));
+
+    let c = d.modify_reg::<Control, _>(|c| {
+        c.set_enable(true).set_irq_enable(true);
+    }).unwrap();
+    assert_eq!(c, Control(0x83));
+
+    i2c.done();
+}
+
+#[test]
+fn registers_over_spi() {
+    let (busy, reset, delay) = reset_ok();
+
+    let mut spi = spi::Mock::new(&[
+        // Chip ID and revision
+        spi::Transaction::write_read(&[0x8f], &[0x5a]),
+        spi::Transaction::write_read(&[0x8e], &[0x10]),
+        spi::Transaction::write(&[0x01, 0x02]),
+        // Read status, with the read flag set
+        spi::Transaction::write_read(&[0x80], &[0x05]),
+        // Modify control
+        spi::Transaction::write_read(&[0x81], &[0x02]),
+        spi::Transaction::write(&[0x01, 0x03]),
+    ]);
+
+    let iface = SpiInterface::new(spi.clone());
+    let mut d = ExampleDriver::new(Config::default(), iface, pin::Mock::new(&busy), pin::Mock::new(&reset), delay::Mock::new(&delay)).unwrap();
+
+    let s = d.read_reg::<Status>().unwrap();
+    assert!(s.busy());
+
+    d.modify_reg::<Control, _>(|c| {
+        c.set_enable(true);
+    }).unwrap();
+
+    spi.done();
+}
+
+#[test]
+fn free_returns_mocks() {
+    let (busy, reset, delay) = reset_ok();
+
+    let i2c = i2c::Mock::new(&[
+        // Chip ID and revision
+        i2c::Transaction::write_read(ADDR, &[0x0f], &[0x5a]),
+        i2c::Transaction::write_read(ADDR, &[0x0e], &[0x10]),
+        i2c::Transaction::write(ADDR, &[0x01, 0x02]),
+    ]);
+
+    let iface = I2cInterface::new(i2c, ADDR);
+    let d = ExampleDriver::new(Config::default(), iface, pin::Mock::new(&busy), pin::Mock::new(&reset), delay::Mock::new(&delay)).unwrap();
+
+    let (_iface, mut busy, mut reset, mut delay) = d.free();
+
+    busy.done();
+    reset.done();
+    delay.done();
+}
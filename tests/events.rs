@@ -0,0 +1,163 @@
+//! Interrupt and event queue tests
+
+use std::thread;
+
+use embedded_hal::digital::InputPin;
+
+use driver_example::events::{Event, EventQueue};
+use driver_example::mock::{delay, i2c, pin};
+use driver_example::registers::{Control, IrqEnable, IrqStatus, Register};
+use driver_example::sim::SimulatedDevice;
+use driver_example::{Config, ExampleDriver, I2cInterface};
+
+const ADDR: u8 = 0x01;
+
+#[test]
+fn queue_order_and_overflow() {
+    let q = EventQueue::<2>::new();
+    assert!(q.is_empty());
+
+    q.push(Event::DataReady).unwrap();
+    q.push(Event::Fault).unwrap();
+    assert_eq!(q.push(Event::Threshold), Err(Event::Threshold));
+    assert_eq!(q.len(), 2);
+    assert_eq!(q.dropped(), 1);
+
+    assert_eq!(q.pop(), Some(Event::DataReady));
+    q.push(Event::Threshold).unwrap();
+
+    let events: Vec<_> = q.poll_events().collect();
+    assert_eq!(events, vec![Event::Fault, Event::Threshold]);
+    assert_eq!(q.pop(), None);
+}
+
+#[test]
+fn queue_wraps() {
+    let q = EventQueue::<3>::new();
+
+    for i in 0..100 {
+        let e = Event::ALL[i % Event::ALL.len()];
+        q.push(e).unwrap();
+        assert_eq!(q.len(), 1);
+        assert_eq!(q.pop(), Some(e));
+    }
+}
+
+#[test]
+fn queue_across_threads() {
+    static QUEUE: EventQueue<4> = EventQueue::new();
+    const COUNT: usize = 10_000;
+
+    // Producer standing in for the interrupt handler
+    let producer = thread::spawn(|| {
+        for i in 0..COUNT {
+            let e = Event::ALL[i % Event::ALL.len()];
+            while QUEUE.push(e).is_err() {
+                thread::yield_now();
+            }
+        }
+    });
+
+    let mut received = 0;
+    while received < COUNT {
+        match QUEUE.pop() {
+            Some(e) => {
+                assert_eq!(e, Event::ALL[received % Event::ALL.len()]);
+                received += 1;
+            }
+            None => thread::yield_now(),
+        }
+    }
+
+    producer.join().unwrap();
+    assert!(QUEUE.is_empty());
+}
+
+#[test]
+fn latch_decodes_status() {
+    let q = EventQueue::<8>::new();
+
+    assert_eq!(q.latch(IrqStatus(0x00)), 0);
+    assert_eq!(q.latch(IrqStatus(0x05)), 2);
+
+    let events: Vec<_> = q.poll_events().collect();
+    assert_eq!(events, vec![Event::DataReady, Event::Fault]);
+}
+
+#[test]
+fn on_interrupt_reads_status() {
+    let events = EventQueue::<4>::new();
+
+    let mut i2c = i2c::Mock::new(&[
+        // Chip ID and revision
+        i2c::Transaction::write_read(ADDR, &[0x0f], &[0x5a]),
+        i2c::Transaction::write_read(ADDR, &[0x0e], &[0x10]),
+        i2c::Transaction::write(ADDR, &[0x01, 0x02]),
+        // Interrupt status with threshold set
+        i2c::Transaction::write_read(ADDR, &[0x05], &[0x02]),
+    ]);
+
+    let busy = pin::Mock::new(&[pin::Transaction::get(pin::State::High)]);
+    let reset = pin::Mock::new(&[
+        pin::Transaction::set(pin::State::Low),
+        pin::Transaction::set(pin::State::High),
+    ]);
+    let delay = delay::Mock::new(&[delay::Transaction::Ms(10)]);
+
+    let iface = I2cInterface::new(i2c.clone(), ADDR);
+    let mut d = ExampleDriver::new(Config::default(), iface, busy, reset, delay).unwrap();
+
+    let status = d.on_interrupt(&events).unwrap();
+    assert!(status.threshold());
+    assert_eq!(events.pop(), Some(Event::Threshold));
+
+    i2c.done();
+}
+
+#[test]
+fn simulated_interrupts() {
+    let dev = SimulatedDevice::new();
+    let mut irq = dev.irq();
+    let events = EventQueue::<8>::new();
+
+    let iface = I2cInterface::new(dev.i2c(), dev.address());
+    let mut d = ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+
+    // Interrupt output is disabled until interrupts are enabled
+    dev.raise_interrupt(0x01);
+    assert!(irq.is_high().unwrap());
+
+    d.enable_interrupts(*IrqEnable::default().set_data_ready(true).set_fault(true)).unwrap();
+    assert!(d.read_reg::<Control>().unwrap().irq_enable());
+    assert!(irq.is_low().unwrap());
+
+    // Service interrupts on the falling edge, as an interrupt handler would,
+    // while the device raises events over time
+    let sequence = [0x01, 0x00, 0x04, 0x02, 0x05];
+    for bits in sequence.iter() {
+        dev.raise_interrupt(*bits);
+
+        if irq.is_low().unwrap() {
+            d.on_interrupt(&events).unwrap();
+
+            // Reading the status clears the interrupt
+            assert_eq!(dev.peek(IrqStatus::ADDRESS), 0x00);
+        }
+
+        assert!(irq.is_high().unwrap());
+    }
+
+    // The threshold interrupt is not enabled so doesn't trigger the handler,
+    // but remains flagged in the status and is picked up with the next interrupt
+    let received: Vec<_> = events.poll_events().collect();
+    assert_eq!(received, vec![
+        Event::DataReady,
+        Event::Fault,
+        Event::DataReady, Event::Threshold, Event::Fault,
+    ]);
+
+    // Disabling interrupts releases the output
+    d.enable_interrupts(IrqEnable::default()).unwrap();
+    dev.raise_interrupt(0x01);
+    assert!(irq.is_high().unwrap());
+}
@@ -0,0 +1,275 @@
+//! FIFO streaming tests
+
+use embedded_hal::digital::InputPin;
+
+use driver_example::events::{Event, EventQueue};
+use driver_example::fifo::{FifoConfig, FifoRead, Frame, FIFO_DEPTH, FRAME_SIZE};
+use driver_example::mock::{delay, i2c, pin};
+use driver_example::registers::{FifoData, FifoMode, IrqEnable, Register};
+use driver_example::sim::{SimI2c, SimulatedDevice};
+use driver_example::{Config, ConfigError, Error, ExampleDriver, I2cInterface, SpiInterface};
+
+const ADDR: u8 = 0x01;
+
+type Driver = ExampleDriver<
+    I2cInterface<SimI2c>,
+    driver_example::sim::SimBusy,
+    driver_example::sim::SimReset,
+    driver_example::sim::SimDelay,
+>;
+
+/// Create a driver over I2C with the FIFO enabled
+fn driver(dev: &SimulatedDevice, config: FifoConfig) -> Driver {
+    let iface = I2cInterface::new(dev.i2c(), dev.address());
+    let mut d = ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+
+    d.configure_fifo(config).unwrap();
+    d
+}
+
+fn frame(i: usize) -> Frame {
+    Frame { x: i as i16, y: -(i as i16), z: 0x1234 }
+}
+
+#[test]
+fn frame_encoding() {
+    let f = Frame { x: 1, y: -2, z: 0x1234 };
+    assert_eq!(f.to_bytes(), [0x00, 0x01, 0xff, 0xfe, 0x12, 0x34]);
+    assert_eq!(Frame::from_bytes(&f.to_bytes()), f);
+}
+
+#[test]
+fn bulk_read_over_i2c() {
+    let dev = SimulatedDevice::new();
+    let mut d = driver(&dev, FifoConfig::default());
+
+    for i in 0..12 {
+        dev.push_fifo(frame(i));
+    }
+
+    // More than one burst of frames
+    let mut buff = [0u8; 16 * FRAME_SIZE];
+    let r = d.read_fifo(&mut buff).unwrap();
+
+    assert_eq!(r, FifoRead { count: 12, ..Default::default() });
+    assert_eq!(r.bytes(), 12 * FRAME_SIZE);
+
+    let frames: Vec<_> = r.frames(&buff).collect();
+    assert_eq!(frames, (0..12).map(frame).collect::<Vec<_>>());
+
+    assert_eq!(dev.fifo_level(), 0);
+}
+
+#[test]
+fn bulk_read_over_spi_with_auto_increment() {
+    let dev = SimulatedDevice::new().with_auto_increment(0x40);
+
+    let iface = SpiInterface::new(dev.spi()).with_auto_increment(0x40);
+    let mut d = ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+    d.configure_fifo(FifoConfig::default()).unwrap();
+
+    for i in 0..7 {
+        dev.push_fifo(frame(i));
+    }
+
+    let mut buff = [0u8; 8 * FRAME_SIZE];
+    let r = d.read_fifo(&mut buff).unwrap();
+
+    assert_eq!(r.count, 7);
+    assert!(r.frames(&buff).eq((0..7).map(frame)));
+}
+
+#[test]
+fn status_and_frames_are_burst_reads() {
+    let busy = pin::Mock::new(&[pin::Transaction::get(pin::State::High)]);
+    let reset = pin::Mock::new(&[pin::Transaction::set(pin::State::Low), pin::Transaction::set(pin::State::High)]);
+    let delay = delay::Mock::new(&[delay::Transaction::Ms(10)]);
+
+    let data: Vec<u8> = (0..6).flat_map(|i| frame(i).to_bytes()).collect();
+
+    let mut i2c = i2c::Mock::new(&[
+        i2c::Transaction::write_read(ADDR, &[0x0f], &[0x5a]),
+        i2c::Transaction::write_read(ADDR, &[0x0e], &[0x10]),
+        i2c::Transaction::write(ADDR, &[0x01, 0x02]),
+        // Status and dropped counter in one read, six frames with no flags
+        i2c::Transaction::write_read(ADDR, &[0x09], &[0x06, 0x00]),
+        // Frames are read in bursts of whole frames
+        i2c::Transaction::write_read(ADDR, &[0x0b], &data[..30]),
+        i2c::Transaction::write_read(ADDR, &[0x0b], &data[30..]),
+    ]);
+
+    let iface = I2cInterface::new(i2c.clone(), ADDR);
+    let mut d = ExampleDriver::new(Config::default(), iface, busy, reset, delay).unwrap();
+
+    let mut buff = [0u8; 8 * FRAME_SIZE];
+    let r = d.read_fifo(&mut buff).unwrap();
+    assert!(r.frames(&buff).eq((0..6).map(frame)));
+
+    i2c.done();
+}
+
+#[test]
+fn frames_are_read_without_auto_increment_flag() {
+    let busy = pin::Mock::new(&[pin::Transaction::get(pin::State::High)]);
+    let reset = pin::Mock::new(&[pin::Transaction::set(pin::State::Low), pin::Transaction::set(pin::State::High)]);
+    let delay = delay::Mock::new(&[delay::Transaction::Ms(10)]);
+
+    let data: Vec<u8> = (0..2).flat_map(|i| frame(i).to_bytes()).collect();
+
+    let mut i2c = i2c::Mock::new(&[
+        // Chip ID, revision and control are single registers, without the flag
+        i2c::Transaction::write_read(ADDR, &[0x0f], &[0x5a]),
+        i2c::Transaction::write_read(ADDR, &[0x0e], &[0x10]),
+        i2c::Transaction::write(ADDR, &[0x01, 0x02]),
+        // Status and dropped counter are consecutive registers
+        i2c::Transaction::write_read(ADDR, &[0x89], &[0x02, 0x00]),
+        // Frames are all read from the FIFO data register
+        i2c::Transaction::write_read(ADDR, &[0x0b], &data),
+    ]);
+
+    let iface = I2cInterface::new(i2c.clone(), ADDR).with_auto_increment(0x80);
+    let mut d = ExampleDriver::new(Config::default(), iface, busy, reset, delay).unwrap();
+
+    let mut buff = [0u8; 2 * FRAME_SIZE];
+    let r = d.read_fifo(&mut buff).unwrap();
+    assert!(r.frames(&buff).eq((0..2).map(frame)));
+
+    i2c.done();
+}
+
+#[test]
+fn small_buffer_leaves_frames_pending() {
+    let dev = SimulatedDevice::new();
+    let mut d = driver(&dev, FifoConfig::default());
+
+    for i in 0..5 {
+        dev.push_fifo(frame(i));
+    }
+
+    // Partial frames aren't read
+    let mut buff = [0u8; 2 * FRAME_SIZE + 3];
+    let r = d.read_fifo(&mut buff).unwrap();
+    assert_eq!((r.count, r.pending), (2, 3));
+    assert!(r.frames(&buff).eq((0..2).map(frame)));
+
+    let mut buff = [0u8; 8 * FRAME_SIZE];
+    let r = d.read_fifo(&mut buff).unwrap();
+    assert_eq!((r.count, r.pending), (3, 0));
+    assert!(r.frames(&buff).eq((2..5).map(frame)));
+}
+
+#[test]
+fn stream_overflow_drops_oldest() {
+    let dev = SimulatedDevice::new();
+    let mut d = driver(&dev, FifoConfig { mode: FifoMode::Stream, ..Default::default() });
+
+    for i in 0..FIFO_DEPTH + 3 {
+        dev.push_fifo(frame(i));
+    }
+
+    let mut buff = [0u8; FIFO_DEPTH * FRAME_SIZE];
+    let r = d.read_fifo(&mut buff).unwrap();
+
+    assert_eq!(r, FifoRead { count: FIFO_DEPTH, pending: 0, overflow: true, underrun: false, dropped: 3 });
+    assert!(r.frames(&buff).eq((3..FIFO_DEPTH + 3).map(frame)));
+
+    // Flags and the dropped counter are cleared by the read
+    let r = d.read_fifo(&mut buff).unwrap();
+    assert_eq!(r, FifoRead::default());
+}
+
+#[test]
+fn stop_on_full_overflow_drops_newest() {
+    let dev = SimulatedDevice::new();
+    let mut d = driver(&dev, FifoConfig { mode: FifoMode::StopOnFull, ..Default::default() });
+
+    for i in 0..FIFO_DEPTH + 2 {
+        dev.push_fifo(frame(i));
+    }
+
+    let mut buff = [0u8; FIFO_DEPTH * FRAME_SIZE];
+    let r = d.read_fifo(&mut buff).unwrap();
+
+    assert!(r.overflow);
+    assert_eq!(r.dropped, 2);
+    assert!(r.frames(&buff).eq((0..FIFO_DEPTH).map(frame)));
+}
+
+#[test]
+fn reading_empty_fifo_reports_underrun() {
+    let dev = SimulatedDevice::new();
+    let mut d = driver(&dev, FifoConfig::default());
+
+    dev.push_fifo(frame(0));
+
+    // Read past the end of the FIFO directly
+    let mut raw = [0u8; 2 * FRAME_SIZE];
+    d.read_regs(FifoData::ADDRESS, &mut raw).unwrap();
+    assert_eq!(&raw[FRAME_SIZE..], &[0u8; FRAME_SIZE]);
+
+    let mut buff = [0u8; FRAME_SIZE];
+    let r = d.read_fifo(&mut buff).unwrap();
+    assert_eq!(r, FifoRead { underrun: true, ..Default::default() });
+}
+
+#[test]
+fn watermark_raises_interrupt() {
+    let dev = SimulatedDevice::new();
+    let mut d = driver(&dev, FifoConfig { watermark: 4, ..Default::default() });
+
+    let events = EventQueue::<4>::new();
+    let mut irq = dev.irq();
+
+    d.enable_interrupts(*IrqEnable::default().set_fifo_watermark(true)).unwrap();
+
+    for i in 0..3 {
+        dev.push_fifo(frame(i));
+    }
+    assert!(irq.is_high().unwrap());
+
+    dev.push_fifo(frame(3));
+    assert!(irq.is_low().unwrap());
+
+    d.on_interrupt(&events).unwrap();
+    assert_eq!(events.pop(), Some(Event::FifoWatermark));
+
+    let mut buff = [0u8; 4 * FRAME_SIZE];
+    assert_eq!(d.read_fifo(&mut buff).unwrap().count, 4);
+}
+
+#[test]
+fn flush_and_disable() {
+    let dev = SimulatedDevice::new();
+    let mut d = driver(&dev, FifoConfig::default());
+
+    dev.push_fifo(frame(0));
+    dev.push_fifo(frame(1));
+
+    d.flush_fifo().unwrap();
+    assert_eq!(dev.fifo_level(), 0);
+
+    // Frames are ignored while the FIFO is disabled
+    d.disable_fifo().unwrap();
+    dev.push_fifo(frame(2));
+    assert_eq!(dev.fifo_level(), 0);
+
+    // Reconfiguring discards anything buffered
+    d.configure_fifo(FifoConfig::default()).unwrap();
+    dev.push_fifo(frame(3));
+    d.configure_fifo(FifoConfig::default()).unwrap();
+    assert_eq!(dev.fifo_level(), 0);
+}
+
+#[test]
+fn invalid_watermark_fails() {
+    let dev = SimulatedDevice::new();
+    let mut d = driver(&dev, FifoConfig::default());
+
+    for watermark in [0, FIFO_DEPTH as u8 + 1] {
+        let r = d.configure_fifo(FifoConfig { watermark, ..Default::default() });
+        assert_eq!(r, Err(Error::Config(ConfigError::FifoWatermark)));
+    }
+
+    let e = ConfigError::FifoWatermark;
+    assert_eq!(e.to_string(), "FIFO watermark must be between 1 and 32 frames");
+}
@@ -0,0 +1,200 @@
+//! embedded-hal 0.2 adaptor tests using mock peripherals (requires the `hal-02` feature)
+
+#![cfg(feature = "hal-02")]
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_hal::i2c::ErrorKind as I2cErrorKind;
+use embedded_hal::spi::ErrorKind as SpiErrorKind;
+
+use driver_example::hal02::{self, I2cError, SpiError, I2C_MAX_WRITE};
+use driver_example::mock::{delay, i2c, pin, spi, MockError};
+use driver_example::registers::{self, Control, Register};
+use driver_example::{checksum, Config, ExampleDriver, Interface};
+
+const ADDR: u8 = 0x01;
+
+#[test]
+fn new_over_i2c() {
+    let mut i2c = i2c::Mock::new(&[
+        i2c::Transaction::write_read(ADDR, &[0x0f], &[0x5a]),
+        i2c::Transaction::write_read(ADDR, &[0x0e], &[0x10]),
+        i2c::Transaction::write(ADDR, &[0x01, 0x02]),
+    ]);
+    let mut busy = pin::Mock::new(&[pin::Transaction::get(pin::State::High)]);
+    let mut reset = pin::Mock::new(&[pin::Transaction::set(pin::State::Low), pin::Transaction::set(pin::State::High)]);
+    let mut delay = delay::Mock::new(&[delay::Transaction::Ms(10)]);
+
+    let iface = hal02::I2cInterface::new(i2c.clone(), ADDR);
+    let d = ExampleDriver::new(Config::default(), iface, hal02::Input(busy.clone()), hal02::Output(reset.clone()), hal02::Delay(delay.clone()));
+
+    assert_eq!(d.unwrap().device_info().chip_id, 0x5a);
+
+    i2c.done();
+    busy.done();
+    reset.done();
+    delay.done();
+}
+
+#[test]
+fn i2c_register_framing() {
+    let mut i2c = i2c::Mock::new(&[
+        i2c::Transaction::write_read(ADDR, &[0xc2], &[0x0a, 0xbc]),
+        i2c::Transaction::write(ADDR, &[0xc2, 0x0a, 0xbc]),
+        i2c::Transaction::write_read(ADDR, &[0x20], &[0x01, 0x02]),
+    ]);
+    let mut iface = hal02::I2cInterface::new(i2c.clone(), ADDR).with_auto_increment(0x80);
+
+    // Multi-byte accesses set the auto-increment flag, the address
+    // and data are written together so there's no STOP between them
+    let mut buff = [0u8; 2];
+    iface.read_register(0x42, &mut buff).unwrap();
+    assert_eq!(buff, [0x0a, 0xbc]);
+    iface.write_register(0x42, &buff).unwrap();
+
+    iface.read_register_fixed(0x20, &mut buff).unwrap();
+    assert_eq!(buff, [0x01, 0x02]);
+
+    i2c.done();
+}
+
+#[test]
+fn i2c_write_limit() {
+    let data = [0x55u8; I2C_MAX_WRITE];
+    assert_eq!(I2C_MAX_WRITE, registers::MAX_BURST + checksum::MAX_LEN + 1);
+
+    // A full burst (with checksum) fits in a single write
+    let mut expected = vec![0x10];
+    expected.extend_from_slice(&data[1..]);
+    let mut i2c = i2c::Mock::new(&[i2c::Transaction::write(ADDR, &expected)]);
+    let mut iface = hal02::I2cInterface::new(i2c.clone(), ADDR);
+
+    iface.write_register(0x10, &data[1..]).unwrap();
+
+    // Anything longer is refused without touching the bus
+    assert_eq!(iface.write_register(0x10, &data), Err(I2cError::TooLong));
+
+    i2c.done();
+}
+
+#[test]
+fn i2c_errors() {
+    let mut i2c = i2c::Mock::new(&[
+        i2c::Transaction::write_read(ADDR, &[0x00], &[0x00]).with_error(I2cErrorKind::Bus),
+    ]);
+    let mut iface = hal02::I2cInterface::new(i2c.clone(), ADDR);
+
+    let mut buff = [0u8; 1];
+    assert_eq!(iface.read_register(0x00, &mut buff), Err(I2cError::I2c(MockError::I2c(I2cErrorKind::Bus))));
+
+    i2c.done();
+}
+
+#[test]
+fn spi_register_framing() {
+    let mut spi = spi::Mock::new(&[
+        // Read, zero filled while clocking in the response
+        spi::Transaction::write(&[0xc2]),
+        spi::Transaction::new(&[spi::Op::Transfer(vec![0x00, 0x00], vec![0x0a, 0xbc])]),
+        // Write
+        spi::Transaction::write(&[0x42]),
+        spi::Transaction::write(&[0x0a, 0xbc]),
+    ]);
+    let mut cs = pin::Mock::new(&[
+        pin::Transaction::set(pin::State::Low),
+        pin::Transaction::set(pin::State::High),
+        pin::Transaction::set(pin::State::Low),
+        pin::Transaction::set(pin::State::High),
+    ]);
+    let mut iface = hal02::SpiInterface::new(spi.clone(), cs.clone()).with_auto_increment(0x40);
+
+    let mut buff = [0xffu8; 2];
+    iface.read_register(0x02, &mut buff).unwrap();
+    assert_eq!(buff, [0x0a, 0xbc]);
+
+    iface.write_register(0x02, &buff).unwrap();
+
+    spi.done();
+    cs.done();
+}
+
+#[test]
+fn spi_releases_cs_on_error() {
+    let mut spi = spi::Mock::new(&[
+        spi::Transaction::write(&[0x80]),
+        spi::Transaction::new(&[spi::Op::Transfer(vec![0x00], vec![0x00])]).with_error(SpiErrorKind::ModeFault),
+        spi::Transaction::write(&[0x01]).with_error(SpiErrorKind::Overrun),
+    ]);
+    let mut cs = pin::Mock::new(&[
+        pin::Transaction::set(pin::State::Low),
+        pin::Transaction::set(pin::State::High),
+        pin::Transaction::set(pin::State::Low),
+        pin::Transaction::set(pin::State::High),
+    ]);
+    let mut iface = hal02::SpiInterface::new(spi.clone(), cs.clone());
+
+    let mut buff = [0u8; 1];
+    assert_eq!(iface.read_register(0x00, &mut buff), Err(SpiError::Spi(MockError::Spi(SpiErrorKind::ModeFault))));
+
+    // Failing before the data is sent still releases CS
+    assert_eq!(iface.write_register(0x01, &[0x02]), Err(SpiError::Spi(MockError::Spi(SpiErrorKind::Overrun))));
+
+    spi.done();
+    cs.done();
+}
+
+#[test]
+fn spi_cs_errors() {
+    let mut spi = spi::Mock::new(&[]);
+    let mut cs = pin::Mock::new(&[pin::Transaction::set(pin::State::Low).with_error()]);
+    let mut iface = hal02::SpiInterface::new(spi.clone(), cs.clone());
+
+    assert_eq!(iface.write_register(Control::ADDRESS, &[0x02]), Err(SpiError::Pin(MockError::Pin)));
+
+    spi.done();
+    cs.done();
+}
+
+#[test]
+fn pin_wrappers() {
+    let mut input = pin::Mock::new(&[
+        pin::Transaction::get(pin::State::High),
+        pin::Transaction::get(pin::State::High),
+        pin::Transaction::get(pin::State::Low).with_error(),
+    ]);
+    let mut output = pin::Mock::new(&[
+        pin::Transaction::set(pin::State::Low),
+        pin::Transaction::set(pin::State::High).with_error(),
+    ]);
+
+    let mut i = hal02::Input(input.clone());
+    assert!(i.is_high().unwrap());
+    assert!(!i.is_low().unwrap());
+    assert_eq!(i.is_low(), Err(hal02::PinError(MockError::Pin)));
+
+    let mut o = hal02::Output(output.clone());
+    o.set_low().unwrap();
+    assert_eq!(o.set_high(), Err(hal02::PinError(MockError::Pin)));
+
+    input.done();
+    output.done();
+}
+
+#[test]
+fn delay_wrapper() {
+    let mut delay = delay::Mock::new(&[
+        delay::Transaction::Ms(5),
+        delay::Transaction::Us(20),
+        // Nanosecond delays round up to the next microsecond
+        delay::Transaction::Us(2),
+        delay::Transaction::Us(1),
+    ]);
+
+    let mut d = hal02::Delay(delay.clone());
+    d.delay_ms(5);
+    d.delay_us(20);
+    d.delay_ns(1_500);
+    d.delay_ns(1);
+
+    delay.done();
+}
@@ -0,0 +1,28 @@
+//! Check the driver still builds for a `no_std` target
+//!
+//! Requires the target to be installed with `rustup target add thumbv7em-none-eabihf`
+
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+const TARGET: &str = "thumbv7em-none-eabihf";
+
+#[test]
+fn builds_for_thumbv7em() {
+    let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+
+    // Use a separate target dir so we don't contend with the outer build
+    let target_dir = manifest_dir.join("target").join(TARGET);
+
+    let status = Command::new(cargo)
+        .current_dir(manifest_dir)
+        .args(["build", "--lib", "--target", TARGET, "--no-default-features", "--features", "hal-02,async,defmt,shared-bus"])
+        .arg("--target-dir")
+        .arg(&target_dir)
+        .status()
+        .expect("failed to run cargo");
+
+    assert!(status.success(), "driver failed to build for {}", TARGET);
+}
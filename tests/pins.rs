@@ -0,0 +1,133 @@
+//! Tests for boards without reset and / or busy pins
+
+use driver_example::mock::{delay, i2c, pin, MockError};
+use driver_example::registers::{Control, Mode, Register};
+use driver_example::sim::SimulatedDevice;
+use driver_example::{Config, Error, ExampleDriver, I2cInterface, NoPin, Operation, ReadyFallback};
+
+const ADDR: u8 = 0x01;
+
+#[test]
+fn soft_reset_without_reset_pin() {
+    let mut busy = pin::Mock::new(&[pin::Transaction::get(pin::State::High)]);
+    let mut delay = delay::Mock::new(&[]);
+
+    let mut i2c = i2c::Mock::new(&[
+        // Software reset command
+        i2c::Transaction::write(ADDR, &[0x04, 0xb6]),
+        // Chip ID and revision
+        i2c::Transaction::write_read(ADDR, &[0x0f], &[0x5a]),
+        i2c::Transaction::write_read(ADDR, &[0x0e], &[0x10]),
+        i2c::Transaction::write(ADDR, &[0x01, 0x02]),
+    ]);
+
+    let iface = I2cInterface::new(i2c.clone(), ADDR);
+    ExampleDriver::new(Config::default(), iface, busy.clone(), NoPin::default(), delay.clone()).unwrap();
+
+    i2c.done();
+    busy.done();
+    delay.done();
+}
+
+#[test]
+fn status_polling_without_busy_pin() {
+    let mut reset = pin::Mock::new(&[
+        pin::Transaction::set(pin::State::Low),
+        pin::Transaction::set(pin::State::High),
+    ]);
+    let mut delay = delay::Mock::new(&[
+        delay::Transaction::Ms(10),
+        delay::Transaction::Ms(20),
+    ]);
+
+    let mut i2c = i2c::Mock::new(&[
+        // Status busy, then ready
+        i2c::Transaction::write_read(ADDR, &[0x00], &[0x01]),
+        i2c::Transaction::write_read(ADDR, &[0x00], &[0x00]),
+        // Chip ID and revision
+        i2c::Transaction::write_read(ADDR, &[0x0f], &[0x5a]),
+        i2c::Transaction::write_read(ADDR, &[0x0e], &[0x10]),
+        i2c::Transaction::write(ADDR, &[0x01, 0x02]),
+    ]);
+
+    let config = Config { poll_ms: 20, ..Default::default() };
+    let iface = I2cInterface::new(i2c.clone(), ADDR);
+    ExampleDriver::new(config, iface, NoPin::default(), reset.clone(), delay.clone()).unwrap();
+
+    i2c.done();
+    reset.done();
+    delay.done();
+}
+
+#[test]
+fn fixed_delay_without_busy_pin() {
+    let mut reset = pin::Mock::new(&[
+        pin::Transaction::set(pin::State::Low),
+        pin::Transaction::set(pin::State::High),
+    ]);
+    let mut delay = delay::Mock::new(&[
+        delay::Transaction::Ms(10),
+        delay::Transaction::Ms(50),
+    ]);
+
+    let mut i2c = i2c::Mock::new(&[
+        // Chip ID and revision
+        i2c::Transaction::write_read(ADDR, &[0x0f], &[0x5a]),
+        i2c::Transaction::write_read(ADDR, &[0x0e], &[0x10]),
+        i2c::Transaction::write(ADDR, &[0x01, 0x02]),
+    ]);
+
+    let config = Config { ready_fallback: ReadyFallback::Delay(50), ..Default::default() };
+    let iface = I2cInterface::new(i2c.clone(), ADDR);
+    ExampleDriver::new(config, iface, NoPin::default(), reset.clone(), delay.clone()).unwrap();
+
+    i2c.done();
+    reset.done();
+    delay.done();
+}
+
+#[test]
+fn pin_errors_with_one_pin_connected() {
+    let mut reset = pin::Mock::new(&[
+        pin::Transaction::set(pin::State::Low).with_error(),
+    ]);
+
+    let iface = I2cInterface::new(i2c::Mock::new(&[]), ADDR);
+    let r = ExampleDriver::new(Config::default(), iface, NoPin::default(), reset.clone(), delay::Mock::new(&[]));
+
+    assert!(matches!(r, Err(Error::Pin { op: Operation::Reset, error: MockError::Pin })));
+
+    reset.done();
+}
+
+#[test]
+fn no_pins_with_simulated_device() {
+    let dev = SimulatedDevice::new();
+
+    let config = Config { poll_ms: 5, ..Default::default() };
+    let iface = I2cInterface::new(dev.i2c(), dev.address());
+    let mut d = ExampleDriver::new(config, iface, NoPin::new(), NoPin::new(), dev.delay()).unwrap();
+
+    // Device was reset by command and polled until ready
+    assert_eq!(dev.resets(), 1);
+    assert!(dev.now_ms() >= 20);
+    assert_eq!(d.read_reg::<Control>().unwrap().mode(), Some(Mode::Normal));
+
+    // Reset again via the state machine
+    let d = d.reset().unwrap();
+    assert_eq!(dev.resets(), 2);
+    assert_eq!(dev.peek(Control::ADDRESS), 0x00);
+
+    d.configure().unwrap();
+}
+
+#[test]
+fn status_polling_times_out() {
+    let dev = SimulatedDevice::new().with_ready_after_ms(500);
+
+    let config = Config { poll_ms: 10, ..Default::default() };
+    let iface = I2cInterface::new(dev.i2c(), dev.address());
+    let r = ExampleDriver::new(config, iface, NoPin::new(), NoPin::new(), dev.delay());
+
+    assert!(matches!(r, Err(Error::ResetTimeout)));
+}
@@ -0,0 +1,115 @@
+//! SPI command / response protocol tests
+
+use embedded_hal::spi::ErrorKind;
+
+use driver_example::mock::{delay, pin, spi, MockError};
+use driver_example::protocol::{AddressWidth, Command, MAX_HEADER, READ_DATA, READ_ID, WRITE_DATA};
+use driver_example::registers::{Mode, Status};
+use driver_example::{Config, Error, ExampleDriver, NoPin, Operation, SpiInterface};
+
+type Driver = ExampleDriver<SpiInterface<spi::Mock>, pin::Mock, NoPin<MockError>, delay::Mock>;
+
+/// Create a driver, expecting the soft reset, identification and configuration
+/// transactions before `commands`
+fn driver(commands: &[spi::Transaction]) -> (Driver, spi::Mock) {
+    let mut spi = spi::Mock::new(&[
+        spi::Transaction::write(&[0x04, 0xb6]),
+        spi::Transaction::write_read(&[0x8f], &[0x5a]),
+        spi::Transaction::write_read(&[0x8e], &[0x10]),
+        spi::Transaction::write(&[0x01, 0x02]),
+    ]);
+    spi.expect(commands);
+
+    let busy = pin::Mock::new(&[pin::Transaction::get(pin::State::High)]);
+    let iface = SpiInterface::new(spi.clone());
+    let d = ExampleDriver::new(Config::default(), iface, busy, NoPin::default(), delay::Mock::new(&[])).unwrap();
+
+    (d, spi)
+}
+
+#[test]
+fn command_headers() {
+    let mut buff = [0u8; MAX_HEADER];
+
+    let n = READ_ID.header(0x1234, &mut buff);
+    assert_eq!(&buff[..n], &[0x9f]);
+
+    let n = READ_DATA.header(0x123456, &mut buff);
+    assert_eq!(&buff[..n], &[0x0b, 0x12, 0x34, 0x56, 0x00]);
+
+    // Addresses are truncated to the command width
+    let cmd = Command::new(0x01).with_address(AddressWidth::Bits16).with_dummy(2);
+    let n = cmd.header(0xabcdef, &mut buff);
+    assert_eq!(&buff[..n], &[0x01, 0xcd, 0xef, 0x00, 0x00]);
+
+    assert_eq!(AddressWidth::Bits32.bytes(), 4);
+}
+
+#[test]
+#[should_panic(expected = "too many dummy bytes")]
+fn too_many_dummy_bytes() {
+    let _ = Command::new(0x01).with_dummy(5);
+}
+
+#[test]
+fn read_id_command() {
+    let (mut d, mut spi) = driver(&[
+        // Status clocked back with the opcode, then the response
+        spi::Transaction::new(&[spi::Op::Transfer(vec![0x9f], vec![0x04]), spi::Op::Read(vec![0x5a, 0x10])]),
+    ]);
+
+    let r = d.command(READ_ID, 0, &[]).unwrap();
+    assert_eq!(r.status, Status(0x04));
+    assert_eq!(r.status.mode(), Some(Mode::Normal));
+    assert_eq!(r.payload(), &[0x5a, 0x10]);
+
+    spi.done();
+}
+
+#[test]
+fn write_command_with_payload() {
+    let (mut d, mut spi) = driver(&[
+        spi::Transaction::new(&[
+            spi::Op::Transfer(vec![0x02, 0x00, 0x10, 0x00], vec![0x05, 0x00, 0x00, 0x00]),
+            spi::Op::Write(vec![0xaa, 0xbb, 0xcc]),
+        ]),
+    ]);
+
+    let r = d.command(WRITE_DATA, 0x001000, &[0xaa, 0xbb, 0xcc]).unwrap();
+    assert!(r.status.busy());
+    assert!(r.payload().is_empty());
+
+    spi.done();
+}
+
+#[test]
+fn read_command_with_dummy_bytes() {
+    let cmd = Command::new(0x0b).with_address(AddressWidth::Bits8).with_dummy(1).with_response(4);
+
+    let (mut d, mut spi) = driver(&[
+        spi::Transaction::new(&[
+            spi::Op::Transfer(vec![0x0b, 0x20, 0x00], vec![0x06, 0x00, 0x00]),
+            spi::Op::Read(vec![0x01, 0x02, 0x03, 0x04]),
+        ]),
+    ]);
+
+    let r = d.command(cmd, 0x20, &[]).unwrap();
+    assert!(r.status.error());
+    assert_eq!(r.payload(), &[0x01, 0x02, 0x03, 0x04]);
+
+    spi.done();
+}
+
+#[test]
+fn command_errors_report_opcode() {
+    let (mut d, mut spi) = driver(&[
+        spi::Transaction::new(&[spi::Op::Transfer(vec![0x9f], vec![0x00]), spi::Op::Read(vec![0x00, 0x00])])
+            .with_error(ErrorKind::ModeFault),
+    ]);
+
+    let e = d.command(READ_ID, 0, &[]).unwrap_err();
+    assert_eq!(e, Error::Interface { op: Operation::Command(0x9f), error: MockError::Spi(ErrorKind::ModeFault) });
+    assert_eq!(e.to_string(), "interface error sending command 0x9f: Spi(ModeFault)");
+
+    spi.done();
+}
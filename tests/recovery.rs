@@ -0,0 +1,124 @@
+//! I2C bus recovery tests
+
+use driver_example::recovery::{self, RECOVERY_PULSES};
+use driver_example::registers::{Control, Mode};
+use driver_example::sim::{SimError, SimI2c, SimulatedDevice};
+use driver_example::{BusErrorKind, Config, Error, ExampleDriver, I2cInterface, Interface, Operation, RetryPolicy};
+
+/// Recovery hook for the simulated device, clocking out via its SCL and SDA lines
+fn sim_recovery(dev: &SimulatedDevice) -> impl FnMut(&mut SimI2c) -> Result<(), SimError> {
+    let (mut scl, mut sda, mut delay) = (dev.scl(), dev.sda(), dev.delay());
+
+    move |_i2c| {
+        match recovery::clock_out(&mut scl, &mut sda, &mut delay)? {
+            true => Ok(()),
+            false => Err(SimError::Bus),
+        }
+    }
+}
+
+#[test]
+fn clock_out_releases_bus() {
+    let dev = SimulatedDevice::new();
+    dev.hold_sda(5);
+    assert!(dev.bus_stuck());
+
+    let released = recovery::clock_out(&mut dev.scl(), &mut dev.sda(), &mut dev.delay()).unwrap();
+
+    assert!(released);
+    assert!(!dev.bus_stuck());
+}
+
+#[test]
+fn clock_out_gives_up_after_nine_pulses() {
+    let dev = SimulatedDevice::new();
+    // Nine pulses, plus the clock in the STOP condition, aren't enough
+    dev.hold_sda(RECOVERY_PULSES as u32 + 2);
+
+    let released = recovery::clock_out(&mut dev.scl(), &mut dev.sda(), &mut dev.delay()).unwrap();
+
+    assert!(!released);
+    assert!(dev.bus_stuck());
+
+    // A second attempt clears the remaining pulse
+    let released = recovery::clock_out(&mut dev.scl(), &mut dev.sda(), &mut dev.delay()).unwrap();
+    assert!(released);
+    assert!(!dev.bus_stuck());
+}
+
+#[test]
+fn stuck_bus_fails_without_recovery() {
+    let dev = SimulatedDevice::new();
+
+    let iface = I2cInterface::new(dev.i2c(), dev.address());
+    let mut d = ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+
+    dev.hold_sda(3);
+    for _ in 0..5 {
+        let e = d.read_reg::<Control>().unwrap_err();
+        assert_eq!(e, Error::Interface { op: Operation::Read(0x01), error: SimError::Bus });
+    }
+}
+
+#[test]
+fn recovery_after_repeated_errors() {
+    let dev = SimulatedDevice::new();
+
+    let iface = I2cInterface::new(dev.i2c(), dev.address()).with_recovery(sim_recovery(&dev), 2);
+    let mut d = ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+
+    dev.hold_sda(3);
+
+    // First error doesn't trigger recovery
+    assert!(d.read_reg::<Control>().is_err());
+    assert!(dev.bus_stuck());
+
+    // Second does, so the bus works again
+    assert!(d.read_reg::<Control>().is_err());
+    assert!(!dev.bus_stuck());
+
+    assert_eq!(d.read_reg::<Control>().unwrap().mode(), Some(Mode::Normal));
+
+    let (iface, ..) = d.free();
+    assert_eq!(iface.recoveries(), 1);
+}
+
+#[test]
+fn recovery_with_retries() {
+    let dev = SimulatedDevice::new();
+
+    // Bus errors are retried, and recovery runs between attempts
+    let config = Config {
+        retry: RetryPolicy { max_attempts: 3, backoff_ms: 1, retryable: &[BusErrorKind::Bus] },
+        ..Default::default()
+    };
+    let iface = I2cInterface::new(dev.i2c(), dev.address()).with_recovery(sim_recovery(&dev), 2);
+    let mut d = ExampleDriver::new(config, iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+
+    dev.hold_sda(9);
+    assert_eq!(d.read_reg::<Control>().unwrap().mode(), Some(Mode::Normal));
+    assert_eq!(d.retry_stats().recovered, 1);
+}
+
+#[test]
+fn errors_counted_across_transactions() {
+    let dev = SimulatedDevice::new();
+    let mut iface = I2cInterface::new(dev.i2c(), dev.address()).with_recovery(sim_recovery(&dev), 3);
+
+    // A success resets the consecutive error count
+    dev.inject_nacks(2);
+    assert!(iface.write_register(0x02, &[0x00, 0x10]).is_err());
+    assert!(iface.write_register(0x02, &[0x00, 0x10]).is_err());
+    iface.write_register(0x02, &[0x00, 0x10]).unwrap();
+
+    dev.inject_nacks(2);
+    assert!(iface.write_register(0x02, &[0x00, 0x10]).is_err());
+    assert!(iface.write_register(0x02, &[0x00, 0x10]).is_err());
+    assert_eq!(iface.recoveries(), 0);
+
+    // Until three errors in a row
+    dev.hold_sda(1);
+    assert!(iface.write_register(0x02, &[0x00, 0x10]).is_err());
+    assert_eq!(iface.recoveries(), 1);
+    assert!(!dev.bus_stuck());
+}
@@ -0,0 +1,225 @@
+//! Bus transaction retry tests
+
+use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
+
+use driver_example::mock::{delay, i2c, pin, MockError};
+use driver_example::registers::{Control, Mode};
+use driver_example::sim::SimulatedDevice;
+use driver_example::{BusErrorKind, Checksum, Config, ConfigError, Error, ExampleDriver, I2cInterface, Operation, RetryPolicy, RetryStats};
+
+const ADDR: u8 = 0x01;
+
+const NACK: ErrorKind = ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address);
+
+fn retry_config(max_attempts: u32) -> Config {
+    Config {
+        retry: RetryPolicy { max_attempts, backoff_ms: 2, ..Default::default() },
+        ..Default::default()
+    }
+}
+
+/// Pin mocks for a reset where the device is immediately ready
+fn pins() -> (pin::Mock, pin::Mock) {
+    let busy = pin::Mock::new(&[pin::Transaction::get(pin::State::High)]);
+    let reset = pin::Mock::new(&[
+        pin::Transaction::set(pin::State::Low),
+        pin::Transaction::set(pin::State::High),
+    ]);
+    (busy, reset)
+}
+
+#[test]
+fn backoff_doubles() {
+    let p = RetryPolicy { max_attempts: 4, backoff_ms: 5, ..Default::default() };
+
+    assert_eq!(p.retry(1, BusErrorKind::NoAcknowledge), Some(5));
+    assert_eq!(p.retry(2, BusErrorKind::Bus), Some(10));
+    assert_eq!(p.retry(3, BusErrorKind::ArbitrationLoss), Some(20));
+    assert_eq!(p.retry(3, BusErrorKind::Checksum), Some(20));
+    assert_eq!(p.retry(4, BusErrorKind::NoAcknowledge), None);
+
+    // Overruns and unknown errors aren't retried by default
+    assert_eq!(p.retry(1, BusErrorKind::Overrun), None);
+    assert_eq!(p.retry(1, BusErrorKind::Other), None);
+}
+
+#[test]
+fn no_retries_by_default() {
+    let (busy, reset) = pins();
+    let delay = delay::Mock::new(&[delay::Transaction::Ms(10)]);
+
+    let mut i2c = i2c::Mock::new(&[
+        // Chip ID and revision
+        i2c::Transaction::write_read(ADDR, &[0x0f], &[0x5a]),
+        i2c::Transaction::write_read(ADDR, &[0x0e], &[0x10]),
+        i2c::Transaction::write(ADDR, &[0x01, 0x02]).with_error(NACK),
+    ]);
+
+    let iface = I2cInterface::new(i2c.clone(), ADDR);
+    let r = ExampleDriver::new(Config::default(), iface, busy, reset, delay);
+
+    assert!(matches!(r, Err(Error::Interface { error: MockError::I2c(NACK), .. })));
+
+    i2c.done();
+}
+
+#[test]
+fn nack_retried_in_new() {
+    let (busy, reset) = pins();
+    let mut delay = delay::Mock::new(&[
+        delay::Transaction::Ms(10),
+        // Backoff
+        delay::Transaction::Ms(2),
+    ]);
+
+    let mut i2c = i2c::Mock::new(&[
+        // Chip ID and revision
+        i2c::Transaction::write_read(ADDR, &[0x0f], &[0x5a]),
+        i2c::Transaction::write_read(ADDR, &[0x0e], &[0x10]),
+        i2c::Transaction::write(ADDR, &[0x01, 0x02]).with_error(NACK),
+        i2c::Transaction::write(ADDR, &[0x01, 0x02]),
+    ]);
+
+    let iface = I2cInterface::new(i2c.clone(), ADDR);
+    let d = ExampleDriver::new(retry_config(3), iface, busy, reset, delay.clone()).unwrap();
+
+    assert_eq!(d.retry_stats(), RetryStats { retries: 1, recovered: 1, failed: 0 });
+
+    i2c.done();
+    delay.done();
+}
+
+#[test]
+fn retries_exhausted() {
+    let (busy, reset) = pins();
+    let mut delay = delay::Mock::new(&[
+        delay::Transaction::Ms(10),
+        delay::Transaction::Ms(2),
+        delay::Transaction::Ms(4),
+        delay::Transaction::Ms(2),
+    ]);
+
+    let mut i2c = i2c::Mock::new(&[
+        // Chip ID and revision
+        i2c::Transaction::write_read(ADDR, &[0x0f], &[0x5a]),
+        i2c::Transaction::write_read(ADDR, &[0x0e], &[0x10]),
+        i2c::Transaction::write(ADDR, &[0x01, 0x02]),
+        // Read fails three times
+        i2c::Transaction::write_read(ADDR, &[0x01], &[0x00]).with_error(ErrorKind::Bus),
+        i2c::Transaction::write_read(ADDR, &[0x01], &[0x00]).with_error(NACK),
+        i2c::Transaction::write_read(ADDR, &[0x01], &[0x00]).with_error(ErrorKind::ArbitrationLoss),
+        // Then recovers on the second attempt
+        i2c::Transaction::write_read(ADDR, &[0x01], &[0x00]).with_error(NACK),
+        i2c::Transaction::write_read(ADDR, &[0x01], &[0x02]),
+    ]);
+
+    let iface = I2cInterface::new(i2c.clone(), ADDR);
+    let mut d = ExampleDriver::new(retry_config(3), iface, busy, reset, delay.clone()).unwrap();
+
+    let e = d.read_reg::<Control>().unwrap_err();
+    assert_eq!(e, Error::Interface { op: Operation::Read(0x01), error: MockError::I2c(ErrorKind::ArbitrationLoss) });
+    assert_eq!(d.retry_stats(), RetryStats { retries: 2, recovered: 0, failed: 1 });
+
+    assert_eq!(d.read_reg::<Control>().unwrap().mode(), Some(Mode::Normal));
+    assert_eq!(d.retry_stats(), RetryStats { retries: 3, recovered: 1, failed: 1 });
+
+    d.clear_retry_stats();
+    assert_eq!(d.retry_stats(), RetryStats::default());
+
+    i2c.done();
+    delay.done();
+}
+
+#[test]
+fn non_retryable_errors_returned_immediately() {
+    let (busy, reset) = pins();
+    let mut delay = delay::Mock::new(&[delay::Transaction::Ms(10)]);
+
+    let mut i2c = i2c::Mock::new(&[
+        // Chip ID and revision
+        i2c::Transaction::write_read(ADDR, &[0x0f], &[0x5a]),
+        i2c::Transaction::write_read(ADDR, &[0x0e], &[0x10]),
+        i2c::Transaction::write(ADDR, &[0x01, 0x02]).with_error(ErrorKind::Overrun),
+    ]);
+
+    let iface = I2cInterface::new(i2c.clone(), ADDR);
+    let r = ExampleDriver::new(retry_config(3), iface, busy, reset, delay.clone());
+
+    assert!(matches!(r, Err(Error::Interface { error: MockError::I2c(ErrorKind::Overrun), .. })));
+
+    i2c.done();
+    delay.done();
+}
+
+#[test]
+fn custom_retryable_kinds() {
+    let (busy, reset) = pins();
+    let mut delay = delay::Mock::new(&[
+        delay::Transaction::Ms(10),
+        delay::Transaction::Ms(1),
+    ]);
+
+    let mut i2c = i2c::Mock::new(&[
+        // Chip ID and revision
+        i2c::Transaction::write_read(ADDR, &[0x0f], &[0x5a]),
+        i2c::Transaction::write_read(ADDR, &[0x0e], &[0x10]),
+        i2c::Transaction::write(ADDR, &[0x01, 0x02]).with_error(ErrorKind::Overrun),
+        i2c::Transaction::write(ADDR, &[0x01, 0x02]),
+    ]);
+
+    let config = Config {
+        retry: RetryPolicy { max_attempts: 2, backoff_ms: 1, retryable: &[BusErrorKind::Overrun] },
+        ..Default::default()
+    };
+    let iface = I2cInterface::new(i2c.clone(), ADDR);
+    ExampleDriver::new(config, iface, busy, reset, delay.clone()).unwrap();
+
+    i2c.done();
+    delay.done();
+}
+
+#[test]
+fn noisy_simulated_bus() {
+    let dev = SimulatedDevice::new();
+
+    // Without retries a single NACK aborts initialisation
+    dev.inject_nacks(1);
+    let iface = I2cInterface::new(dev.i2c(), dev.address());
+    let r = ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay());
+    assert!(matches!(r, Err(Error::Interface { op: Operation::Read(0x0f), .. })));
+
+    // With retries the driver recovers
+    dev.inject_nacks(2);
+    let iface = I2cInterface::new(dev.i2c(), dev.address());
+    let mut d = ExampleDriver::new(retry_config(3), iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+    assert_eq!(d.retry_stats(), RetryStats { retries: 2, recovered: 1, failed: 0 });
+
+    dev.inject_nacks(1);
+    assert_eq!(d.read_reg::<Control>().unwrap().mode(), Some(Mode::Normal));
+    assert_eq!(d.retry_stats(), RetryStats { retries: 3, recovered: 2, failed: 0 });
+}
+
+#[test]
+fn checksum_mismatch_retried() {
+    let dev = SimulatedDevice::new().with_checksum(Checksum::Crc8Smbus);
+
+    let config = Config { checksum: Checksum::Crc8Smbus, ..retry_config(3) };
+    let iface = I2cInterface::new(dev.i2c(), dev.address());
+    let mut d = ExampleDriver::new(config, iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+
+    // A corrupted response is read again
+    dev.corrupt_reads(1);
+    assert_eq!(d.read_reg::<Control>().unwrap().mode(), Some(Mode::Normal));
+    assert_eq!(d.retry_stats(), RetryStats { retries: 1, recovered: 1, failed: 0 });
+
+    // Until the attempts run out
+    dev.corrupt_reads(3);
+    assert!(matches!(d.read_reg::<Control>(), Err(Error::Checksum { .. })));
+    assert_eq!(d.retry_stats(), RetryStats { retries: 3, recovered: 1, failed: 1 });
+}
+
+#[test]
+fn zero_attempts_rejected() {
+    let c = retry_config(0);
+    assert_eq!(c.validate(), Err(ConfigError::RetryAttempts));
+}
@@ -0,0 +1,92 @@
+//! Multiple drivers sharing one simulated bus
+
+use std::cell::RefCell;
+
+use critical_section::Mutex;
+
+use driver_example::registers::Threshold;
+use driver_example::sim::{SimBus, SimulatedDevice};
+use driver_example::{Config, ExampleDriver, I2cInterface, SpiInterface};
+
+/// Set a different threshold on each device, checking the writes went to the right one
+fn check_thresholds(a: &SimulatedDevice, b: &SimulatedDevice) {
+    assert_eq!((a.peek(0x02), a.peek(0x03)), (0x01, 0x11));
+    assert_eq!((b.peek(0x02), b.peek(0x03)), (0x02, 0x22));
+}
+
+fn threshold(level: u16) -> Threshold {
+    let mut t = Threshold::default();
+    t.set_level(level);
+    t
+}
+
+#[test]
+fn two_drivers_on_shared_i2c() {
+    let dev_a = SimulatedDevice::new().with_address(0x01);
+    let dev_b = SimulatedDevice::new().with_address(0x02);
+    let bus = RefCell::new(SimBus::new(&[&dev_a, &dev_b]));
+
+    let iface = I2cInterface::new_shared(&bus, 0x01);
+    let mut a = ExampleDriver::new(Config::default(), iface, dev_a.busy(), dev_a.reset(), dev_a.delay()).unwrap();
+
+    let iface = I2cInterface::new_shared(&bus, 0x02);
+    let mut b = ExampleDriver::new(Config::default(), iface, dev_b.busy(), dev_b.reset(), dev_b.delay()).unwrap();
+
+    // Interleave accesses to both devices
+    a.write_reg(threshold(0x0111)).unwrap();
+    b.write_reg(threshold(0x0222)).unwrap();
+
+    check_thresholds(&dev_a, &dev_b);
+    assert_eq!(a.read_reg::<Threshold>().unwrap().level(), 0x0111);
+    assert_eq!(b.read_reg::<Threshold>().unwrap().level(), 0x0222);
+}
+
+#[test]
+fn two_drivers_on_shared_spi() {
+    let dev_a = SimulatedDevice::new();
+    let dev_b = SimulatedDevice::new().with_revision(0x21);
+    let bus = RefCell::new(SimBus::new(&[&dev_a, &dev_b]));
+
+    let iface = SpiInterface::new_shared(&bus, dev_a.cs()).unwrap();
+    let mut a = ExampleDriver::new(Config::default(), iface, dev_a.busy(), dev_a.reset(), dev_a.delay()).unwrap();
+
+    let iface = SpiInterface::new_shared(&bus, dev_b.cs()).unwrap();
+    let mut b = ExampleDriver::new(Config::default(), iface, dev_b.busy(), dev_b.reset(), dev_b.delay()).unwrap();
+
+    // Chip select picks the device
+    assert_eq!(a.device_info().revision, 0x10);
+    assert_eq!(b.device_info().revision, 0x21);
+
+    a.write_reg(threshold(0x0111)).unwrap();
+    b.write_reg(threshold(0x0222)).unwrap();
+
+    check_thresholds(&dev_a, &dev_b);
+}
+
+#[test]
+fn two_drivers_on_critical_section_bus() {
+    let dev_a = SimulatedDevice::new().with_address(0x01);
+    let dev_b = SimulatedDevice::new();
+    let i2c = Mutex::new(RefCell::new(SimBus::new(&[&dev_a])));
+    let spi = Mutex::new(RefCell::new(SimBus::new(&[&dev_b])));
+
+    let iface = I2cInterface::new_critical_section(&i2c, 0x01);
+    let mut a = ExampleDriver::new(Config::default(), iface, dev_a.busy(), dev_a.reset(), dev_a.delay()).unwrap();
+
+    let iface = SpiInterface::new_critical_section(&spi, dev_b.cs()).unwrap();
+    let mut b = ExampleDriver::new(Config::default(), iface, dev_b.busy(), dev_b.reset(), dev_b.delay()).unwrap();
+
+    a.write_reg(threshold(0x0111)).unwrap();
+    b.write_reg(threshold(0x0222)).unwrap();
+
+    check_thresholds(&dev_a, &dev_b);
+}
+
+#[test]
+fn shared_i2c_unknown_address() {
+    let dev = SimulatedDevice::new().with_address(0x01);
+    let bus = RefCell::new(SimBus::new(&[&dev]));
+
+    let iface = I2cInterface::new_shared(&bus, 0x03);
+    assert!(ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay()).is_err());
+}
@@ -0,0 +1,241 @@
+//! End-to-end driver tests against the simulated device
+
+use driver_example::registers::{Command, Control, Mode, Status, Threshold};
+use driver_example::registers::Register;
+use driver_example::sim::SimulatedDevice;
+use driver_example::{init_control, soft_reset_command, Config, DeviceInfo, Error, ExampleDriver, I2cInterface, Operation, Polarity, SpiInterface, CHIP_ID};
+
+#[test]
+fn init_over_i2c() {
+    let dev = SimulatedDevice::new();
+
+    let iface = I2cInterface::new(dev.i2c(), dev.address());
+    let mut d = ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+
+    assert_eq!(dev.resets(), 1);
+    assert_eq!(d.read_reg::<Control>().unwrap().mode(), Some(Mode::Normal));
+    assert!(!d.read_reg::<Status>().unwrap().busy());
+}
+
+#[test]
+fn init_over_spi() {
+    let dev = SimulatedDevice::new();
+
+    let iface = SpiInterface::new(dev.spi());
+    let mut d = ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+
+    assert_eq!(d.read_reg::<Control>().unwrap().mode(), Some(Mode::Normal));
+}
+
+#[test]
+fn wrong_i2c_address_fails() {
+    let dev = SimulatedDevice::new().with_address(0x42);
+
+    let iface = I2cInterface::new(dev.i2c(), 0x01);
+    let r = ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay());
+
+    // Fails reading the chip ID after reset
+    assert!(matches!(r, Err(Error::Interface { op: Operation::Read(0x0f), .. })));
+}
+
+#[test]
+fn device_info_reports_revision() {
+    let dev = SimulatedDevice::new().with_revision(0x23);
+
+    let iface = I2cInterface::new(dev.i2c(), dev.address());
+    let d = ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+
+    let info = d.device_info();
+    assert_eq!(info, DeviceInfo { chip_id: CHIP_ID, revision: 0x23 });
+    assert_eq!((info.major(), info.minor()), (2, 3));
+}
+
+#[test]
+fn unexpected_device_fails() {
+    let dev = SimulatedDevice::new().with_chip_id(0x42);
+
+    let iface = SpiInterface::new(dev.spi());
+    let r = ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay());
+
+    assert!(matches!(r, Err(Error::UnexpectedDevice { found: 0x42, expected: CHIP_ID })));
+}
+
+#[test]
+fn register_read_back() {
+    let dev = SimulatedDevice::new();
+
+    let iface = SpiInterface::new(dev.spi());
+    let mut d = ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+
+    assert_eq!(d.read_reg::<Threshold>().unwrap(), Threshold::RESET);
+
+    let mut t = Threshold::default();
+    t.set_level(0x0abc);
+    d.write_reg(t).unwrap();
+
+    assert_eq!(d.read_reg::<Threshold>().unwrap().level(), 0x0abc);
+    assert_eq!((dev.peek(0x02), dev.peek(0x03)), (0x0a, 0xbc));
+}
+
+#[test]
+fn read_only_registers_ignore_writes() {
+    let dev = SimulatedDevice::new();
+    dev.poke(Status::ADDRESS, 0x02);
+
+    // Write to the status register address directly, bypassing type checks
+    let mut i2c = dev.i2c();
+    embedded_hal::i2c::I2c::write(&mut i2c, dev.address(), &[Status::ADDRESS, 0xff]).unwrap();
+
+    assert_eq!(dev.peek(Status::ADDRESS), 0x02);
+}
+
+#[test]
+fn reset_restores_registers() {
+    let dev = SimulatedDevice::new();
+
+    let iface = I2cInterface::new(dev.i2c(), dev.address());
+    let mut d = ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+
+    d.write_reg(Threshold(0x0123)).unwrap();
+    let mut d = d.reset().unwrap();
+
+    assert_eq!(dev.resets(), 2);
+    assert_eq!(d.read_reg::<Threshold>().unwrap(), Threshold::RESET);
+    assert_eq!(d.read_reg::<Control>().unwrap(), Control::RESET);
+}
+
+#[test]
+fn sleep_and_wake() {
+    let dev = SimulatedDevice::new();
+
+    let iface = I2cInterface::new(dev.i2c(), dev.address());
+    let mut d = ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+
+    d.modify_reg::<Control, _>(|c| {
+        c.set_irq_enable(true);
+    }).unwrap();
+
+    let d = d.sleep().unwrap();
+    assert_eq!(Control(dev.peek(Control::ADDRESS)).mode(), Some(Mode::Sleep));
+
+    // Waking restores the mode without losing other settings
+    let mut d = d.wake().unwrap();
+    let c = d.read_reg::<Control>().unwrap();
+    assert_eq!(c.mode(), Some(Mode::Normal));
+    assert!(c.irq_enable());
+}
+
+#[test]
+fn raw_writes_cant_change_state() {
+    let dev = SimulatedDevice::new();
+
+    let iface = I2cInterface::new(dev.i2c(), dev.address());
+    let mut d = ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+
+    // Sleeping via the control register is refused
+    let e = d.modify_reg::<Control, _>(|c| {
+        c.set_mode(Mode::Sleep);
+    }).unwrap_err();
+    assert_eq!(e, Error::StateChange(Control::ADDRESS));
+    assert_eq!(e.operation(), Some(Operation::Write(Control::ADDRESS)));
+
+    // Including as part of a burst, or a soft reset
+    assert_eq!(d.write_regs(0x00, &[0x00, 0x00]), Err(Error::StateChange(Control::ADDRESS)));
+    assert_eq!(d.write_reg(soft_reset_command()), Err(Error::StateChange(Command::ADDRESS)));
+    assert_eq!(dev.resets(), 1);
+    assert_eq!(Control(dev.peek(Control::ADDRESS)).mode(), Some(Mode::Normal));
+
+    // Other control settings can still be changed
+    d.write_reg(*init_control().set_mode(Mode::Fast)).unwrap();
+    assert_eq!(Control(dev.peek(Control::ADDRESS)).mode(), Some(Mode::Fast));
+
+    // And before configuration anything goes
+    let mut d = d.reset().unwrap();
+    d.write_reg(Control::default()).unwrap();
+}
+
+#[test]
+fn configure_unconfigured() {
+    let dev = SimulatedDevice::new();
+
+    let iface = I2cInterface::new(dev.i2c(), dev.address());
+    let d = ExampleDriver::new_unconfigured(Config::default(), iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+
+    // Creating the driver doesn't touch the device
+    assert_eq!(dev.resets(), 0);
+
+    let mut d = d.configure().unwrap();
+    assert_eq!(dev.resets(), 1);
+    assert_eq!(d.read_reg::<Control>().unwrap().mode(), Some(Mode::Normal));
+}
+
+#[test]
+fn free_returns_peripherals() {
+    let dev = SimulatedDevice::new();
+
+    let iface = I2cInterface::new(dev.i2c(), dev.address());
+    let d = ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+    let d = d.sleep().unwrap();
+
+    // Peripherals can be reused for a new driver once freed
+    let (iface, busy, reset, delay) = d.free();
+    let mut d = ExampleDriver::new(Config::default(), iface, busy, reset, delay).unwrap();
+
+    assert_eq!(dev.resets(), 2);
+    assert_eq!(d.read_reg::<Control>().unwrap().mode(), Some(Mode::Normal));
+}
+
+#[test]
+fn slow_device_within_timeout() {
+    let config = Config { poll_ms: 10, ..Default::default() };
+    let dev = SimulatedDevice::new().with_ready_after_ms(config.reset_timeout_ms - 10);
+
+    let iface = I2cInterface::new(dev.i2c(), dev.address());
+    ExampleDriver::new(config, iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+}
+
+#[test]
+fn slow_device_times_out() {
+    let config = Config { poll_ms: 10, ..Default::default() };
+    let timeout = config.reset_timeout_ms;
+    let dev = SimulatedDevice::new().with_ready_after_ms(timeout + 50);
+
+    let iface = I2cInterface::new(dev.i2c(), dev.address());
+    let r = ExampleDriver::new(config, iface, dev.busy(), dev.reset(), dev.delay());
+
+    assert!(matches!(r, Err(Error::ResetTimeout)));
+
+    // Driver gives up shortly after the timeout, without waiting for the device
+    assert!(dev.now_ms() <= (timeout + 20) as u64);
+}
+
+#[test]
+fn longer_reset_timeout() {
+    let config = Config { poll_ms: 10, reset_timeout_ms: 500, ..Default::default() };
+    let dev = SimulatedDevice::new().with_ready_after_ms(300);
+
+    let iface = I2cInterface::new(dev.i2c(), dev.address());
+    ExampleDriver::new(config, iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+}
+
+#[test]
+fn line_polarities() {
+    let polarities = [Polarity::ActiveLow, Polarity::ActiveHigh];
+
+    for reset_polarity in polarities.iter().copied() {
+        for busy_polarity in polarities.iter().copied() {
+            let dev = SimulatedDevice::new()
+                .with_reset_polarity(reset_polarity)
+                .with_busy_polarity(busy_polarity);
+
+            let config = Config { poll_ms: 10, reset_polarity, busy_polarity, ..Default::default() };
+            let iface = I2cInterface::new(dev.i2c(), dev.address());
+            let mut d = ExampleDriver::new(config, iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+
+            // Device was reset and released, and we waited for it to be ready
+            assert_eq!(dev.resets(), 1, "reset {:?} busy {:?}", reset_polarity, busy_polarity);
+            assert!(dev.now_ms() >= 20, "reset {:?} busy {:?}", reset_polarity, busy_polarity);
+            assert_eq!(d.read_reg::<Control>().unwrap().mode(), Some(Mode::Normal));
+        }
+    }
+}
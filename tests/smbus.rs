@@ -0,0 +1,183 @@
+//! SMBus protocol tests
+
+use embedded_hal::i2c::ErrorKind;
+
+use driver_example::mock::{delay, i2c, pin, MockError};
+use driver_example::registers::{Control, Mode, Register, Threshold};
+use driver_example::sim::SimulatedDevice;
+use driver_example::smbus::{SmbusError, BLOCK_MAX};
+use driver_example::checksum::crc8_smbus;
+use driver_example::{Config, Error, ExampleDriver, Operation, RetryPolicy, RetryStats, Smbus};
+
+const ADDR: u8 = 0x0b;
+
+#[test]
+fn byte_and_word_data() {
+    let mut i2c = i2c::Mock::new(&[
+        i2c::Transaction::write(ADDR, &[0x01, 0x42]),
+        i2c::Transaction::write_read(ADDR, &[0x01], &[0x42]),
+        // Words are little-endian
+        i2c::Transaction::write(ADDR, &[0x09, 0x34, 0x12]),
+        i2c::Transaction::write_read(ADDR, &[0x09], &[0x34, 0x12]),
+        i2c::Transaction::write(ADDR, &[0x55]),
+        i2c::Transaction::read(ADDR, &[0x55]),
+    ]);
+
+    let mut s = Smbus::new(i2c.clone(), ADDR);
+    s.write_byte(0x01, 0x42).unwrap();
+    assert_eq!(s.read_byte(0x01).unwrap(), 0x42);
+    s.write_word(0x09, 0x1234).unwrap();
+    assert_eq!(s.read_word(0x09).unwrap(), 0x1234);
+    s.send_byte(0x55).unwrap();
+    assert_eq!(s.receive_byte().unwrap(), 0x55);
+
+    i2c.done();
+}
+
+#[test]
+fn process_call() {
+    let mut i2c = i2c::Mock::new(&[
+        i2c::Transaction::write_read(ADDR, &[0x20, 0xef, 0xbe], &[0x01, 0x00, 0xd2]),
+    ]);
+
+    let mut s = Smbus::new(i2c.clone(), ADDR).with_pec(true);
+    assert_eq!(s.process_call(0x20, 0xbeef).unwrap(), 0x0001);
+
+    i2c.done();
+}
+
+#[test]
+fn pec() {
+    let mut i2c = i2c::Mock::new(&[
+        i2c::Transaction::write(ADDR, &[0x01, 0x42, 0x03]),
+        i2c::Transaction::write_read(ADDR, &[0x09], &[0x34, 0x12, 0xb8]),
+        i2c::Transaction::read(ADDR, &[0x55, 0x90]),
+        // Corrupted data
+        i2c::Transaction::write_read(ADDR, &[0x09], &[0x35, 0x12, 0xb8]),
+    ]);
+
+    let mut s = Smbus::new(i2c.clone(), ADDR).with_pec(true);
+    s.write_byte(0x01, 0x42).unwrap();
+    assert_eq!(s.read_word(0x09).unwrap(), 0x1234);
+    assert_eq!(s.receive_byte().unwrap(), 0x55);
+
+    assert!(matches!(s.read_word(0x09), Err(SmbusError::Pec { actual: 0xb8, .. })));
+
+    i2c.done();
+}
+
+#[test]
+fn block_transfers() {
+    let mut i2c = i2c::Mock::new(&[
+        i2c::Transaction::write(ADDR, &[0x30, 0x03, 0x01, 0x02, 0x03, 0x4c]),
+        // Three byte block read into a four byte buffer, with padding after the PEC
+        i2c::Transaction::write_read(ADDR, &[0x31], &[0x03, 0x0a, 0x0b, 0x0c, 0x86, 0xff]),
+        // Corrupted PEC
+        i2c::Transaction::write_read(ADDR, &[0x31], &[0x03, 0x0a, 0x0b, 0x0c, 0x87, 0xff]),
+        // Block longer than the buffer
+        i2c::Transaction::write_read(ADDR, &[0x31], &[0x05, 0x00, 0x00, 0x00, 0x00, 0x00]),
+    ]);
+
+    let mut s = Smbus::new(i2c.clone(), ADDR).with_pec(true);
+    s.block_write(0x30, &[0x01, 0x02, 0x03]).unwrap();
+
+    let mut buff = [0u8; 4];
+    assert_eq!(s.block_read(0x31, &mut buff).unwrap(), 3);
+    assert_eq!(&buff[..3], &[0x0a, 0x0b, 0x0c]);
+
+    assert_eq!(s.block_read(0x31, &mut buff), Err(SmbusError::Pec { expected: 0x86, actual: 0x87 }));
+    assert_eq!(s.block_read(0x31, &mut buff), Err(SmbusError::BlockLength(5)));
+
+    // Blocks are limited to 32 bytes
+    assert_eq!(s.block_write(0x30, &[0u8; BLOCK_MAX + 1]), Err(SmbusError::BlockLength(BLOCK_MAX + 1)));
+
+    i2c.done();
+}
+
+#[test]
+fn bus_errors() {
+    let mut i2c = i2c::Mock::new(&[
+        i2c::Transaction::write(ADDR, &[0x01, 0x42]).with_error(ErrorKind::ArbitrationLoss),
+    ]);
+
+    let mut s = Smbus::new(i2c.clone(), ADDR);
+    assert_eq!(s.write_byte(0x01, 0x42), Err(SmbusError::I2c(MockError::I2c(ErrorKind::ArbitrationLoss))));
+
+    i2c.done();
+}
+
+#[test]
+fn word_registers_over_smbus() {
+    let busy = pin::Mock::new(&[pin::Transaction::get(pin::State::High)]);
+    let reset = pin::Mock::new(&[pin::Transaction::set(pin::State::Low), pin::Transaction::set(pin::State::High)]);
+    let delay = delay::Mock::new(&[delay::Transaction::Ms(10)]);
+
+    let mut i2c = i2c::Mock::new(&[
+        // Chip ID, revision and control are byte data
+        i2c::Transaction::write_read(ADDR, &[0x0f], &[0x5a]),
+        i2c::Transaction::write_read(ADDR, &[0x0e], &[0x10]),
+        i2c::Transaction::write(ADDR, &[0x01, 0x02]),
+        // Threshold words are sent least significant byte first
+        i2c::Transaction::write(ADDR, &[0x02, 0x23, 0x01]),
+        i2c::Transaction::write_read(ADDR, &[0x02], &[0x56, 0x04]),
+    ]);
+
+    let iface = Smbus::new(i2c.clone(), ADDR);
+    let mut d = ExampleDriver::new(Config::default(), iface, busy, reset, delay).unwrap();
+
+    d.write_reg(*Threshold::default().set_level(0x123)).unwrap();
+    assert_eq!(d.read_reg::<Threshold>().unwrap().level(), 0x456);
+
+    // Bursts longer than a word aren't supported
+    let mut buff = [0u8; 3];
+    assert_eq!(d.read_regs(Control::ADDRESS, &mut buff), Err(Error::Interface {
+        op: Operation::Read(Control::ADDRESS),
+        error: SmbusError::BlockLength(3),
+    }));
+
+    i2c.done();
+}
+
+/// PEC for a byte data read of `data` from `reg`
+fn read_pec(reg: u8, data: u8) -> u8 {
+    crc8_smbus(0, &[ADDR << 1, reg, (ADDR << 1) | 1, data])
+}
+
+#[test]
+fn pec_mismatch_is_retried() {
+    let busy = pin::Mock::new(&[pin::Transaction::get(pin::State::High)]);
+    let reset = pin::Mock::new(&[pin::Transaction::set(pin::State::Low), pin::Transaction::set(pin::State::High)]);
+    let delay = delay::Mock::new(&[delay::Transaction::Ms(10), delay::Transaction::Ms(1)]);
+
+    let mut i2c = i2c::Mock::new(&[
+        i2c::Transaction::write_read(ADDR, &[0x0f], &[0x5a, read_pec(0x0f, 0x5a)]),
+        i2c::Transaction::write_read(ADDR, &[0x0e], &[0x10, read_pec(0x0e, 0x10)]),
+        i2c::Transaction::write(ADDR, &[0x01, 0x02, crc8_smbus(0, &[ADDR << 1, 0x01, 0x02])]),
+        // Corrupted PEC, then retried
+        i2c::Transaction::write_read(ADDR, &[0x01], &[0x02, read_pec(0x01, 0x02) ^ 0x01]),
+        i2c::Transaction::write_read(ADDR, &[0x01], &[0x02, read_pec(0x01, 0x02)]),
+    ]);
+
+    let config = Config { retry: RetryPolicy { max_attempts: 2, ..Default::default() }, ..Default::default() };
+    let iface = Smbus::new(i2c.clone(), ADDR).with_pec(true);
+    let mut d = ExampleDriver::new(config, iface, busy, reset, delay).unwrap();
+
+    assert_eq!(d.read_reg::<Control>().unwrap().mode(), Some(Mode::Normal));
+    assert_eq!(d.retry_stats(), RetryStats { retries: 1, recovered: 1, failed: 0 });
+
+    i2c.done();
+}
+
+#[test]
+fn driver_over_smbus() {
+    let dev = SimulatedDevice::new();
+
+    let iface = Smbus::new(dev.i2c(), dev.address());
+    let mut d = ExampleDriver::new(Config::default(), iface, dev.busy(), dev.reset(), dev.delay()).unwrap();
+
+    assert_eq!(d.read_reg::<Control>().unwrap().mode(), Some(Mode::Normal));
+
+    // SMBus transfers are still available once the driver is released
+    let (mut iface, ..) = d.free();
+    assert_eq!(iface.read_byte(0x0f).unwrap(), 0x5a);
+}
@@ -0,0 +1,91 @@
+//! Timeout measurement tests
+
+use driver_example::mock::{delay, i2c, pin};
+use driver_example::sim::SimulatedDevice;
+use driver_example::{Clock, Config, Error, ExampleDriver, I2cInterface, NoPin, WithClock};
+
+const ADDR: u8 = 0x01;
+
+/// Clock advancing by a fixed step on each reading
+struct StepClock {
+    now: u32,
+    step: u32,
+}
+
+impl Clock for StepClock {
+    fn now_ms(&mut self) -> u32 {
+        let now = self.now;
+        self.now = self.now.wrapping_add(self.step);
+        now
+    }
+}
+
+#[test]
+fn bus_time_ignored_without_clock() {
+    // Each status poll takes 5ms on the bus, which delay counting misses
+    let dev = SimulatedDevice::new().with_ready_after_ms(1000).with_transaction_time_us(5_000);
+
+    let config = Config { poll_ms: 10, ..Default::default() };
+    let iface = I2cInterface::new(dev.i2c(), dev.address());
+    let r = ExampleDriver::new(config, iface, NoPin::new(), NoPin::new(), dev.delay());
+
+    assert!(matches!(r, Err(Error::ResetTimeout)));
+    assert!(dev.now_ms() > 150);
+}
+
+#[test]
+fn bus_time_included_with_clock() {
+    let dev = SimulatedDevice::new().with_ready_after_ms(1000).with_transaction_time_us(5_000);
+
+    let config = Config { poll_ms: 10, ..Default::default() };
+    let timeout = config.reset_timeout_ms as u64;
+    let iface = I2cInterface::new(dev.i2c(), dev.address());
+    let delay = WithClock::new(dev.delay(), dev.clock());
+    let r = ExampleDriver::new(config, iface, NoPin::new(), NoPin::new(), delay);
+
+    assert!(matches!(r, Err(Error::ResetTimeout)));
+
+    // Within a poll period (and bus transactions) of the timeout
+    assert!(dev.now_ms() > timeout);
+    assert!(dev.now_ms() <= timeout + 20);
+}
+
+#[test]
+fn clock_with_busy_pin() {
+    let dev = SimulatedDevice::new().with_transaction_time_us(1_000);
+
+    let config = Config { poll_ms: 5, ..Default::default() };
+    let iface = I2cInterface::new(dev.i2c(), dev.address());
+    let delay = WithClock::new(dev.delay(), dev.clock());
+    let d = ExampleDriver::new(config, iface, dev.busy(), dev.reset(), delay).unwrap();
+
+    // Delay and clock are returned on release
+    let (_iface, _busy, _reset, delay) = d.free();
+    let (_delay, mut clock) = delay.split();
+    assert!(clock.now_ms() >= 20);
+}
+
+#[test]
+fn clock_wraps() {
+    // Clock advances 30ms per reading, wrapping after the second poll
+    let clock = StepClock { now: u32::MAX - 50, step: 30 };
+
+    // So the fourth poll exceeds the 100ms timeout, despite only 40ms of delays
+    let mut busy = pin::Mock::new(&[pin::Transaction::get(pin::State::Low); 4]);
+    let mut reset = pin::Mock::new(&[
+        pin::Transaction::set(pin::State::Low),
+        pin::Transaction::set(pin::State::High),
+    ]);
+    let mut delay = delay::Mock::new(&[delay::Transaction::Ms(10)]);
+    delay.expect(&[delay::Transaction::Ms(10); 4]);
+
+    let config = Config { poll_ms: 10, ..Default::default() };
+    let iface = I2cInterface::new(i2c::Mock::new(&[]), ADDR);
+    let r = ExampleDriver::new(config, iface, busy.clone(), reset.clone(), WithClock::new(delay.clone(), clock));
+
+    assert!(matches!(r, Err(Error::ResetTimeout)));
+
+    busy.done();
+    reset.done();
+    delay.done();
+}